@@ -0,0 +1,51 @@
+use std::str::FromStr;
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use rpex::{HyperRectangle, Layout, Rpex};
+
+// A 64x64 grid on a 4096x4096 image, like when tiling images.
+fn grid() -> (Rpex<2>, HyperRectangle<2>) {
+    let dim_sum = "+".repeat(63);
+
+    (
+        Rpex::from_str(&format!("{dim_sum}:{dim_sum}")).unwrap(),
+        HyperRectangle::from_str("4096x4096").unwrap(),
+    )
+}
+
+fn evaluate(c: &mut Criterion) {
+    let (rpex, rectangle) = grid();
+
+    c.bench_function("evaluate", |b| {
+        b.iter(|| black_box(rpex.clone()).evaluate(black_box(rectangle)))
+    });
+}
+
+fn iter_cells(c: &mut Criterion) {
+    let (rpex, rectangle) = grid();
+    let layout = Layout::from(rpex.evaluate(rectangle).unwrap());
+
+    let mut group = c.benchmark_group("iter_cells");
+
+    group.bench_function("generic", |b| {
+        b.iter(|| {
+            black_box(&layout)
+                .iter_cells()
+                .map(|cell| cell.position[0])
+                .sum::<u32>()
+        })
+    });
+    group.bench_function("2d", |b| {
+        b.iter(|| {
+            black_box(&layout)
+                .iter_cells_2d()
+                .map(|cell| cell.position[0])
+                .sum::<u32>()
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, evaluate, iter_cells);
+criterion_main!(benches);
@@ -1,51 +1,160 @@
-use std::{fmt::Display, ops::Mul, str::FromStr};
+use std::{
+    collections::BTreeMap,
+    fmt::Display,
+    ops::{Div, Mul},
+    str::FromStr,
+};
 
-use fraction::Ratio;
+use fraction::{Integer, Ratio};
+use itertools::Itertools;
 use nom::{
-    character::complete::{char as char_parser, u32 as u32_parser},
+    bytes::complete::take_while1,
+    character::complete::char as char_parser,
     combinator::opt,
     multi::separated_list1,
+    sequence::{delimited, preceded, separated_pair, tuple},
     IResult,
 };
 use thiserror::Error;
 
 use crate::{
-    impl_from_str_for_nom_parsable, nom_parsable::NomParsable, ratio_ext::NotAnInteger,
-    ratio_ext::RatioExt,
+    impl_from_str_for_nom_parsable, nom_parsable::NomParsable,
+    parser_combinators::u32_with_separators, ratio_ext::NotAnInteger, ratio_ext::RatioExt,
 };
 
+/// Key-value pairs attached to an addend, like `ws=1` in `2@main[ws=1]`, for whatever consumes
+/// the partitions to read.
+pub type Annotations = BTreeMap<String, String>;
+
 #[derive(Debug, Default, Clone, PartialEq, Eq)]
 pub struct DimensionSum {
     addends: Vec<u32>,
+    /// Offset of every addend followed by the sum, cached when the sum is evaluated.
+    offsets: Vec<u32>,
+    labels: BTreeMap<usize, String>,
+    annotations: BTreeMap<usize, Annotations>,
 }
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub struct AddendWithOffset<'a> {
     pub addend: &'a u32,
     pub offset: u32,
+    pub label: Option<&'a str>,
+    pub annotations: Option<&'a Annotations>,
 }
 
 impl DimensionSum {
+    fn new(
+        addends: Vec<u32>,
+        labels: BTreeMap<usize, String>,
+        annotations: BTreeMap<usize, Annotations>,
+    ) -> DimensionSum {
+        let offsets = std::iter::once(0)
+            .chain(addends.iter().scan(0, |offset, addend| {
+                *offset += addend;
+                Some(*offset)
+            }))
+            .collect();
+
+        DimensionSum {
+            addends,
+            offsets,
+            labels,
+            annotations,
+        }
+    }
+
     pub fn addends(&self) -> &[u32] {
         &self.addends
     }
 
-    pub fn iter_with_offsets(&self) -> impl Iterator<Item = AddendWithOffset> {
-        self.addends.iter().scan(0, |offset, addend| {
-            let previous_offset = *offset;
-            *offset += addend;
-            Some(AddendWithOffset {
-                offset: previous_offset,
+    pub fn label(&self, index: usize) -> Option<&str> {
+        self.labels.get(&index).map(String::as_str)
+    }
+
+    pub fn annotations(&self, index: usize) -> Option<&Annotations> {
+        self.annotations.get(&index)
+    }
+
+    pub fn iter_with_offsets(&self) -> impl Iterator<Item = AddendWithOffset<'_>> + Clone {
+        self.addends
+            .iter()
+            .zip(&self.offsets)
+            .enumerate()
+            .map(|(index, (addend, offset))| AddendWithOffset {
                 addend,
+                offset: *offset,
+                label: self.label(index),
+                annotations: self.annotations(index),
             })
-        })
     }
 
-    fn sum(&self) -> u32 {
-        self.addends.iter().sum()
+    /// Replaces the addend at `index` with the addends of `inner`, after scaling this sum by
+    /// `factor` and `inner` by `inner_factor`, which must make them fit. The inner addends keep
+    /// the label of the replaced addend unless they have their own, and its annotations unless
+    /// they override them.
+    pub(crate) fn refine(
+        &self,
+        index: usize,
+        factor: u32,
+        inner: &DimensionSum,
+        inner_factor: u32,
+    ) -> DimensionSum {
+        let mut addends = vec![];
+        let mut labels = BTreeMap::new();
+        let mut annotations = BTreeMap::new();
+
+        for (i, addend) in self.addends.iter().enumerate() {
+            if i != index {
+                if let Some(label) = self.labels.get(&i) {
+                    labels.insert(addends.len(), label.clone());
+                }
+                if let Some(outer) = self.annotations.get(&i) {
+                    annotations.insert(addends.len(), outer.clone());
+                }
+                addends.push(addend * factor);
+                continue;
+            }
+
+            for (j, addend) in inner.addends.iter().enumerate() {
+                if let Some(label) = inner.labels.get(&j).or(self.labels.get(&i)) {
+                    labels.insert(addends.len(), label.clone());
+                }
+
+                let mut merged = self.annotations.get(&i).cloned().unwrap_or_default();
+                merged.extend(inner.annotations.get(&j).cloned().unwrap_or_default());
+                if !merged.is_empty() {
+                    annotations.insert(addends.len(), merged);
+                }
+
+                addends.push(addend * inner_factor);
+            }
+        }
+
+        debug_assert_eq!(
+            addends.iter().sum::<u32>(),
+            self.sum() * factor,
+            "refined sum should fit the original"
+        );
+
+        DimensionSum::new(addends, labels, annotations)
     }
 
-    fn infer_scale(&self, length: u32) -> u32 {
+    /// Where every addend starts, followed by where the last one ends.
+    pub fn boundaries(&self) -> &[u32] {
+        &self.offsets
+    }
+
+    /// Index of the addend covering `offset`, if it is within the sum.
+    pub fn addend_at(&self, offset: u32) -> Option<usize> {
+        (offset < self.sum()).then(|| self.offsets.partition_point(|&start| start <= offset) - 1)
+    }
+
+    pub fn sum(&self) -> u32 {
+        self.offsets.last().copied().unwrap_or_default()
+    }
+
+    pub fn infer_scale(&self, length: u32) -> u32 {
         length / self.sum()
     }
 }
@@ -53,6 +162,23 @@ impl DimensionSum {
 #[derive(Clone, Debug, Default, PartialEq, Eq)]
 pub struct IndeterminateDimensionSum {
     pub addends: Vec<Option<u32>>,
+    pub labels: BTreeMap<usize, String>,
+    pub annotations: BTreeMap<usize, Annotations>,
+}
+
+/// How [`IndeterminateDimensionSum::evaluate`] sizes the addends without a value.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum UnknownStrategy {
+    /// Split whatever the known addends leave over equally between the unknowns.
+    #[default]
+    EqualSplit,
+    /// Weigh each unknown like the mean of the known addends, and scale every addend to fill the
+    /// total, so that known addends are only ratios of each other. Without known addends this is
+    /// the same as `EqualSplit`.
+    ProportionalToKnowns,
+    /// Split whatever the known addends leave over equally, rounded down, and give the remainder
+    /// to the last unknown, so that the total never has to divide.
+    FillLast,
 }
 
 #[derive(Error, Debug)]
@@ -61,20 +187,221 @@ pub enum DimensionSumEvaluationError {
     DoesNotDivide(#[from] NotAnInteger<u32>),
     #[error("provided total {0} does not equal actual total {1}")]
     UnequalTotal(u32, u32),
+    #[error("known addends sum to {1}, more than the total {0}")]
+    KnownsExceedTotal(u32, u32),
+    #[error("addends are too large to evaluate")]
+    Overflow,
+}
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum EditError {
+    #[error("expression has no dimension {0}")]
+    NoDimension(usize),
+    #[error("dimension has no addend {0}")]
+    NoAddend(usize),
+    #[error("unable to remove the only addend of a dimension")]
+    LastAddend,
+    #[error("unable to cut at {0} ratio units, which isn't strictly inside the dimension")]
+    CutOutOfRange(u32),
+    #[error("unable to cut at {0} ratio units, which lies past an unknown addend")]
+    CutPastUnknown(u32),
+}
+
+// Labels and annotations are keyed by the index of their addend, so they are moved along with it.
+fn shift_keys<V>(map: &mut BTreeMap<usize, V>, shift: &impl Fn(usize) -> Option<usize>) {
+    *map = std::mem::take(map)
+        .into_iter()
+        .filter_map(|(index, value)| Some((shift(index)?, value)))
+        .collect();
 }
 
 impl IndeterminateDimensionSum {
-    fn count_unknowns(&self) -> usize {
+    fn shift_labels(&mut self, shift: impl Fn(usize) -> Option<usize>) {
+        shift_keys(&mut self.labels, &shift);
+        shift_keys(&mut self.annotations, &shift);
+    }
+
+    /// Inserts `addend` before the addend at `index`, or at the end if `index` is the number of
+    /// addends.
+    pub fn insert_addend(
+        mut self,
+        index: usize,
+        addend: Option<u32>,
+    ) -> Result<IndeterminateDimensionSum, EditError> {
+        if index > self.addends.len() {
+            return Err(EditError::NoAddend(index));
+        }
+
+        self.addends.insert(index, addend);
+        self.shift_labels(|i| Some(if i >= index { i + 1 } else { i }));
+
+        Ok(self)
+    }
+
+    pub fn remove_addend(mut self, index: usize) -> Result<IndeterminateDimensionSum, EditError> {
+        if index >= self.addends.len() {
+            return Err(EditError::NoAddend(index));
+        }
+        if self.addends.len() == 1 {
+            return Err(EditError::LastAddend);
+        }
+
+        self.addends.remove(index);
+        self.shift_labels(|i| match i.cmp(&index) {
+            std::cmp::Ordering::Less => Some(i),
+            std::cmp::Ordering::Equal => None,
+            std::cmp::Ordering::Greater => Some(i - 1),
+        });
+
+        Ok(self)
+    }
+
+    /// Replaces the addend at `index` with two addends that take up its space, the first keeping
+    /// its label and annotations. An odd addend can't be split evenly, so the second half gets
+    /// the extra unit.
+    pub fn split_addend(mut self, index: usize) -> Result<IndeterminateDimensionSum, EditError> {
+        let addend = *self.addends.get(index).ok_or(EditError::NoAddend(index))?;
+
+        let halves = addend.map(|addend| (addend / 2, addend - addend / 2));
+
+        self.addends[index] = halves.map(|(first, _)| first);
+
+        self.insert_addend(index + 1, halves.map(|(_, second)| second))
+    }
+
+    /// Replaces the addends at `index` and `index + 1` with their sum, which is unknown if either
+    /// of them is. The merged addend keeps the first label of the two, and the annotations of
+    /// both, those of the first winning when both have the same key.
+    pub fn merge_addends(mut self, index: usize) -> Result<IndeterminateDimensionSum, EditError> {
+        if index + 1 >= self.addends.len() {
+            return Err(EditError::NoAddend(index + 1));
+        }
+
+        let second = self.addends.remove(index + 1);
+        self.addends[index] = self.addends[index].zip(second).map(|(a, b)| a + b);
+
+        if let Some(label) = self.labels.remove(&(index + 1)) {
+            self.labels.entry(index).or_insert(label);
+        }
+        if let Some(annotations) = self.annotations.remove(&(index + 1)) {
+            let merged = self.annotations.entry(index).or_default();
+
+            for (key, value) in annotations {
+                merged.entry(key).or_insert(value);
+            }
+        }
+        self.shift_labels(|i| Some(if i > index + 1 { i - 1 } else { i }));
+
+        Ok(self)
+    }
+
+    /// Cuts the sum at `at` ratio units into the sums before and after it. An addend that the cut
+    /// falls inside of is split in two, both keeping its label and annotations. Only the addends
+    /// before the cut need to be known, since those after it don't change its position.
+    pub fn split_at(
+        mut self,
+        at: u32,
+    ) -> Result<(IndeterminateDimensionSum, IndeterminateDimensionSum), EditError> {
+        let mut offset = 0;
+        let mut index = 0;
+
+        while offset < at {
+            match self.addends.get(index) {
+                Some(Some(addend)) if offset + addend > at => {
+                    let addend = *addend;
+
+                    self.addends[index] = Some(at - offset);
+                    self = self.insert_addend(index + 1, Some(offset + addend - at))?;
+
+                    if let Some(label) = self.labels.get(&index).cloned() {
+                        self.labels.insert(index + 1, label);
+                    }
+                    if let Some(annotations) = self.annotations.get(&index).cloned() {
+                        self.annotations.insert(index + 1, annotations);
+                    }
+
+                    offset = at;
+                }
+                Some(Some(addend)) => offset += addend,
+                Some(None) => return Err(EditError::CutPastUnknown(at)),
+                None => return Err(EditError::CutOutOfRange(at)),
+            }
+
+            index += 1;
+        }
+
+        if index == 0 || index == self.addends.len() {
+            return Err(EditError::CutOutOfRange(at));
+        }
+
+        let mut second = IndeterminateDimensionSum {
+            addends: self.addends.split_off(index),
+            labels: self.labels.split_off(&index),
+            annotations: self.annotations.split_off(&index),
+        };
+        second.shift_labels(|i| Some(i - index));
+
+        Ok((self, second))
+    }
+
+    pub fn count_unknowns(&self) -> usize {
         self.addends.iter().filter(|o| o.is_none()).count()
     }
 
-    fn sum_knowns(&self) -> u32 {
-        self.addends.iter().flatten().sum()
+    fn sum_knowns(&self) -> Result<u32, DimensionSumEvaluationError> {
+        self.addends
+            .iter()
+            .flatten()
+            .try_fold(0u32, |sum, addend| sum.checked_add(*addend))
+            .ok_or(DimensionSumEvaluationError::Overflow)
+    }
+
+    /// Like multiplying by `rhs`, but fails instead of overflowing.
+    pub fn checked_mul(
+        mut self,
+        rhs: u32,
+    ) -> Result<IndeterminateDimensionSum, DimensionSumEvaluationError> {
+        for addend in self.addends.iter_mut().flatten() {
+            *addend = addend
+                .checked_mul(rhs)
+                .ok_or(DimensionSumEvaluationError::Overflow)?;
+        }
+
+        Ok(self)
+    }
+
+    /// Relative sizes of the addends when there is no total to fill, sizing unknowns like
+    /// [`UnknownStrategy::ProportionalToKnowns`].
+    pub fn weights(&self) -> Vec<u32> {
+        let known_count = (self.addends.len() - self.count_unknowns()) as u32;
+        // Weights are only relative, so ones too large to sum are merely skewed.
+        let sum_knowns = self.sum_knowns().unwrap_or(u32::MAX);
+
+        if sum_knowns == 0 {
+            return vec![1; self.addends.len()];
+        }
+
+        self.addends
+            .iter()
+            .map(|maybe_addend| {
+                maybe_addend.map_or(sum_knowns, |addend| addend.saturating_mul(known_count))
+            })
+            .collect()
     }
 
-    pub fn infer_scale(&self, length: u32) -> Result<Option<u32>, NotAnInteger<u32>> {
+    pub fn gcd_knowns(&self) -> u32 {
+        self.addends
+            .iter()
+            .flatten()
+            .fold(0, |gcd, addend| gcd.gcd(addend))
+    }
+
+    pub fn infer_scale(&self, length: u32) -> Result<Option<u32>, DimensionSumEvaluationError> {
         if self.count_unknowns() == 0 {
-            let scale = Ratio::new(length, self.sum_knowns()).try_to_integer()?;
+            let scale = Ratio::new(length, self.sum_knowns()?).try_to_integer()?;
+
+            #[cfg(feature = "tracing")]
+            tracing::trace!(dim_sum = %self, length, scale, "inferred scale of dimension");
 
             Ok(Some(scale))
         } else {
@@ -82,57 +409,194 @@ impl IndeterminateDimensionSum {
         }
     }
 
-    pub fn evaluate(self, total: u32) -> Result<DimensionSum, DimensionSumEvaluationError> {
-        let unknown_count = self.count_unknowns();
-
-        let addends = if unknown_count != 0 {
-            let total_unknown = total - self.sum_knowns();
+    pub fn evaluate(
+        self,
+        total: u32,
+        strategy: UnknownStrategy,
+    ) -> Result<DimensionSum, DimensionSumEvaluationError> {
+        let unknown_count = self.count_unknowns() as u32;
+        let known_count = self.addends.len() as u32 - unknown_count;
+        let sum_knowns = self.sum_knowns()?;
+
+        // Addends are consumed rather than borrowed, so that collecting them can reuse their
+        // buffer instead of allocating.
+        let addends = match strategy {
+            _ if unknown_count == 0 => {
+                if sum_knowns != total {
+                    return Err(DimensionSumEvaluationError::UnequalTotal(total, sum_knowns));
+                }
+
+                self.addends
+                    .into_iter()
+                    .map(Option::unwrap_or_default)
+                    .collect()
+            }
+            UnknownStrategy::ProportionalToKnowns if sum_knowns != 0 => {
+                // Scaling by the number of knowns keeps the weight of an unknown, their mean,
+                // an integer.
+                let weight_total = sum_knowns
+                    .checked_mul(known_count + unknown_count)
+                    .ok_or(DimensionSumEvaluationError::Overflow)?;
+                let factor = Ratio::new(total, weight_total).try_to_integer()?;
+
+                // Every weight is at most the total of the weights, so none overflows once that
+                // total fits.
+                self.addends
+                    .into_iter()
+                    .map(|maybe_addend| {
+                        maybe_addend.map_or(sum_knowns, |addend| addend * known_count) * factor
+                    })
+                    .collect()
+            }
+            UnknownStrategy::FillLast => {
+                let total_unknown = total.checked_sub(sum_knowns).ok_or(
+                    DimensionSumEvaluationError::KnownsExceedTotal(total, sum_knowns),
+                )?;
+                let solution = total_unknown / unknown_count;
+                let last_unknown = self
+                    .addends
+                    .iter()
+                    .rposition(Option::is_none)
+                    .expect("there is an unknown");
+
+                self.addends
+                    .into_iter()
+                    .enumerate()
+                    .map(|(index, maybe_addend)| match maybe_addend {
+                        Some(addend) => addend,
+                        None if index == last_unknown => {
+                            total_unknown - solution * (unknown_count - 1)
+                        }
+                        None => solution,
+                    })
+                    .collect()
+            }
+            UnknownStrategy::EqualSplit | UnknownStrategy::ProportionalToKnowns => {
+                let total_unknown = total.checked_sub(sum_knowns).ok_or(
+                    DimensionSumEvaluationError::KnownsExceedTotal(total, sum_knowns),
+                )?;
 
-            let solution =
-                Ratio::new(total_unknown, self.count_unknowns() as u32).try_to_integer()?;
+                let solution = Ratio::new(total_unknown, unknown_count).try_to_integer()?;
 
-            self.addends
-                .into_iter()
-                .map(|maybe_addend| maybe_addend.unwrap_or(solution))
-                .collect()
-        } else {
-            let actual_total = self.sum_knowns();
-            if actual_total != total {
-                return Err(DimensionSumEvaluationError::UnequalTotal(
-                    total,
-                    actual_total,
-                ));
+                self.addends
+                    .into_iter()
+                    .map(|maybe_addend| maybe_addend.unwrap_or(solution))
+                    .collect()
             }
-
-            self.addends.into_iter().flatten().collect()
         };
 
-        Ok(DimensionSum { addends })
+        #[cfg(feature = "tracing")]
+        tracing::debug!(total, ?strategy, ?addends, "solved unknowns");
+
+        Ok(DimensionSum::new(addends, self.labels, self.annotations))
     }
 }
 
 impl Mul<u32> for IndeterminateDimensionSum {
     type Output = IndeterminateDimensionSum;
 
-    fn mul(self, rhs: u32) -> Self::Output {
-        let addends = self
-            .addends
+    fn mul(mut self, rhs: u32) -> Self::Output {
+        for addend in self.addends.iter_mut().flatten() {
+            *addend *= rhs;
+        }
+
+        self
+    }
+}
+
+impl Div<u32> for IndeterminateDimensionSum {
+    type Output = IndeterminateDimensionSum;
+
+    fn div(mut self, rhs: u32) -> Self::Output {
+        for addend in self.addends.iter_mut().flatten() {
+            *addend /= rhs;
+        }
+
+        self
+    }
+}
+
+/// An addend as written, before being collected into a dimension sum.
+pub(crate) type ParsedAddend<'a> = (Option<u32>, Option<&'a str>, Option<Annotations>);
+
+fn annotations_parser(input: &str) -> IResult<&str, Annotations> {
+    let key = take_while1(|c: char| c.is_ascii_alphanumeric() || c == '_');
+    let value = take_while1(|c: char| c.is_ascii_alphanumeric() || "_.-".contains(c));
+
+    let (input, pairs) = delimited(
+        char_parser('['),
+        separated_list1(
+            char_parser(','),
+            separated_pair(key, char_parser('='), value),
+        ),
+        char_parser(']'),
+    )(input)?;
+
+    Ok((
+        input,
+        pairs
             .into_iter()
-            .map(|maybe_addend| maybe_addend.map(|addend| addend * rhs))
-            .collect();
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect(),
+    ))
+}
 
-        IndeterminateDimensionSum { addends }
+/// Parses one addend, which may be empty for an unknown, with an optional label and optional
+/// annotations.
+pub(crate) fn addend_parser(input: &str) -> IResult<&str, ParsedAddend<'_>> {
+    let label = take_while1(|c: char| c.is_ascii_alphanumeric() || c == '_');
+
+    tuple((
+        opt(u32_with_separators),
+        opt(preceded(char_parser('@'), label)),
+        opt(annotations_parser),
+    ))(input)
+}
+
+impl<'a> FromIterator<ParsedAddend<'a>> for IndeterminateDimensionSum {
+    fn from_iter<I: IntoIterator<Item = ParsedAddend<'a>>>(iter: I) -> Self {
+        let mut dim_sum = IndeterminateDimensionSum::default();
+
+        for (index, (addend, label, annotations)) in iter.into_iter().enumerate() {
+            dim_sum.addends.push(addend);
+
+            if let Some(label) = label {
+                dim_sum.labels.insert(index, label.to_string());
+            }
+            if let Some(annotations) = annotations {
+                dim_sum.annotations.insert(index, annotations);
+            }
+        }
+
+        dim_sum
     }
 }
 
 impl NomParsable for IndeterminateDimensionSum {
     fn parser(input: &str) -> IResult<&str, IndeterminateDimensionSum> {
-        let (input, values) = separated_list1(char_parser('+'), opt(u32_parser))(input)?;
+        let (input, values) = separated_list1(char_parser('+'), addend_parser)(input)?;
 
-        Ok((input, IndeterminateDimensionSum { addends: values }))
+        Ok((input, values.into_iter().collect()))
     }
 }
 
+// Writes an addend back the way it is parsed, e.g. `2@main[dpi=2,ws=1]`.
+fn format_addend(addend: String, label: Option<&str>, annotations: Option<&Annotations>) -> String {
+    let label = label.map(|label| format!("@{label}")).unwrap_or_default();
+    let annotations = annotations
+        .map(|annotations| {
+            let pairs = annotations
+                .iter()
+                .map(|(key, value)| format!("{key}={value}"))
+                .join(",");
+
+            format!("[{pairs}]")
+        })
+        .unwrap_or_default();
+
+    format!("{addend}{label}{annotations}")
+}
+
 impl FromStr for IndeterminateDimensionSum {
     impl_from_str_for_nom_parsable!();
 }
@@ -142,9 +606,18 @@ impl Display for IndeterminateDimensionSum {
         let string_representations = self
             .addends
             .iter()
-            .map(|addend| match addend {
-                Some(a) => a.to_string(),
-                None => "".to_string(),
+            .enumerate()
+            .map(|(index, addend)| {
+                let addend = match addend {
+                    Some(a) => a.to_string(),
+                    None => "".to_string(),
+                };
+
+                format_addend(
+                    addend,
+                    self.labels.get(&index).map(String::as_str),
+                    self.annotations.get(&index),
+                )
             })
             .collect::<Vec<_>>();
 
@@ -154,6 +627,24 @@ impl Display for IndeterminateDimensionSum {
     }
 }
 
+impl Display for DimensionSum {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let joined = self
+            .iter_with_offsets()
+            .map(
+                |AddendWithOffset {
+                     addend,
+                     label,
+                     annotations,
+                     ..
+                 }| format_addend(addend.to_string(), label, annotations),
+            )
+            .join("+");
+
+        f.write_str(joined.as_str())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -163,19 +654,22 @@ mod tests {
         assert_eq!(
             IndeterminateDimensionSum::from_str("").unwrap(),
             IndeterminateDimensionSum {
-                addends: vec![None]
+                addends: vec![None],
+                ..Default::default()
             }
         );
         assert_eq!(
             IndeterminateDimensionSum::from_str("+").unwrap(),
             IndeterminateDimensionSum {
-                addends: vec![None, None]
+                addends: vec![None, None],
+                ..Default::default()
             }
         );
         assert_eq!(
             IndeterminateDimensionSum::from_str("+++").unwrap(),
             IndeterminateDimensionSum {
-                addends: vec![None, None, None, None]
+                addends: vec![None, None, None, None],
+                ..Default::default()
             }
         );
     }
@@ -185,19 +679,22 @@ mod tests {
         assert_eq!(
             IndeterminateDimensionSum::from_str("1+").unwrap(),
             IndeterminateDimensionSum {
-                addends: vec![Some(1), None]
+                addends: vec![Some(1), None],
+                ..Default::default()
             }
         );
         assert_eq!(
             IndeterminateDimensionSum::from_str("+2").unwrap(),
             IndeterminateDimensionSum {
-                addends: vec![None, Some(2)]
+                addends: vec![None, Some(2)],
+                ..Default::default()
             }
         );
         assert_eq!(
             IndeterminateDimensionSum::from_str("1+2++45+56").unwrap(),
             IndeterminateDimensionSum {
-                addends: vec![Some(1), Some(2), None, Some(45), Some(56)]
+                addends: vec![Some(1), Some(2), None, Some(45), Some(56)],
+                ..Default::default()
             }
         );
     }
@@ -207,19 +704,29 @@ mod tests {
         assert_eq!(
             IndeterminateDimensionSum::from_str("12").unwrap(),
             IndeterminateDimensionSum {
-                addends: vec![Some(12)]
+                addends: vec![Some(12)],
+                ..Default::default()
             }
         );
         assert_eq!(
             IndeterminateDimensionSum::from_str("1+23").unwrap(),
             IndeterminateDimensionSum {
-                addends: vec![Some(1), Some(23)]
+                addends: vec![Some(1), Some(23)],
+                ..Default::default()
             }
         );
         assert_eq!(
             IndeterminateDimensionSum::from_str("12+3+4+56").unwrap(),
             IndeterminateDimensionSum {
-                addends: vec![Some(12), Some(3), Some(4), Some(56)]
+                addends: vec![Some(12), Some(3), Some(4), Some(56)],
+                ..Default::default()
+            }
+        );
+        assert_eq!(
+            IndeterminateDimensionSum::from_str("3+1_000").unwrap(),
+            IndeterminateDimensionSum {
+                addends: vec![Some(3), Some(1000)],
+                ..Default::default()
             }
         );
     }
@@ -235,6 +742,7 @@ mod tests {
     fn we_cannot_parse_dim_sum_with_bad_numbers() {
         assert!(IndeterminateDimensionSum::from_str("1+y").is_err());
         assert!(IndeterminateDimensionSum::from_str("x+2").is_err());
+        assert!(IndeterminateDimensionSum::from_str("_1+2").is_err());
     }
 
     #[test]
@@ -242,4 +750,126 @@ mod tests {
         assert!(IndeterminateDimensionSum::from_str("1-1").is_err());
         assert!(IndeterminateDimensionSum::from_str("1+-2").is_err());
     }
+
+    #[test]
+    fn we_can_parse_dim_sum_with_labels() {
+        let dim_sum = IndeterminateDimensionSum::from_str("2@main+@side_1+1").unwrap();
+
+        assert_eq!(dim_sum.addends, vec![Some(2), None, Some(1)]);
+        assert_eq!(
+            dim_sum.labels,
+            BTreeMap::from([(0, "main".to_string()), (1, "side_1".to_string())])
+        );
+        assert_eq!(dim_sum.to_string(), "2@main+@side_1+1");
+        assert_eq!(
+            dim_sum
+                .evaluate(6, UnknownStrategy::EqualSplit)
+                .unwrap()
+                .to_string(),
+            "2@main+3@side_1+1"
+        );
+    }
+
+    #[test]
+    fn we_cannot_parse_dim_sum_with_bad_labels() {
+        assert!(IndeterminateDimensionSum::from_str("1@+1").is_err());
+        assert!(IndeterminateDimensionSum::from_str("1@a-b").is_err());
+        assert!(IndeterminateDimensionSum::from_str("1@a@b").is_err());
+    }
+
+    #[test]
+    fn we_can_parse_dim_sum_with_annotations() {
+        let dim_sum = IndeterminateDimensionSum::from_str("2@main[ws=1,dpi=1.5]+[ws=9]+1").unwrap();
+
+        assert_eq!(dim_sum.addends, vec![Some(2), None, Some(1)]);
+        assert_eq!(
+            dim_sum.annotations,
+            BTreeMap::from([
+                (
+                    0,
+                    Annotations::from([
+                        ("dpi".to_string(), "1.5".to_string()),
+                        ("ws".to_string(), "1".to_string())
+                    ])
+                ),
+                (1, Annotations::from([("ws".to_string(), "9".to_string())])),
+            ])
+        );
+        assert_eq!(dim_sum.to_string(), "2@main[dpi=1.5,ws=1]+[ws=9]+1");
+        assert_eq!(
+            dim_sum
+                .evaluate(6, UnknownStrategy::EqualSplit)
+                .unwrap()
+                .to_string(),
+            "2@main[dpi=1.5,ws=1]+3[ws=9]+1"
+        );
+    }
+
+    #[test]
+    fn we_cannot_parse_dim_sum_with_bad_annotations() {
+        assert!(IndeterminateDimensionSum::from_str("1[]").is_err());
+        assert!(IndeterminateDimensionSum::from_str("1[ws]").is_err());
+        assert!(IndeterminateDimensionSum::from_str("1[ws=1").is_err());
+        assert!(IndeterminateDimensionSum::from_str("1[ws=1]@main").is_err());
+        assert!(IndeterminateDimensionSum::from_str("1[ws=a b]").is_err());
+    }
+
+    #[test]
+    fn we_can_evaluate_dim_sum_with_unknown_strategies() {
+        let evaluate = |dim_sum: &str, total, strategy| {
+            IndeterminateDimensionSum::from_str(dim_sum)
+                .unwrap()
+                .evaluate(total, strategy)
+                .map(|dim_sum| dim_sum.to_string())
+        };
+
+        assert_eq!(
+            evaluate("2++1", 9, UnknownStrategy::EqualSplit).unwrap(),
+            "2+6+1"
+        );
+        assert!(evaluate("2++", 11, UnknownStrategy::EqualSplit).is_err());
+        assert_eq!(
+            evaluate("2++1", 18, UnknownStrategy::ProportionalToKnowns).unwrap(),
+            "8+6+4"
+        );
+        assert!(evaluate("2++1", 10, UnknownStrategy::ProportionalToKnowns).is_err());
+        assert_eq!(
+            evaluate("+", 6, UnknownStrategy::ProportionalToKnowns).unwrap(),
+            "3+3"
+        );
+        assert_eq!(
+            evaluate("2++", 11, UnknownStrategy::FillLast).unwrap(),
+            "2+4+5"
+        );
+        assert!(matches!(
+            evaluate("1+1", 3, UnknownStrategy::FillLast),
+            Err(DimensionSumEvaluationError::UnequalTotal(3, 2))
+        ));
+    }
+
+    #[test]
+    fn we_cannot_evaluate_dim_sum_with_knowns_past_the_total() {
+        for strategy in [UnknownStrategy::EqualSplit, UnknownStrategy::FillLast] {
+            assert!(matches!(
+                IndeterminateDimensionSum::from_str("4+")
+                    .unwrap()
+                    .evaluate(3, strategy),
+                Err(DimensionSumEvaluationError::KnownsExceedTotal(3, 4))
+            ));
+        }
+    }
+
+    #[test]
+    fn we_can_look_up_addends_by_offset() {
+        let dim_sum = IndeterminateDimensionSum::from_str("2++1")
+            .unwrap()
+            .evaluate(9, UnknownStrategy::EqualSplit)
+            .unwrap();
+
+        assert_eq!(dim_sum.boundaries(), [0, 2, 8, 9]);
+        assert_eq!(
+            [0, 1, 2, 7, 8, 9].map(|offset| dim_sum.addend_at(offset)),
+            [Some(0), Some(0), Some(1), Some(1), Some(2), None]
+        );
+    }
 }
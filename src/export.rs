@@ -0,0 +1,688 @@
+use std::{fmt::Display, str::FromStr};
+
+use fraction::Integer;
+use serde_json::json;
+use thiserror::Error;
+
+use crate::layout::{Cell, Layout};
+use crate::sums_in_ratio::Partition;
+use crate::units::RatioUnits;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ExportFormat {
+    Json,
+    Tsv,
+    Shell,
+    XrandrGeometry,
+    Zellij,
+    Awesome,
+    Qtile,
+    I3,
+    Html,
+    GeoJson,
+    CutList,
+}
+
+impl ExportFormat {
+    pub const ALL: [ExportFormat; 11] = [
+        ExportFormat::Json,
+        ExportFormat::Tsv,
+        ExportFormat::Shell,
+        ExportFormat::XrandrGeometry,
+        ExportFormat::Zellij,
+        ExportFormat::Awesome,
+        ExportFormat::Qtile,
+        ExportFormat::I3,
+        ExportFormat::Html,
+        ExportFormat::GeoJson,
+        ExportFormat::CutList,
+    ];
+
+    fn name(&self) -> &'static str {
+        match self {
+            ExportFormat::Json => "json",
+            ExportFormat::Tsv => "tsv",
+            ExportFormat::Shell => "shell",
+            ExportFormat::XrandrGeometry => "xrandr-geometry",
+            ExportFormat::Zellij => "zellij",
+            ExportFormat::Awesome => "awesome",
+            ExportFormat::Qtile => "qtile",
+            ExportFormat::I3 => "i3",
+            ExportFormat::Html => "html",
+            ExportFormat::GeoJson => "geojson",
+            ExportFormat::CutList => "cut-list",
+        }
+    }
+}
+
+/// Maps pixel positions to coordinates, like the geotransform of a north-up raster: a pixel at
+/// `[x, y]` is mapped to `origin + [x, y] * pixel_size`.
+///
+/// The default maps pixels to themselves. Rasters usually have a negative vertical pixel size,
+/// since their rows run from north to south.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct CoordinateMapping {
+    pub origin: [f64; 2],
+    pub pixel_size: [f64; 2],
+}
+
+impl Default for CoordinateMapping {
+    fn default() -> Self {
+        CoordinateMapping {
+            origin: [0.0, 0.0],
+            pixel_size: [1.0, 1.0],
+        }
+    }
+}
+
+impl CoordinateMapping {
+    /// Maps the layout onto `[min_x, min_y, max_x, max_y]`, with the first row of pixels at
+    /// `max_y`.
+    pub fn from_extent(extent: [f64; 4], layout: &Layout<2>) -> CoordinateMapping {
+        let [min_x, min_y, max_x, max_y] = extent;
+        let [width, height] = layout
+            .sums_in_ratio()
+            .totals()
+            .map(|total| (total * layout.scale()) as f64);
+
+        CoordinateMapping {
+            origin: [min_x, max_y],
+            pixel_size: [(max_x - min_x) / width, (min_y - max_y) / height],
+        }
+    }
+
+    pub fn map(&self, [x, y]: [u32; 2]) -> [f64; 2] {
+        [
+            self.origin[0] + x as f64 * self.pixel_size[0],
+            self.origin[1] + y as f64 * self.pixel_size[1],
+        ]
+    }
+}
+
+#[derive(Error, Debug)]
+#[error("unknown export format {0}")]
+pub struct UnknownExportFormat(String);
+
+impl FromStr for ExportFormat {
+    type Err = UnknownExportFormat;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ExportFormat::ALL
+            .into_iter()
+            .find(|format| format.name() == s)
+            .ok_or_else(|| UnknownExportFormat(s.to_string()))
+    }
+}
+
+impl Display for ExportFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl Layout<2> {
+    pub fn export(&self, format: ExportFormat) -> String {
+        let cells = self
+            .iter_cells()
+            .map(
+                |Cell {
+                     position: [x, y],
+                     rectangle,
+                 }| {
+                    let [width, height] = rectangle.lengths;
+                    (x, y, width, height)
+                },
+            )
+            .collect::<Vec<_>>();
+
+        match format {
+            ExportFormat::Json => {
+                let cells = cells
+                    .into_iter()
+                    .map(|(x, y, width, height)| {
+                        json!({ "x": x, "y": y, "width": width, "height": height })
+                    })
+                    .collect::<Vec<_>>();
+
+                serde_json::to_string(&cells).expect("cells only contain integers") + "\n"
+            }
+            ExportFormat::Tsv => cells
+                .into_iter()
+                .map(|(x, y, width, height)| format!("{x}\t{y}\t{width}\t{height}\n"))
+                .collect(),
+            ExportFormat::Shell => cells
+                .iter()
+                .enumerate()
+                .map(|(index, (x, y, width, height))| {
+                    format!(
+                        "RPEX_{index}_X={x}\nRPEX_{index}_Y={y}\n\
+                         RPEX_{index}_WIDTH={width}\nRPEX_{index}_HEIGHT={height}\n"
+                    )
+                })
+                .chain(std::iter::once(format!("RPEX_COUNT={}\n", cells.len())))
+                .collect(),
+            ExportFormat::XrandrGeometry => cells
+                .into_iter()
+                .map(|(x, y, width, height)| format!("{width}/0x{height}/1+{x}+{y}\n"))
+                .collect(),
+            ExportFormat::Zellij => self.zellij_layout(),
+            ExportFormat::Qtile => self.qtile_layout(&cells),
+            ExportFormat::I3 => self.i3_layout(),
+            ExportFormat::Html => self.to_html(),
+            ExportFormat::GeoJson => self.to_geojson(&CoordinateMapping::default()),
+            ExportFormat::CutList => self.cut_list(&cells),
+            // Cells are placed relative to the workarea, and clients past the last cell start
+            // over from the first.
+            ExportFormat::Awesome => {
+                let cells = cells
+                    .into_iter()
+                    .map(|(x, y, width, height)| {
+                        format!("    {{ x = {x}, y = {y}, width = {width}, height = {height} }},\n")
+                    })
+                    .collect::<String>();
+
+                format!(
+                    "local cells = {{\n{cells}}}\n\
+                     \n\
+                     local rpex = {{ name = \"rpex\" }}\n\
+                     \n\
+                     function rpex.arrange(p)\n    \
+                         local area = p.workarea\n    \
+                         for i, c in ipairs(p.clients) do\n        \
+                             local cell = cells[(i - 1) % #cells + 1]\n        \
+                             p.geometries[c] = {{\n            \
+                                 x = area.x + cell.x,\n            \
+                                 y = area.y + cell.y,\n            \
+                                 width = cell.width,\n            \
+                                 height = cell.height,\n        \
+                             }}\n    \
+                         end\n\
+                     end\n\
+                     \n\
+                     return rpex\n"
+                )
+            }
+        }
+    }
+
+    /// Renders a self-contained HTML page showing every cell as a coloured box, with its label or
+    /// index and its size. Boxes are positioned in percentages, so the page scales with the
+    /// browser while keeping the aspect ratio of the layout.
+    pub fn to_html(&self) -> String {
+        let [total_width, total_height] = self
+            .sums_in_ratio()
+            .totals()
+            .map(|total| total * self.scale());
+        let percent = |length: u32, total: u32| length as f64 * 100.0 / total as f64;
+
+        let cells = self
+            .iter_cells()
+            .zip(self.iter_labels())
+            .enumerate()
+            .map(
+                |(
+                    index,
+                    (
+                        Cell {
+                            position: [x, y],
+                            rectangle,
+                        },
+                        label,
+                    ),
+                )| {
+                    let [width, height] = rectangle.lengths;
+                    // Golden angle steps keep neighbouring cells apart in hue.
+                    let hue = index * 137 % 360;
+                    let name = label.map_or_else(|| index.to_string(), |label| escape_html(&label));
+
+                    format!(
+                        "<div class=\"cell\" style=\"left: {:.4}%; top: {:.4}%; width: {:.4}%; \
+                         height: {:.4}%; background: hsl({hue}, 60%, 75%);\">\
+                         <b>{name}</b><br>{width}x{height}</div>\n",
+                        percent(x, total_width),
+                        percent(y, total_height),
+                        percent(width, total_width),
+                        percent(height, total_height),
+                    )
+                },
+            )
+            .collect::<String>();
+
+        format!(
+            "<!DOCTYPE html>\n\
+             <html>\n\
+             <head>\n\
+             <meta charset=\"utf-8\">\n\
+             <title>rpex layout {total_width}x{total_height}</title>\n\
+             <style>\n\
+             .layout {{ position: relative; width: 100%; aspect-ratio: {total_width} / {total_height}; \
+             background: #333; }}\n\
+             .cell {{ position: absolute; box-sizing: border-box; border: 1px solid #333; \
+             display: flex; flex-direction: column; align-items: center; justify-content: center; \
+             font-family: sans-serif; text-align: center; overflow: hidden; }}\n\
+             </style>\n\
+             </head>\n\
+             <body>\n\
+             <div class=\"layout\">\n{cells}</div>\n\
+             </body>\n\
+             </html>\n"
+        )
+    }
+
+    /// Builds a GeoJSON feature collection with a polygon for every cell, mapped by `mapping`.
+    ///
+    /// Features have the index and label of their cell and its geometry in pixels as properties.
+    pub fn to_geojson(&self, mapping: &CoordinateMapping) -> String {
+        let features = self
+            .iter_cells()
+            .zip(self.iter_labels())
+            .enumerate()
+            .map(
+                |(
+                    index,
+                    (
+                        Cell {
+                            position: [x, y],
+                            rectangle,
+                        },
+                        label,
+                    ),
+                )| {
+                    let [width, height] = rectangle.lengths;
+                    let mut ring = [
+                        [x, y],
+                        [x + width, y],
+                        [x + width, y + height],
+                        [x, y + height],
+                        [x, y],
+                    ]
+                    .map(|corner| mapping.map(corner));
+
+                    // Exterior rings go counterclockwise, which flips along with an axis.
+                    if mapping.pixel_size[0] * mapping.pixel_size[1] < 0.0 {
+                        ring.reverse();
+                    }
+
+                    json!({
+                        "type": "Feature",
+                        "geometry": { "type": "Polygon", "coordinates": [ring] },
+                        "properties": {
+                            "index": index,
+                            "label": label,
+                            "x": x,
+                            "y": y,
+                            "width": width,
+                            "height": height,
+                        },
+                    })
+                },
+            )
+            .collect::<Vec<_>>();
+
+        let collection = json!({ "type": "FeatureCollection", "features": features });
+
+        serde_json::to_string(&collection).expect("features only contain json values") + "\n"
+    }
+
+    // Sheets are cut into strips along x first, and then every strip along y at the same
+    // positions, with the gutter as the kerf. Positions are measured from the left and top edges
+    // of the sheet.
+    fn cut_list(&self, cells: &[(u32, u32, u32, u32)]) -> String {
+        let [width, height] = self
+            .sums_in_ratio()
+            .totals()
+            .map(|total| total * self.scale());
+        let [kerf_x, kerf_y] = self.gutter();
+
+        let mut list = format!("stock {width}x{height} kerf {kerf_x}x{kerf_y}\n");
+
+        for (axis, cuts) in ["x", "y"].into_iter().zip(self.cuts()) {
+            for cut in cuts {
+                list.push_str(&format!("cut {axis} {}..{}\n", cut.start, cut.end));
+            }
+        }
+
+        for (index, ((x, y, width, height), label)) in
+            cells.iter().zip(self.iter_labels()).enumerate()
+        {
+            let name = label.unwrap_or_else(|| index.to_string());
+
+            list.push_str(&format!("part {name} {width}x{height}+{x}+{y}\n"));
+        }
+
+        list
+    }
+
+    // Placeholders swallow windows by instance, named by the label of their cell or its index
+    // otherwise, e.g. `xterm -name rpex-0`. Columns of a single cell are placeholders themselves.
+    fn i3_layout(&self) -> String {
+        let totals = self.sums_in_ratio().totals();
+        let mut index = 0;
+
+        let columns = self
+            .columns()
+            .map(|column| {
+                let first = &column.partitions[0];
+                let width = first.ratio[0].0 as f64 / totals[0] as f64;
+
+                let mut placeholders = column
+                    .partitions
+                    .iter()
+                    .zip(&column.cells)
+                    .map(|(partition, cell)| {
+                        let name = partition.label().unwrap_or_else(|| format!("rpex-{index}"));
+                        let [x, y] = cell.position;
+                        let [width, height] = cell.rectangle.lengths;
+                        index += 1;
+
+                        json!({
+                            "type": "con",
+                            "percent": partition.ratio[1].0 as f64 / totals[1] as f64,
+                            "geometry": { "x": x, "y": y, "width": width, "height": height },
+                            "swallows": [{ "instance": format!("^{name}$") }],
+                        })
+                    })
+                    .collect::<Vec<_>>();
+
+                if let [placeholder] = &mut placeholders[..] {
+                    placeholder["percent"] = json!(width);
+
+                    return placeholder.take();
+                }
+
+                json!({
+                    "type": "con",
+                    "layout": "splitv",
+                    "percent": width,
+                    "nodes": placeholders,
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let layout = json!({ "type": "con", "layout": "splith", "nodes": columns });
+
+        serde_json::to_string_pretty(&layout).expect("layout only contains json values") + "\n"
+    }
+
+    // ScreenSplit places splits by fractions of the screen, which are written as Python divisions
+    // to keep them exact. Splits are named by their label, or their index otherwise.
+    fn qtile_layout(&self, cells: &[(u32, u32, u32, u32)]) -> String {
+        let [total_width, total_height] = self
+            .sums_in_ratio()
+            .totals()
+            .map(|total| total * self.scale());
+        let fraction = |length: u32, total: u32| {
+            let gcd = length.gcd(&total);
+
+            match (length / gcd, total / gcd) {
+                (length, 1) => length.to_string(),
+                (length, total) => format!("{length} / {total}"),
+            }
+        };
+
+        let splits = cells
+            .iter()
+            .zip(self.iter_labels())
+            .enumerate()
+            .map(|(index, ((x, y, width, height), label))| {
+                format!(
+                    "        {{\"name\": \"{}\", \"rect\": ({}, {}, {}, {}), \"layout\": layout.Max()}},\n",
+                    label.unwrap_or_else(|| index.to_string()),
+                    fraction(*x, total_width),
+                    fraction(*y, total_height),
+                    fraction(*width, total_width),
+                    fraction(*height, total_height),
+                )
+            })
+            .collect::<String>();
+
+        format!(
+            "from libqtile import layout\n\
+             \n\
+             rpex = layout.ScreenSplit(\n    \
+                 name=\"rpex\",\n    \
+                 splits=[\n{splits}    ],\n\
+             )\n"
+        )
+    }
+
+    // Zellij sizes panes in whole percentages of their parent, so the seams are rounded rather
+    // than each size, which keeps the sizes of siblings adding up to 100%. Each column becomes a
+    // pane, split into rows unless it only has one.
+    fn zellij_layout(&self) -> String {
+        let totals = self.sums_in_ratio().totals();
+        let percentage = |dim: usize, offset: RatioUnits, addend: RatioUnits| {
+            let seam =
+                |RatioUnits(offset): RatioUnits| (offset * 200 + totals[dim]) / (totals[dim] * 2);
+
+            seam(offset + addend) - seam(offset)
+        };
+        let name = |partition: &Partition<2>| {
+            partition
+                .label()
+                .map(|label| format!(" name=\"{label}\""))
+                .unwrap_or_default()
+        };
+
+        let mut kdl = "layout {\n    pane split_direction=\"vertical\" {\n".to_string();
+
+        for column in self.columns() {
+            let first = &column.partitions[0];
+            let width = percentage(0, first.ratio_position[0], first.ratio[0]);
+
+            if let [partition] = &column.partitions[..] {
+                kdl.push_str(&format!(
+                    "        pane size=\"{width}%\"{}\n",
+                    name(partition)
+                ));
+                continue;
+            }
+
+            kdl.push_str(&format!(
+                "        pane size=\"{width}%\" split_direction=\"horizontal\" {{\n"
+            ));
+
+            for partition in &column.partitions {
+                let height = percentage(1, partition.ratio_position[1], partition.ratio[1]);
+
+                kdl.push_str(&format!(
+                    "            pane size=\"{height}%\"{}\n",
+                    name(partition)
+                ));
+            }
+
+            kdl.push_str("        }\n");
+        }
+
+        kdl.push_str("    }\n}\n");
+
+        kdl
+    }
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::Rpex;
+
+    fn layout(rpex: &str, rectangle: &str) -> Layout<2> {
+        Rpex::from_str(rpex)
+            .unwrap()
+            .evaluate(rectangle.parse().unwrap())
+            .unwrap()
+            .into()
+    }
+
+    #[test]
+    fn we_can_parse_every_export_format() {
+        for format in ExportFormat::ALL {
+            assert_eq!(ExportFormat::from_str(&format.to_string()).unwrap(), format);
+        }
+        assert!(ExportFormat::from_str("yaml").is_err());
+    }
+
+    #[test]
+    fn we_can_export_layout_as_json() {
+        assert_eq!(
+            layout("1+1:1", "200x100").export(ExportFormat::Json),
+            concat!(
+                r#"[{"height":100,"width":100,"x":0,"y":0},"#,
+                r#"{"height":100,"width":100,"x":100,"y":0}]"#,
+                "\n"
+            )
+        );
+    }
+
+    #[test]
+    fn we_can_export_layout_as_text_formats() {
+        let layout = layout("1:1+1", "100x200");
+
+        assert_eq!(
+            layout.export(ExportFormat::Tsv),
+            "0\t0\t100\t100\n0\t100\t100\t100\n"
+        );
+        assert_eq!(
+            layout.export(ExportFormat::XrandrGeometry),
+            "100/0x100/1+0+0\n100/0x100/1+0+100\n"
+        );
+        assert!(layout
+            .export(ExportFormat::Shell)
+            .ends_with("RPEX_1_HEIGHT=100\nRPEX_COUNT=2\n"));
+    }
+
+    #[test]
+    fn we_can_export_layout_as_awesome_lua() {
+        let lua = layout("1+1:1", "200x100").export(ExportFormat::Awesome);
+
+        assert!(lua.starts_with(
+            "local cells = {\n    \
+                 { x = 0, y = 0, width = 100, height = 100 },\n    \
+                 { x = 100, y = 0, width = 100, height = 100 },\n\
+             }\n"
+        ));
+        assert!(lua.ends_with("return rpex\n"));
+    }
+
+    #[test]
+    fn we_can_export_layout_as_qtile_python() {
+        assert_eq!(
+            layout("2+1:1", "300x100").export(ExportFormat::Qtile),
+            "from libqtile import layout\n\
+             \n\
+             rpex = layout.ScreenSplit(\n    \
+                 name=\"rpex\",\n    \
+                 splits=[\n        \
+                     {\"name\": \"0\", \"rect\": (0, 0, 2 / 3, 1), \"layout\": layout.Max()},\n        \
+                     {\"name\": \"1\", \"rect\": (2 / 3, 0, 1 / 3, 1), \"layout\": layout.Max()},\n    \
+                 ],\n\
+             )\n"
+        );
+    }
+
+    #[test]
+    fn we_can_export_layout_as_i3_json() {
+        let tree: serde_json::Value =
+            serde_json::from_str(&layout("1+1:1+1", "200x200").export(ExportFormat::I3)).unwrap();
+
+        assert_eq!(tree["layout"], "splith");
+        assert_eq!(tree["nodes"][1]["layout"], "splitv");
+        assert_eq!(tree["nodes"][1]["percent"], 0.5);
+        assert_eq!(
+            tree["nodes"][1]["nodes"][1],
+            json!({
+                "type": "con",
+                "percent": 0.5,
+                "geometry": { "x": 100, "y": 100, "width": 100, "height": 100 },
+                "swallows": [{ "instance": "^rpex-3$" }],
+            })
+        );
+
+        let tree: serde_json::Value =
+            serde_json::from_str(&layout("3+1:1", "400x100").export(ExportFormat::I3)).unwrap();
+
+        assert_eq!(tree["nodes"][0]["percent"], 0.75);
+        assert_eq!(tree["nodes"][0]["swallows"][0]["instance"], "^rpex-0$");
+    }
+
+    #[test]
+    fn we_can_export_layout_as_html() {
+        let html = layout("1+1:1", "200x100").to_html();
+
+        assert!(html.contains("aspect-ratio: 200 / 100;"));
+        assert!(html.contains(
+            "<div class=\"cell\" style=\"left: 50.0000%; top: 0.0000%; width: 50.0000%; \
+             height: 100.0000%; background: hsl(137, 60%, 75%);\"><b>1</b><br>100x100</div>\n"
+        ));
+        assert_eq!(layout("1+1:1", "200x100").export(ExportFormat::Html), html);
+    }
+
+    #[test]
+    fn we_can_export_layout_as_geojson() {
+        let layout = layout("1+1:1", "200x100");
+        let mapping = CoordinateMapping::from_extent([10.0, 40.0, 12.0, 41.0], &layout);
+        let collection: serde_json::Value =
+            serde_json::from_str(&layout.to_geojson(&mapping)).unwrap();
+
+        assert_eq!(
+            collection["features"][1]["geometry"]["coordinates"],
+            json!([[
+                [11.0, 41.0],
+                [11.0, 40.0],
+                [12.0, 40.0],
+                [12.0, 41.0],
+                [11.0, 41.0]
+            ]])
+        );
+        assert_eq!(collection["features"][1]["properties"]["x"], 100);
+    }
+
+    #[test]
+    fn we_can_export_layout_as_cut_list() {
+        assert_eq!(
+            layout("1+1:1", "200x100")
+                .with_gutter([3, 0])
+                .export(ExportFormat::CutList),
+            "stock 200x100 kerf 3x0\n\
+             cut x 99..102\n\
+             part 0 99x100+0+0\n\
+             part 1 98x100+102+0\n"
+        );
+    }
+
+    #[test]
+    fn we_can_export_layout_as_zellij_kdl() {
+        assert_eq!(
+            layout("2+1:1+1", "300x200").export(ExportFormat::Zellij),
+            "layout {\n    \
+                 pane split_direction=\"vertical\" {\n        \
+                     pane size=\"67%\" split_direction=\"horizontal\" {\n            \
+                         pane size=\"50%\"\n            \
+                         pane size=\"50%\"\n        \
+                     }\n        \
+                     pane size=\"33%\" split_direction=\"horizontal\" {\n            \
+                         pane size=\"50%\"\n            \
+                         pane size=\"50%\"\n        \
+                     }\n    \
+                 }\n\
+             }\n"
+        );
+        assert_eq!(
+            layout("1+1+1:1", "300x100").export(ExportFormat::Zellij),
+            "layout {\n    \
+                 pane split_direction=\"vertical\" {\n        \
+                     pane size=\"33%\"\n        \
+                     pane size=\"34%\"\n        \
+                     pane size=\"33%\"\n    \
+                 }\n\
+             }\n"
+        );
+    }
+}
@@ -0,0 +1,79 @@
+use crate::{layout::Cell, sums_in_ratio::Partition};
+
+/// How [`Cell::format_geometry`] and [`Partition::format_geometry`] write geometries.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum GeometryStyle {
+    /// `WxH+X+Y`, like X geometry arguments.
+    Xrandr,
+    /// `W/MMxH/MM+X+Y`, like `xrandr --setmonitor` takes, with the given physical size in
+    /// millimetres.
+    XrandrPhysical([u32; 2]),
+}
+
+fn format_geometry([x, y]: [u32; 2], [width, height]: [u32; 2], style: GeometryStyle) -> String {
+    match style {
+        GeometryStyle::Xrandr => format!("{width}x{height}+{x}+{y}"),
+        GeometryStyle::XrandrPhysical([width_mm, height_mm]) => {
+            format!("{width}/{width_mm}x{height}/{height_mm}+{x}+{y}")
+        }
+    }
+}
+
+impl Cell<2> {
+    /// Formats the geometry of this cell, with the physical size of the cell itself for
+    /// [`GeometryStyle::XrandrPhysical`].
+    pub fn format_geometry(&self, style: GeometryStyle) -> String {
+        format_geometry(self.position, self.rectangle.lengths, style)
+    }
+}
+
+impl Partition<'_, 2> {
+    /// Formats the geometry of this partition at `scale` pixels per ratio unit.
+    ///
+    /// For [`GeometryStyle::XrandrPhysical`], the physical size is that of the whole layout, and
+    /// the partition gets its share of it, rounded to the nearest millimetre.
+    pub fn format_geometry(&self, scale: u32, style: GeometryStyle) -> String {
+        let position = self.ratio_position.map(|offset| offset.to_px(scale).0);
+        let lengths = self.ratio.map(|addend| addend.to_px(scale).0);
+
+        let style = match style {
+            GeometryStyle::XrandrPhysical(total_mm) => {
+                GeometryStyle::XrandrPhysical(std::array::from_fn(|dim| {
+                    let share = self.fraction(dim);
+                    let (numer, denom) = (*share.numer(), *share.denom());
+
+                    (total_mm[dim] * numer * 2 + denom) / (denom * 2)
+                }))
+            }
+            style => style,
+        };
+
+        format_geometry(position, lengths, style)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+    use crate::{HyperRectangle, Rpex};
+
+    #[test]
+    fn we_can_format_geometries() {
+        let (sums_in_ratio, scale) = Rpex::<2>::from_str("2+1:1")
+            .unwrap()
+            .evaluate(HyperRectangle::from_str("3840x1280").unwrap())
+            .unwrap();
+        let partition = sums_in_ratio.iter_partitions().last().unwrap();
+
+        assert_eq!(
+            partition.format_geometry(scale, GeometryStyle::Xrandr),
+            "1280x1280+2560+0"
+        );
+        assert_eq!(
+            partition.format_geometry(scale, GeometryStyle::XrandrPhysical([1200, 340])),
+            "1280/400x1280/340+2560+0"
+        );
+    }
+}
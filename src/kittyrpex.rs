@@ -0,0 +1,126 @@
+use std::io;
+use std::process::Command;
+
+use clap::Parser;
+use rpex::{Rpex, Split, SplitDirection};
+use thiserror::Error;
+
+/// Split the current kitty window into panes by an expression
+///
+/// Uses kitty's remote control, which has to be enabled with `allow_remote_control`, and switches
+/// the tab to the splits layout.
+#[derive(Parser)]
+struct KittyRpexArgs {
+    rpex: Rpex<2>,
+    /// Id of the window to split, the window kittyrpex runs in by default
+    #[arg(long, env = "KITTY_WINDOW_ID")]
+    window: u32,
+    /// Print the kitten commands instead of running them
+    #[arg(long)]
+    dry_run: bool,
+}
+
+#[derive(Error, Debug)]
+enum KittyRpexError {
+    #[error("unable to run kitten: {0}")]
+    Io(#[from] io::Error),
+    #[error("kitten @ {0} failed: {1}")]
+    Failed(String, String),
+    #[error("kitten @ launch printed {0:?} instead of a window id")]
+    InvalidWindowId(String),
+}
+
+fn kitten(args: &[String]) -> Result<String, KittyRpexError> {
+    let output = Command::new("kitten").arg("@").args(args).output()?;
+
+    if !output.status.success() {
+        return Err(KittyRpexError::Failed(
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr)
+                .trim_end()
+                .to_string(),
+        ));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+// In the splits layout, the bias is the percentage of the split window that the new window takes.
+fn launch_args(
+    Split {
+        direction, percent, ..
+    }: Split,
+    window: &str,
+) -> Vec<String> {
+    let location = match direction {
+        SplitDirection::Right => "vsplit",
+        SplitDirection::Down => "hsplit",
+    };
+
+    vec![
+        "launch".to_string(),
+        format!("--location={location}"),
+        format!("--bias={percent}"),
+        format!("--next-to=id:{window}"),
+        "--cwd=current".to_string(),
+    ]
+}
+
+fn main() -> Result<(), KittyRpexError> {
+    let args = KittyRpexArgs::parse();
+
+    let layout_args = ["goto-layout".to_string(), "splits".to_string()];
+
+    // Windows created by a dry run don't exist, so they are referred to by their pane number.
+    if args.dry_run {
+        println!("kitten @ {}", layout_args.join(" "));
+
+        let mut windows = vec![args.window.to_string()];
+
+        for split in args.rpex.splits() {
+            println!(
+                "kitten @ {}",
+                launch_args(split, &windows[split.pane]).join(" ")
+            );
+            windows.push(format!("$WINDOW_{}", windows.len()));
+        }
+
+        return Ok(());
+    }
+
+    kitten(&layout_args)?;
+
+    let mut windows = vec![args.window.to_string()];
+
+    for split in args.rpex.splits() {
+        let window = kitten(&launch_args(split, &windows[split.pane]))?;
+        let window = window.trim();
+
+        if window.parse::<u32>().is_err() {
+            return Err(KittyRpexError::InvalidWindowId(window.to_string()));
+        }
+
+        windows.push(window.to_string());
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn we_can_build_launch_args() {
+        let split = Split {
+            pane: 0,
+            direction: SplitDirection::Right,
+            percent: 33,
+        };
+
+        assert_eq!(
+            launch_args(split, "1").join(" "),
+            "launch --location=vsplit --bias=33 --next-to=id:1 --cwd=current"
+        );
+    }
+}
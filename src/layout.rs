@@ -0,0 +1,634 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    ops::Range,
+};
+
+use fraction::Integer;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use thiserror::Error;
+
+use crate::{
+    rectangle::HyperRectangle,
+    sums_in_ratio::{Partition, SumsInRatio, SumsInRatioEvaluationError},
+    units::{Px, RatioUnits},
+    Rpex,
+};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Layout<const D: usize> {
+    sums_in_ratio: SumsInRatio<D>,
+    scale: u32,
+    gutter: [u32; D],
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Cell<const D: usize> {
+    pub position: [u32; D],
+    pub rectangle: HyperRectangle<D>,
+}
+
+#[derive(Error, Debug)]
+pub enum RefineError {
+    #[error("layout has no cell {0}")]
+    NoCell(usize),
+    #[error("unable to evaluate expression for the cell: {0}")]
+    Evaluation(#[from] SumsInRatioEvaluationError),
+}
+
+/// Partitions of a [`Layout`] that share their position in one dimension, like a row or column,
+/// see [`Layout::strips`].
+pub struct Strip<'a, const D: usize> {
+    pub partitions: Vec<Partition<'a, D>>,
+    /// Cell of each partition, in the same order.
+    pub cells: Vec<Cell<D>>,
+    /// Smallest cell in pixels that covers the cells of every partition.
+    pub bounds: Cell<D>,
+}
+
+impl<const D: usize> Layout<D> {
+    pub fn sums_in_ratio(&self) -> &SumsInRatio<D> {
+        &self.sums_in_ratio
+    }
+
+    pub fn scale(&self) -> u32 {
+        self.scale
+    }
+
+    /// Converts ratio units of this layout, e.g. of its partitions, to pixels.
+    pub fn to_px(&self, units: RatioUnits) -> Px {
+        units.to_px(self.scale)
+    }
+
+    pub fn gutter(&self) -> [u32; D] {
+        self.gutter
+    }
+
+    /// Leaves `gutter` pixels between neighbouring cells in each dimension.
+    ///
+    /// Cells shrink away from the seams they share with other cells, while the outer edges of the
+    /// layout are left in place.
+    pub fn with_gutter(self, gutter: [u32; D]) -> Layout<D> {
+        Layout { gutter, ..self }
+    }
+
+    pub fn iter_cells(&self) -> impl Iterator<Item = Cell<D>> + '_ {
+        let totals = self.sums_in_ratio.totals().map(|total| total * self.scale);
+
+        self.sums_in_ratio
+            .iter_partitions()
+            .map(move |partition| self.cell(&partition, totals))
+    }
+
+    /// Like [`Layout::iter_cells`], but starting at the cell at `index` without computing the
+    /// ones before it, see [`SumsInRatio::iter_partitions_from`].
+    pub fn iter_cells_from(&self, index: usize) -> impl Iterator<Item = Cell<D>> + '_ {
+        let totals = self.sums_in_ratio.totals().map(|total| total * self.scale);
+
+        self.sums_in_ratio
+            .iter_partitions_from(index)
+            .map(move |partition| self.cell(&partition, totals))
+    }
+
+    // `totals` are the totals of the sums in pixels, where cells don't shrink for the gutter.
+    fn cell(
+        &self,
+        Partition {
+            ratio_position,
+            ratio,
+            ..
+        }: &Partition<'_, D>,
+        totals: [u32; D],
+    ) -> Cell<D> {
+        let bounds: [(u32, u32); D] = std::array::from_fn(|dim| {
+            let start = self.to_px(ratio_position[dim]).0;
+            let end = self.to_px(ratio_position[dim] + ratio[dim]).0;
+            let gutter = self.gutter[dim];
+
+            let start = if start == 0 {
+                start
+            } else {
+                start + (gutter - gutter / 2)
+            };
+            let end = if end == totals[dim] {
+                end
+            } else {
+                end.saturating_sub(gutter / 2)
+            };
+
+            (start, end.max(start))
+        });
+
+        Cell {
+            position: bounds.map(|(start, _)| start),
+            rectangle: HyperRectangle {
+                lengths: bounds.map(|(start, end)| end - start),
+            },
+        }
+    }
+
+    /// The material removed between neighbouring cells in each dimension, as `start..end` ranges
+    /// in order, which are as wide as the gutter. Without a gutter, every range is empty and only
+    /// marks a seam.
+    pub fn cuts(&self) -> [Vec<Range<u32>>; D] {
+        std::array::from_fn(|dim| {
+            let gutter = self.gutter[dim];
+
+            self.sums_in_ratio
+                .iter_partitions()
+                .map(|partition| self.to_px(partition.ratio_position[dim]).0)
+                .filter(|&seam| seam != 0)
+                .collect::<BTreeSet<_>>()
+                .into_iter()
+                .map(|seam| seam.saturating_sub(gutter / 2)..seam + (gutter - gutter / 2))
+                .collect()
+        })
+    }
+
+    /// Yields the label of every cell, in the same order as [`Layout::iter_cells`].
+    pub fn iter_labels(&self) -> impl Iterator<Item = Option<String>> + '_ {
+        self.sums_in_ratio
+            .iter_partitions()
+            .map(|partition| partition.label())
+    }
+
+    /// Groups the partitions by their position in dimension `dim`, one strip per addend of that
+    /// dimension in order, e.g. the columns for dimension 0. Within a strip, partitions are in the
+    /// same order as [`Layout::iter_cells`].
+    pub fn strips(&self, dim: usize) -> impl Iterator<Item = Strip<'_, D>> + '_ {
+        let totals = self.sums_in_ratio.totals().map(|total| total * self.scale);
+
+        let mut strips = BTreeMap::<_, Vec<_>>::new();
+        for partition in self.sums_in_ratio.iter_partitions() {
+            strips
+                .entry(partition.ratio_position[dim])
+                .or_default()
+                .push(partition);
+        }
+
+        strips.into_values().map(move |partitions| {
+            let cells = partitions
+                .iter()
+                .map(|partition| self.cell(partition, totals))
+                .collect::<Vec<_>>();
+
+            let bounds: [(u32, u32); D] = std::array::from_fn(|dim| {
+                let start = cells.iter().map(|cell| cell.position[dim]).min();
+                let end = cells
+                    .iter()
+                    .map(|cell| cell.position[dim] + cell.rectangle.lengths[dim])
+                    .max();
+
+                start.zip(end).expect("every strip has a partition")
+            });
+
+            Strip {
+                partitions,
+                cells,
+                bounds: Cell {
+                    position: bounds.map(|(start, _)| start),
+                    rectangle: HyperRectangle {
+                        lengths: bounds.map(|(start, end)| end - start),
+                    },
+                },
+            }
+        })
+    }
+
+    /// Partitions the cell at `index` in [`Layout::iter_cells`] with `rpex`, evaluated on the
+    /// cell's partition in pixels, into a single layout with the cells of `rpex` in its place.
+    ///
+    /// A layout is a grid, so the cells sharing a row or column with the refined cell are cut
+    /// along the same lines, taking on the labels of `rpex` where it has them. The gutter is kept.
+    pub fn refine(&self, index: usize, rpex: Rpex<D>) -> Result<Layout<D>, RefineError> {
+        let partition = self
+            .sums_in_ratio
+            .iter_partitions()
+            .nth(index)
+            .ok_or(RefineError::NoCell(index))?;
+
+        let rectangle = HyperRectangle {
+            lengths: partition.ratio.map(|units| self.to_px(units).0),
+        };
+        let (inner, inner_scale) = rpex.evaluate(rectangle)?;
+
+        // Every boundary of both layouts is a multiple of the scale they have in common.
+        let scale = self.scale.gcd(&inner_scale);
+
+        Ok(Layout {
+            sums_in_ratio: self.sums_in_ratio.refine(
+                index,
+                self.scale / scale,
+                &inner,
+                inner_scale / scale,
+            ),
+            scale,
+            gutter: self.gutter,
+        })
+    }
+
+    /// Index in [`Layout::iter_cells`] of the cell covering each of `points`, in pixels, or
+    /// `None` for points outside of the layout. Points in the gutter belong to the cell whose
+    /// partition they are in.
+    ///
+    /// Every point is looked up by binary search in each dimension.
+    pub fn map_points(&self, points: &[[u32; D]]) -> Vec<Option<usize>> {
+        // Partitions start at multiples of the scale, so a point is in the same partition as the
+        // ratio unit it is in.
+        points
+            .iter()
+            .map(|point| {
+                self.sums_in_ratio
+                    .partition_at(point.map(|px| RatioUnits(px / self.scale)))
+            })
+            .collect()
+    }
+}
+
+/// Summary of the cells of a [`Layout`], see [`Layout::stats`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct LayoutStats {
+    /// Smallest width and height of any cell, not necessarily of the same cell.
+    pub min_size: [u32; 2],
+    /// Largest width and height of any cell, not necessarily of the same cell.
+    pub max_size: [u32; 2],
+    pub mean_size: [f64; 2],
+    /// Width divided by height of every cell, in the same order as [`Layout::iter_cells`].
+    pub aspect_ratios: Vec<f64>,
+    /// Fraction of the layout's area covered by cells, less than 1 when there is a gutter.
+    pub coverage: f64,
+}
+
+impl Layout<2> {
+    /// Like [`Layout::iter_cells`], built on [`SumsInRatio::iter_partitions_2d`].
+    pub fn iter_cells_2d(&self) -> impl Iterator<Item = Cell<2>> + '_ {
+        let totals = self.sums_in_ratio.totals().map(|total| total * self.scale);
+
+        self.sums_in_ratio
+            .iter_partitions_2d()
+            .map(move |partition| self.cell(&partition, totals))
+    }
+
+    /// The strips of cells at the same height, from top to bottom.
+    pub fn rows(&self) -> impl Iterator<Item = Strip<'_, 2>> + '_ {
+        self.strips(1)
+    }
+
+    /// The strips of cells at the same horizontal position, from left to right.
+    pub fn columns(&self) -> impl Iterator<Item = Strip<'_, 2>> + '_ {
+        self.strips(0)
+    }
+
+    pub fn stats(&self) -> LayoutStats {
+        let sizes = self
+            .iter_cells()
+            .map(|Cell { rectangle, .. }| rectangle.lengths)
+            .collect::<Vec<_>>();
+
+        let extreme = |pick: fn(u32, u32) -> u32| {
+            [0, 1].map(|dim| {
+                sizes
+                    .iter()
+                    .map(|lengths| lengths[dim])
+                    .reduce(pick)
+                    .expect("a layout has at least one cell")
+            })
+        };
+
+        let area = |[width, height]: [u32; 2]| width as f64 * height as f64;
+        let total_area = area(self.sums_in_ratio.totals().map(|total| total * self.scale));
+
+        LayoutStats {
+            min_size: extreme(u32::min),
+            max_size: extreme(u32::max),
+            mean_size: [0, 1].map(|dim| {
+                sizes.iter().map(|lengths| lengths[dim] as f64).sum::<f64>() / sizes.len() as f64
+            }),
+            aspect_ratios: sizes
+                .iter()
+                .map(|[width, height]| *width as f64 / *height as f64)
+                .collect(),
+            coverage: sizes.iter().copied().map(area).sum::<f64>() / total_area,
+        }
+    }
+}
+
+// Serde only implements its traits for arrays of known lengths, so gutters are written as
+// sequences.
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SerializedLayout<const D: usize> {
+    sums_in_ratio: SumsInRatio<D>,
+    scale: u32,
+    gutter: Vec<u32>,
+}
+
+impl<const D: usize> Serialize for Layout<D> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        SerializedLayout {
+            sums_in_ratio: self.sums_in_ratio.clone(),
+            scale: self.scale,
+            gutter: self.gutter.to_vec(),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de, const D: usize> Deserialize<'de> for Layout<D> {
+    fn deserialize<De: Deserializer<'de>>(deserializer: De) -> Result<Self, De::Error> {
+        let SerializedLayout {
+            sums_in_ratio,
+            scale,
+            gutter,
+        } = SerializedLayout::deserialize(deserializer)?;
+
+        if scale == 0 {
+            return Err(serde::de::Error::custom("scale must be greater than 0"));
+        }
+
+        let gutter = gutter.try_into().map_err(|gutter: Vec<u32>| {
+            serde::de::Error::invalid_length(gutter.len(), &format!("{D} gutters").as_str())
+        })?;
+
+        Ok(Layout {
+            sums_in_ratio,
+            scale,
+            gutter,
+        })
+    }
+}
+
+impl<const D: usize> From<(SumsInRatio<D>, u32)> for Layout<D> {
+    fn from((sums_in_ratio, scale): (SumsInRatio<D>, u32)) -> Self {
+        Layout {
+            sums_in_ratio,
+            scale,
+            gutter: [0; D],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    use crate::Rpex;
+
+    fn layout(rpex: &str, rectangle: &str) -> Layout<2> {
+        Rpex::from_str(rpex)
+            .unwrap()
+            .evaluate(rectangle.parse().unwrap())
+            .unwrap()
+            .into()
+    }
+
+    fn bounds(layout: &Layout<2>) -> Vec<([u32; 2], [u32; 2])> {
+        layout
+            .iter_cells()
+            .map(
+                |Cell {
+                     position,
+                     rectangle,
+                 }| (position, rectangle.lengths),
+            )
+            .collect()
+    }
+
+    #[test]
+    fn we_can_iterate_cells_in_pixels() {
+        assert_eq!(
+            bounds(&layout("1+2:1", "300x100")),
+            vec![([0, 0], [100, 100]), ([100, 0], [200, 100])]
+        );
+    }
+
+    #[test]
+    fn gutter_only_shrinks_cells_at_inner_seams() {
+        assert_eq!(
+            bounds(&layout("1+1+1:1", "300x100").with_gutter([10, 10])),
+            vec![
+                ([0, 0], [95, 100]),
+                ([105, 0], [90, 100]),
+                ([205, 0], [95, 100])
+            ]
+        );
+        assert_eq!(
+            bounds(&layout("1:1+1", "100x200").with_gutter([0, 5])),
+            vec![([0, 0], [100, 98]), ([0, 103], [100, 97])]
+        );
+    }
+
+    #[test]
+    fn we_can_iterate_cell_labels() {
+        assert_eq!(
+            layout("2@main+1@side:1", "300x100")
+                .iter_labels()
+                .collect::<Vec<_>>(),
+            vec![Some("main".to_string()), Some("side".to_string())]
+        );
+        assert_eq!(
+            layout("1@left+1:1@top+1", "200x200")
+                .iter_labels()
+                .collect::<Vec<_>>(),
+            vec![
+                Some("left_top".to_string()),
+                Some("left".to_string()),
+                Some("top".to_string()),
+                None
+            ]
+        );
+    }
+
+    #[test]
+    fn we_can_summarize_cells() {
+        assert_eq!(
+            layout("2+1:1+1", "300x200").stats(),
+            LayoutStats {
+                min_size: [100, 100],
+                max_size: [200, 100],
+                mean_size: [150.0, 100.0],
+                aspect_ratios: vec![2.0, 2.0, 1.0, 1.0],
+                coverage: 1.0,
+            }
+        );
+        assert_eq!(
+            layout("1+1:1", "200x100")
+                .with_gutter([10, 0])
+                .stats()
+                .coverage,
+            0.95
+        );
+    }
+
+    #[test]
+    fn we_can_group_cells_into_rows_and_columns() {
+        let layout = layout("2@main+1:1@top+1", "300x200").with_gutter([10, 0]);
+
+        let rows = layout
+            .rows()
+            .map(|strip| {
+                (
+                    strip
+                        .partitions
+                        .iter()
+                        .map(|partition| partition.label())
+                        .collect::<Vec<_>>(),
+                    strip.bounds,
+                )
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            rows,
+            vec![
+                (
+                    vec![Some("main_top".to_string()), Some("top".to_string())],
+                    Cell {
+                        position: [0, 0],
+                        rectangle: HyperRectangle {
+                            lengths: [300, 100]
+                        },
+                    }
+                ),
+                (
+                    vec![Some("main".to_string()), None],
+                    Cell {
+                        position: [0, 100],
+                        rectangle: HyperRectangle {
+                            lengths: [300, 100]
+                        },
+                    }
+                ),
+            ]
+        );
+
+        let columns = layout
+            .columns()
+            .map(|strip| (strip.partitions.len(), strip.bounds))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            columns,
+            vec![
+                (
+                    2,
+                    Cell {
+                        position: [0, 0],
+                        rectangle: HyperRectangle {
+                            lengths: [195, 200]
+                        },
+                    }
+                ),
+                (
+                    2,
+                    Cell {
+                        position: [205, 0],
+                        rectangle: HyperRectangle { lengths: [95, 200] },
+                    }
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn we_can_refine_a_cell() {
+        let outer = layout("2@main+1[ws=2]:1", "300x100");
+
+        let refined = outer
+            .refine(1, Rpex::from_str("2:1@top+1").unwrap())
+            .unwrap();
+
+        assert_eq!(
+            bounds(&refined),
+            vec![
+                ([0, 0], [200, 50]),
+                ([0, 50], [200, 50]),
+                ([200, 0], [100, 50]),
+                ([200, 50], [100, 50]),
+            ]
+        );
+        assert_eq!(
+            refined.iter_labels().collect::<Vec<_>>(),
+            vec![
+                Some("main_top".to_string()),
+                Some("main".to_string()),
+                Some("top".to_string()),
+                None
+            ]
+        );
+        assert_eq!(
+            refined.sums_in_ratio().to_string(),
+            "4@main+2[ws=2]:1@top+1"
+        );
+
+        let refined = outer.refine(0, Rpex::from_str("1+3:").unwrap()).unwrap();
+
+        assert_eq!(refined.scale(), 50);
+        assert_eq!(
+            refined.sums_in_ratio().to_string(),
+            "1@main+3@main+2[ws=2]:2"
+        );
+
+        assert!(matches!(
+            outer.refine(2, Rpex::from_str("1:1").unwrap()),
+            Err(RefineError::NoCell(2))
+        ));
+        assert!(matches!(
+            outer.refine(0, Rpex::from_str("3:1").unwrap()),
+            Err(RefineError::Evaluation(_))
+        ));
+    }
+
+    #[test]
+    fn we_can_serialize_layouts() {
+        let layout = layout("2@main+1:1", "300x100").with_gutter([4, 0]);
+
+        let json = serde_json::to_value(&layout).unwrap();
+
+        assert_eq!(
+            json,
+            serde_json::json!({ "sums_in_ratio": "2@main+1:1", "scale": 100, "gutter": [4, 0] })
+        );
+        assert_eq!(serde_json::from_value::<Layout<2>>(json).unwrap(), layout);
+
+        assert!(serde_json::from_value::<Layout<2>>(serde_json::json!({
+            "sums_in_ratio": "2+1:1",
+            "scale": 100,
+            "gutter": [4],
+        }))
+        .is_err());
+        assert!(serde_json::from_value::<Layout<2>>(serde_json::json!({
+            "sums_in_ratio": "2+1:1",
+            "scale": 0,
+            "gutter": [0, 0],
+        }))
+        .is_err());
+    }
+
+    #[test]
+    fn we_can_map_points_to_cells() {
+        let layout = layout("2+1:1+1", "3840x2560");
+        let cells = layout.iter_cells().collect::<Vec<_>>();
+
+        let points = [
+            [0, 0],
+            [2559, 1279],
+            [2560, 0],
+            [3839, 2559],
+            [3840, 0],
+            [0, 2560],
+        ];
+
+        let indices = layout.map_points(&points);
+
+        assert_eq!(indices, [Some(0), Some(0), Some(2), Some(3), None, None]);
+        for (point, index) in points.iter().zip(indices).take(4) {
+            let cell = cells[index.unwrap()];
+            assert!((0..2).all(|dim| {
+                (cell.position[dim]..cell.position[dim] + cell.rectangle.lengths[dim])
+                    .contains(&point[dim])
+            }));
+        }
+    }
+}
@@ -0,0 +1,148 @@
+use std::ops::Range;
+
+use nom::combinator::all_consuming;
+use thiserror::Error;
+
+use crate::{
+    dimension_sum::{addend_parser, IndeterminateDimensionSum},
+    sums_in_ratio::IndeterminateSumsInRatio,
+};
+
+#[derive(Error, Clone, Debug, PartialEq, Eq)]
+pub enum LenientParseError {
+    #[error("invalid addend {0:?}")]
+    InvalidAddend(String),
+    #[error("expected {expected} dimensions, found {found}")]
+    DimensionCount { expected: usize, found: usize },
+}
+
+/// A [`LenientParseError`] with the byte range of the input it is about.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SpannedParseError {
+    pub span: Range<usize>,
+    pub error: LenientParseError,
+}
+
+// Splits `input` at every `separator`, along with the byte offset of every part.
+fn split_with_offsets(input: &str, separator: char) -> Vec<(usize, &str)> {
+    input
+        .split(separator)
+        .scan(0, |offset, part| {
+            let start = *offset;
+            *offset += part.len() + separator.len_utf8();
+
+            Some((start, part))
+        })
+        .collect()
+}
+
+fn parse_dimension_sum_lenient(
+    input: &str,
+    offset: usize,
+    errors: &mut Vec<SpannedParseError>,
+) -> IndeterminateDimensionSum {
+    split_with_offsets(input, '+')
+        .into_iter()
+        .map(
+            |(start, addend)| match all_consuming(addend_parser)(addend) {
+                Ok((_, parsed)) => parsed,
+                Err(_) => {
+                    errors.push(SpannedParseError {
+                        span: offset + start..offset + start + addend.len(),
+                        error: LenientParseError::InvalidAddend(addend.to_string()),
+                    });
+
+                    (None, None, None)
+                }
+            },
+        )
+        .collect()
+}
+
+impl<const D: usize> IndeterminateSumsInRatio<D> {
+    /// Parses as much of `input` as possible instead of failing at the first mistake, e.g. for
+    /// previewing an expression while it is being typed.
+    ///
+    /// Invalid addends are read as unknowns, missing dimensions as a single unknown, and extra
+    /// dimensions are left out. Every mistake is returned with where it is in `input`, so the
+    /// expression is only valid if there are none.
+    pub fn parse_lenient(input: &str) -> (IndeterminateSumsInRatio<D>, Vec<SpannedParseError>) {
+        let mut errors = vec![];
+        let dimensions = split_with_offsets(input, ':');
+
+        let sums = std::array::from_fn(|dim| match dimensions.get(dim) {
+            Some(&(offset, dim_sum)) => parse_dimension_sum_lenient(dim_sum, offset, &mut errors),
+            None => IndeterminateDimensionSum {
+                addends: vec![None],
+                ..Default::default()
+            },
+        });
+
+        if dimensions.len() != D {
+            // Extra dimensions start at the separator before the first of them.
+            let start = dimensions
+                .get(D)
+                .map_or(input.len(), |&(offset, _)| offset - 1);
+
+            errors.push(SpannedParseError {
+                span: start..input.len(),
+                error: LenientParseError::DimensionCount {
+                    expected: D,
+                    found: dimensions.len(),
+                },
+            });
+        }
+
+        (IndeterminateSumsInRatio { sums }, errors)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    #[test]
+    fn we_can_parse_valid_ratio_leniently() {
+        let (rpex, errors) = IndeterminateSumsInRatio::<2>::parse_lenient("2@main+1_000:+");
+
+        assert_eq!(
+            rpex,
+            IndeterminateSumsInRatio::from_str("2@main+1_000:+").unwrap()
+        );
+        assert!(errors.is_empty());
+    }
+
+    #[test]
+    fn we_can_recover_from_invalid_ratio() {
+        let (rpex, errors) = IndeterminateSumsInRatio::<2>::parse_lenient("2+x+1:1@:3");
+
+        assert_eq!(rpex.to_string(), "2++1:");
+        assert_eq!(
+            errors,
+            vec![
+                SpannedParseError {
+                    span: 2..3,
+                    error: LenientParseError::InvalidAddend("x".to_string()),
+                },
+                SpannedParseError {
+                    span: 6..8,
+                    error: LenientParseError::InvalidAddend("1@".to_string()),
+                },
+                SpannedParseError {
+                    span: 8..10,
+                    error: LenientParseError::DimensionCount {
+                        expected: 2,
+                        found: 3
+                    },
+                },
+            ]
+        );
+
+        let (rpex, errors) = IndeterminateSumsInRatio::<2>::parse_lenient("1+1");
+
+        assert_eq!(rpex.to_string(), "1+1:");
+        assert_eq!(errors[0].span, 3..3);
+    }
+}
@@ -1,10 +1,41 @@
 mod dimension_sum;
+pub use dimension_sum::{Annotations, DimensionSumEvaluationError, EditError, UnknownStrategy};
+mod export;
+pub use export::{CoordinateMapping, ExportFormat, UnknownExportFormat};
+mod geometry;
+pub use geometry::GeometryStyle;
+mod layout;
+pub use layout::{Cell, Layout, LayoutStats, RefineError, Strip};
+mod lenient;
+pub use lenient::{LenientParseError, SpannedParseError};
+mod limits;
+pub use limits::{ParseLimitError, ParseLimits};
 mod nom_parsable;
+mod os_str;
+pub use os_str::{FromOsStr, OsStrParseError};
 mod parser_combinators;
+pub mod presets;
+#[cfg(feature = "random")]
+mod random;
+#[cfg(feature = "random")]
+pub use random::RandomOptions;
 mod ratio_ext;
 mod rectangle;
-pub use rectangle::HyperRectangle;
+#[cfg(any(test, feature = "test-support"))]
+pub mod reference;
+pub use rectangle::{HyperRectangle, ZeroLength};
+mod spiral;
+pub use spiral::fibonacci_spiral;
+mod splits;
+pub use splits::{Split, SplitDirection};
 mod sums_in_ratio;
-pub use sums_in_ratio::{Partition, SumsInRatioEvaluationError};
+pub use sums_in_ratio::{
+    Partition, PartitionCursor, RpexInferenceError, ScalarDivisionError, SumsInRatioEvaluationError,
+};
+mod tiles;
+pub use tiles::{tile_scheme, Tile};
+
+mod units;
+pub use units::{Px, RatioUnits};
 
 pub type Rpex<const D: usize> = sums_in_ratio::IndeterminateSumsInRatio<D>;
@@ -0,0 +1,125 @@
+use thiserror::Error;
+
+use crate::sums_in_ratio::IndeterminateSumsInRatio;
+
+/// Bounds on the expressions [`IndeterminateSumsInRatio::parse_with_limits`] accepts, so that
+/// parsing untrusted input takes predictable time and memory. Expressions don't nest, so there is
+/// no depth to bound, and addends too large to evaluate fail with
+/// [`crate::DimensionSumEvaluationError::Overflow`] rather than overflowing.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ParseLimits {
+    /// Longest expression in bytes
+    pub max_length: usize,
+    /// Most addends in any one dimension
+    pub max_addends: usize,
+}
+
+impl Default for ParseLimits {
+    fn default() -> Self {
+        ParseLimits {
+            max_length: 4096,
+            max_addends: 256,
+        }
+    }
+}
+
+#[derive(Error, Debug, PartialEq)]
+pub enum ParseLimitError {
+    #[error("expression is {length} bytes long, more than the limit of {max}")]
+    TooLong { length: usize, max: usize },
+    #[error("dimension {dim} has {count} addends, more than the limit of {max}")]
+    TooManyAddends {
+        dim: usize,
+        count: usize,
+        max: usize,
+    },
+    #[error("unable to parse: {0}")]
+    Parse(#[from] nom::error::Error<String>),
+}
+
+impl ParseLimits {
+    /// Checks `input` against the limits without parsing it, which only scans it once.
+    pub fn check(&self, input: &str) -> Result<(), ParseLimitError> {
+        if input.len() > self.max_length {
+            return Err(ParseLimitError::TooLong {
+                length: input.len(),
+                max: self.max_length,
+            });
+        }
+
+        // Neither labels nor annotations can contain separators, so counting them is enough.
+        for (dim, sum) in input.split(':').enumerate() {
+            let count = sum.matches('+').count() + 1;
+
+            if count > self.max_addends {
+                return Err(ParseLimitError::TooManyAddends {
+                    dim,
+                    count,
+                    max: self.max_addends,
+                });
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl<const D: usize> IndeterminateSumsInRatio<D> {
+    /// Like [`str::parse`], but rejects `input` beyond `limits` before parsing it.
+    pub fn parse_with_limits(
+        input: &str,
+        limits: &ParseLimits,
+    ) -> Result<IndeterminateSumsInRatio<D>, ParseLimitError> {
+        limits.check(input)?;
+
+        Ok(input.parse()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn we_can_parse_within_limits() {
+        let limits = ParseLimits {
+            max_length: 16,
+            max_addends: 3,
+        };
+
+        assert_eq!(
+            IndeterminateSumsInRatio::<2>::parse_with_limits("2@main+1+1:1", &limits)
+                .unwrap()
+                .to_string(),
+            "2@main+1+1:1"
+        );
+        assert!(matches!(
+            IndeterminateSumsInRatio::<2>::parse_with_limits("2+x:1", &limits),
+            Err(ParseLimitError::Parse(_))
+        ));
+    }
+
+    #[test]
+    fn we_cannot_parse_beyond_limits() {
+        let limits = ParseLimits {
+            max_length: 16,
+            max_addends: 3,
+        };
+
+        assert_eq!(
+            IndeterminateSumsInRatio::<2>::parse_with_limits("1:1+1+1+1", &limits),
+            Err(ParseLimitError::TooManyAddends {
+                dim: 1,
+                count: 4,
+                max: 3
+            })
+        );
+        assert_eq!(
+            IndeterminateSumsInRatio::<2>::parse_with_limits(&"1".repeat(17), &limits),
+            Err(ParseLimitError::TooLong {
+                length: 17,
+                max: 16
+            })
+        );
+    }
+}
@@ -0,0 +1,81 @@
+use std::ffi::OsStr;
+use std::str::FromStr;
+
+use thiserror::Error;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum OsStrParseError {
+    #[error("{lossy:?} is not valid UTF-8 after byte {valid_up_to}")]
+    NotUtf8 { valid_up_to: usize, lossy: String },
+    #[error("unable to parse: {0}")]
+    Parse(#[from] nom::error::Error<String>),
+}
+
+/// Parses arguments as given by the OS, e.g. from `std::env::args_os`, without first converting
+/// them lossily.
+///
+/// Expressions and rectangles are ASCII, and digits are only ever the ASCII `0`-`9` regardless of
+/// locale, so any argument that isn't UTF-8 is rejected with where its first invalid byte is.
+pub trait FromOsStr: Sized {
+    fn from_os_str(s: &OsStr) -> Result<Self, OsStrParseError>;
+}
+
+impl<T: FromStr<Err = nom::error::Error<String>>> FromOsStr for T {
+    fn from_os_str(s: &OsStr) -> Result<Self, OsStrParseError> {
+        // Encoded bytes are a superset of UTF-8 on every platform, so UTF-8 arguments are read
+        // exactly as they were given.
+        let s =
+            std::str::from_utf8(s.as_encoded_bytes()).map_err(|e| OsStrParseError::NotUtf8 {
+                valid_up_to: e.valid_up_to(),
+                lossy: s.to_string_lossy().into_owned(),
+            })?;
+
+        Ok(s.parse()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{HyperRectangle, Rpex};
+
+    #[test]
+    fn we_can_parse_os_str() {
+        assert_eq!(
+            Rpex::<2>::from_os_str(OsStr::new("2@main+1:1"))
+                .unwrap()
+                .to_string(),
+            "2@main+1:1"
+        );
+        assert_eq!(
+            HyperRectangle::<2>::from_os_str(OsStr::new("1_920x1080")).unwrap(),
+            HyperRectangle {
+                lengths: [1920, 1080]
+            }
+        );
+    }
+
+    #[test]
+    fn we_cannot_parse_non_ascii_digits() {
+        for digits in ["٢+١:١", "２+1:1", "2+1:१"] {
+            assert!(matches!(
+                Rpex::<2>::from_os_str(OsStr::new(digits)),
+                Err(OsStrParseError::Parse(_))
+            ));
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn we_cannot_parse_os_str_that_is_not_utf8() {
+        use std::os::unix::ffi::OsStrExt;
+
+        assert_eq!(
+            Rpex::<2>::from_os_str(OsStr::from_bytes(b"2+1:\xff")).unwrap_err(),
+            OsStrParseError::NotUtf8 {
+                valid_up_to: 4,
+                lossy: "2+1:\u{fffd}".to_string()
+            }
+        );
+    }
+}
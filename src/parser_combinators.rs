@@ -1,4 +1,24 @@
-use nom::{error::ParseError, multi::many_m_n, sequence::pair, Err, IResult, InputLength, Parser};
+use nom::{
+    bytes::complete::take_while,
+    character::complete::digit1,
+    combinator::{map_res, recognize},
+    error::ParseError,
+    multi::many_m_n,
+    sequence::pair,
+    Err, IResult, InputLength, Parser,
+};
+
+/// Parses a `u32` that may have `_` between or after its digits, like Rust literals, e.g.
+/// `1_920`. Only ASCII digits are accepted, whatever the locale.
+pub fn u32_with_separators(input: &str) -> IResult<&str, u32> {
+    map_res(
+        recognize(pair(
+            digit1,
+            take_while(|c: char| c.is_ascii_digit() || c == '_'),
+        )),
+        |digits: &str| digits.replace('_', "").parse::<u32>(),
+    )(input)
+}
 
 pub fn separated_list_m_n<I, O, O2, E, F, G>(
     min: usize,
@@ -0,0 +1,101 @@
+//! Named expressions for common layouts, e.g. to accept `--preset thirds` in place of an
+//! expression.
+
+use std::{fmt::Display, str::FromStr};
+
+use thiserror::Error;
+
+use crate::Rpex;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Preset {
+    /// Two equal columns, `+:`.
+    Halves,
+    /// Three equal columns, `++:`.
+    Thirds,
+    /// Two equal columns of two equal rows, `+:+`.
+    Grid2x2,
+    /// A main column of 70% next to a sidebar of 30%, `7+3:`.
+    Sidebar,
+    /// A centre column as wide as the two columns beside it together, for ultrawide monitors,
+    /// `1+2+1:`.
+    UltrawideThreeWay,
+}
+
+impl Preset {
+    pub const ALL: [Preset; 5] = [
+        Preset::Halves,
+        Preset::Thirds,
+        Preset::Grid2x2,
+        Preset::Sidebar,
+        Preset::UltrawideThreeWay,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Preset::Halves => "halves",
+            Preset::Thirds => "thirds",
+            Preset::Grid2x2 => "grid-2x2",
+            Preset::Sidebar => "sidebar",
+            Preset::UltrawideThreeWay => "ultrawide-three-way",
+        }
+    }
+
+    fn expression(&self) -> &'static str {
+        match self {
+            Preset::Halves => "+:",
+            Preset::Thirds => "++:",
+            Preset::Grid2x2 => "+:+",
+            Preset::Sidebar => "7+3:",
+            Preset::UltrawideThreeWay => "1+2+1:",
+        }
+    }
+
+    pub fn rpex(&self) -> Rpex<2> {
+        self.expression()
+            .parse()
+            .expect("preset expressions are valid")
+    }
+}
+
+#[derive(Error, Debug)]
+#[error("unknown preset {0}")]
+pub struct UnknownPreset(String);
+
+impl FromStr for Preset {
+    type Err = UnknownPreset;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Preset::ALL
+            .into_iter()
+            .find(|preset| preset.name() == s)
+            .ok_or_else(|| UnknownPreset(s.to_string()))
+    }
+}
+
+impl Display for Preset {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl From<Preset> for Rpex<2> {
+    fn from(preset: Preset) -> Self {
+        preset.rpex()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn we_can_evaluate_every_preset() {
+        for preset in Preset::ALL {
+            assert_eq!(Preset::from_str(preset.name()).unwrap(), preset);
+            assert!(preset.rpex().evaluate("3840x1080".parse().unwrap()).is_ok());
+        }
+        assert_eq!(Preset::Sidebar.rpex().to_string(), "7+3:");
+        assert!(Preset::from_str("quarters").is_err());
+    }
+}
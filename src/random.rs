@@ -0,0 +1,98 @@
+use std::ops::RangeInclusive;
+
+use rand::Rng;
+
+use crate::{dimension_sum::IndeterminateDimensionSum, sums_in_ratio::IndeterminateSumsInRatio};
+
+/// What [`IndeterminateSumsInRatio::random_with`] generates addends from.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RandomOptions {
+    /// Chance of each addend being unknown, from 0 to 1.
+    pub unknown_density: f64,
+    /// Values of known addends.
+    pub addends: RangeInclusive<u32>,
+}
+
+impl Default for RandomOptions {
+    fn default() -> RandomOptions {
+        RandomOptions {
+            unknown_density: 0.5,
+            addends: 1..=4,
+        }
+    }
+}
+
+impl<const D: usize> IndeterminateSumsInRatio<D> {
+    /// Generates an expression with a number of addends from `dims` in each dimension, with the
+    /// default [`RandomOptions`].
+    pub fn random(
+        dims: [RangeInclusive<usize>; D],
+        rng: &mut impl Rng,
+    ) -> IndeterminateSumsInRatio<D> {
+        IndeterminateSumsInRatio::random_with(dims, &RandomOptions::default(), rng)
+    }
+
+    /// Generates an expression with a number of addends from `dims` in each dimension. Every
+    /// dimension gets at least one addend. Generated expressions always parse, but like written
+    /// ones, whether they evaluate depends on the rectangle.
+    pub fn random_with(
+        dims: [RangeInclusive<usize>; D],
+        options: &RandomOptions,
+        rng: &mut impl Rng,
+    ) -> IndeterminateSumsInRatio<D> {
+        IndeterminateSumsInRatio {
+            sums: dims.map(|count| {
+                let count = rng.gen_range(count).max(1);
+
+                IndeterminateDimensionSum {
+                    addends: (0..count)
+                        .map(|_| {
+                            (!rng.gen_bool(options.unknown_density))
+                                .then(|| rng.gen_range(options.addends.clone()))
+                        })
+                        .collect(),
+                    ..Default::default()
+                }
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+
+    #[test]
+    fn we_can_generate_random_expressions() {
+        let mut rng = StdRng::seed_from_u64(0);
+
+        for _ in 0..100 {
+            let rpex = IndeterminateSumsInRatio::random([1..=3, 2..=2], &mut rng);
+
+            assert!((1..=3).contains(&rpex.sums[0].addends.len()));
+            assert_eq!(rpex.sums[1].addends.len(), 2);
+            assert!(rpex
+                .sums
+                .iter()
+                .flat_map(|sum| &sum.addends)
+                .all(|addend| addend.is_none_or(|addend| (1..=4).contains(&addend))));
+            assert_eq!(
+                IndeterminateSumsInRatio::from_str(&rpex.to_string()).unwrap(),
+                rpex
+            );
+        }
+
+        let options = RandomOptions {
+            unknown_density: 1.0,
+            ..Default::default()
+        };
+        assert_eq!(
+            IndeterminateSumsInRatio::random_with([2..=2, 1..=1], &options, &mut rng).to_string(),
+            "+:"
+        );
+    }
+}
@@ -1,13 +1,22 @@
-use std::str::FromStr;
+use std::{fmt::Display, num::NonZeroU32, str::FromStr};
+
+use fraction::Integer;
+use itertools::Itertools;
 
 use nom::{
-    character::complete::{char as char_parser, u32 as u32_parser},
+    bytes::complete::{tag_no_case, take_while1},
+    character::complete::{char as char_parser, digit1, u32 as u32_parser},
+    combinator::{map_opt, opt, recognize},
+    sequence::{pair, separated_pair, terminated, tuple},
     IResult,
 };
 
+use thiserror::Error;
+
 use crate::{
-    impl_from_str_for_nom_parsable, nom_parsable::NomParsable,
-    parser_combinators::separated_list_m_n,
+    impl_from_str_for_nom_parsable,
+    nom_parsable::NomParsable,
+    parser_combinators::{separated_list_m_n, u32_with_separators},
 };
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
@@ -15,11 +24,194 @@ pub struct HyperRectangle<const D: usize> {
     pub lengths: [u32; D],
 }
 
+#[derive(Error, Debug, PartialEq, Eq)]
+#[error("rectangle has no length in dimension {0}")]
+pub struct ZeroLength(pub usize);
+
+impl<const D: usize> HyperRectangle<D> {
+    pub fn from_nonzero(lengths: [NonZeroU32; D]) -> HyperRectangle<D> {
+        HyperRectangle {
+            lengths: lengths.map(NonZeroU32::get),
+        }
+    }
+
+    /// Checks that every length is nonzero, e.g. for rectangles reported by disconnected
+    /// monitors, failing with the first dimension that isn't.
+    pub fn nonzero_lengths(&self) -> Result<[NonZeroU32; D], ZeroLength> {
+        let mut lengths = [NonZeroU32::MIN; D];
+
+        for (dim, length) in self.lengths.iter().enumerate() {
+            lengths[dim] = NonZeroU32::new(*length).ok_or(ZeroLength(dim))?;
+        }
+
+        Ok(lengths)
+    }
+
+    /// Scales this rectangle to the largest one with exactly the same ratios that fits within
+    /// `bounds`.
+    ///
+    /// The returned scale multiplies the lengths divided by their greatest common divisor, like
+    /// the scale of an evaluated expression. It is 0, along with the lengths, when not even those
+    /// fit.
+    pub fn fit_within(&self, bounds: HyperRectangle<D>) -> (HyperRectangle<D>, u32) {
+        let gcd = self.lengths.iter().fold(0, |gcd, length| gcd.gcd(length));
+
+        if gcd == 0 {
+            return (*self, 0);
+        }
+
+        let ratio = self.lengths.map(|length| length / gcd);
+
+        let scale = ratio
+            .iter()
+            .zip(bounds.lengths)
+            .filter(|(length, _)| **length != 0)
+            .map(|(length, bound)| bound / length)
+            .min()
+            .unwrap_or(0);
+
+        (
+            HyperRectangle {
+                lengths: ratio.map(|length| length * scale),
+            },
+            scale,
+        )
+    }
+}
+
+// Portrait sizes in tenths of a millimetre, which are exact for both ISO and US sizes.
+const PAPER_SIZES: [(&str, [u32; 2]); 10] = [
+    ("a0", [8410, 11890]),
+    ("a1", [5940, 8410]),
+    ("a2", [4200, 5940]),
+    ("a3", [2970, 4200]),
+    ("a4", [2100, 2970]),
+    ("a5", [1480, 2100]),
+    ("a6", [1050, 1480]),
+    ("letter", [2159, 2794]),
+    ("legal", [2159, 3556]),
+    ("tabloid", [2794, 4318]),
+];
+
+const RESOLUTION_ALIASES: [(&str, [u32; 2]); 5] = [
+    ("720p", [1280, 720]),
+    ("1080p", [1920, 1080]),
+    ("1440p", [2560, 1440]),
+    ("2160p", [3840, 2160]),
+    ("4k", [3840, 2160]),
+];
+
+// Modes are often given with their refresh rate, e.g. `1920x1080@59.94`, which doesn't matter to
+// the size.
+fn refresh_rate(input: &str) -> IResult<&str, Option<&str>> {
+    opt(recognize(tuple((
+        char_parser('@'),
+        digit1,
+        opt(pair(char_parser('.'), digit1)),
+        opt(tag_no_case("hz")),
+    ))))(input)
+}
+
+impl HyperRectangle<2> {
+    /// The portrait size in pixels at `dpi` of the paper size called `name`, e.g. `a4` or
+    /// `letter`, ignoring case. Lengths are rounded to the nearest pixel.
+    ///
+    /// Returns `None` for unknown paper sizes, and for a `dpi` of 0 or one so high that the
+    /// lengths don't fit a `u32`.
+    pub fn paper(name: &str, dpi: u32) -> Option<HyperRectangle<2>> {
+        let (_, tenths_of_mm) = PAPER_SIZES
+            .iter()
+            .find(|(paper, _)| paper.eq_ignore_ascii_case(name))?;
+
+        if dpi == 0 {
+            return None;
+        }
+
+        let [width, height] = tenths_of_mm
+            .map(|length| u32::try_from((u64::from(length) * u64::from(dpi) + 127) / 254).ok());
+
+        Some(HyperRectangle {
+            lengths: [width?, height?],
+        })
+    }
+
+    /// # Panics
+    ///
+    /// Panics if `dpi` is 0, or so high that the lengths don't fit a `u32`.
+    pub fn a3(dpi: u32) -> HyperRectangle<2> {
+        HyperRectangle::paper("a3", dpi).expect("dpi should be positive and fit the size")
+    }
+
+    /// # Panics
+    ///
+    /// Panics if `dpi` is 0, or so high that the lengths don't fit a `u32`.
+    pub fn a4(dpi: u32) -> HyperRectangle<2> {
+        HyperRectangle::paper("a4", dpi).expect("dpi should be positive and fit the size")
+    }
+
+    /// # Panics
+    ///
+    /// Panics if `dpi` is 0, or so high that the lengths don't fit a `u32`.
+    pub fn a5(dpi: u32) -> HyperRectangle<2> {
+        HyperRectangle::paper("a5", dpi).expect("dpi should be positive and fit the size")
+    }
+
+    /// # Panics
+    ///
+    /// Panics if `dpi` is 0, or so high that the lengths don't fit a `u32`.
+    pub fn letter(dpi: u32) -> HyperRectangle<2> {
+        HyperRectangle::paper("letter", dpi).expect("dpi should be positive and fit the size")
+    }
+
+    /// # Panics
+    ///
+    /// Panics if `dpi` is 0, or so high that the lengths don't fit a `u32`.
+    pub fn legal(dpi: u32) -> HyperRectangle<2> {
+        HyperRectangle::paper("legal", dpi).expect("dpi should be positive and fit the size")
+    }
+
+    /// Parses common names of resolutions, e.g. `1080p` or `4k`, ignoring case.
+    fn alias_parser(input: &str) -> IResult<&str, HyperRectangle<2>> {
+        map_opt(take_while1(|c: char| c.is_ascii_alphanumeric()), |alias| {
+            RESOLUTION_ALIASES
+                .iter()
+                .find(|(name, _)| name.eq_ignore_ascii_case(alias))
+                .map(|(_, lengths)| HyperRectangle { lengths: *lengths })
+        })(input)
+    }
+
+    /// Parses paper sizes at a resolution, e.g. `A4@300dpi`.
+    fn paper_parser(input: &str) -> IResult<&str, HyperRectangle<2>> {
+        map_opt(
+            separated_pair(
+                take_while1(|c: char| c.is_ascii_alphanumeric()),
+                char_parser('@'),
+                terminated(u32_parser, tag_no_case("dpi")),
+            ),
+            |(name, dpi)| HyperRectangle::paper(name, dpi),
+        )(input)
+    }
+}
+
 impl<const D: usize> NomParsable for HyperRectangle<D> {
     fn parser(input: &str) -> IResult<&str, HyperRectangle<D>> {
         assert!(D != 0, "0-dimensional HyperRectangles not supported");
 
-        let (input, lengths) = separated_list_m_n(D, D, char_parser('x'), u32_parser)(input)?;
+        if D == 2 {
+            let named = HyperRectangle::paper_parser(input)
+                .or_else(|_| terminated(HyperRectangle::alias_parser, refresh_rate)(input));
+
+            if let Ok((input, rectangle)) = named {
+                let lengths = rectangle.lengths.as_slice().try_into().expect("D is 2");
+
+                return Ok((input, HyperRectangle { lengths }));
+            }
+        }
+
+        let (input, lengths) = terminated(
+            separated_list_m_n(D, D, char_parser('x'), u32_with_separators),
+            refresh_rate,
+        )(input)?;
 
         Ok((
             input,
@@ -35,3 +227,64 @@ impl<const D: usize> NomParsable for HyperRectangle<D> {
 impl<const D: usize> FromStr for HyperRectangle<D> {
     impl_from_str_for_nom_parsable!();
 }
+
+impl<const D: usize> Display for HyperRectangle<D> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.lengths.iter().join("x").as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn we_can_fit_rectangle_within_bounds() {
+        let fit = |rectangle: &str, bounds: &str| {
+            let (fitted, scale) = HyperRectangle::<2>::from_str(rectangle)
+                .unwrap()
+                .fit_within(bounds.parse().unwrap());
+
+            (fitted.to_string(), scale)
+        };
+
+        assert_eq!(fit("1920x1080", "80x24"), ("32x18".to_string(), 2));
+        assert_eq!(fit("16x9", "3840x2160"), ("3840x2160".to_string(), 240));
+        assert_eq!(fit("1921x1080", "80x24"), ("0x0".to_string(), 0));
+    }
+
+    #[test]
+    fn we_can_parse_paper_sizes() {
+        assert_eq!(HyperRectangle::a4(300).lengths, [2480, 3508]);
+        assert_eq!(HyperRectangle::letter(300).lengths, [2550, 3300]);
+        assert_eq!(
+            HyperRectangle::<2>::from_str("A4@300dpi").unwrap(),
+            HyperRectangle::a4(300)
+        );
+        assert_eq!(
+            HyperRectangle::<2>::from_str("letter@72DPI")
+                .unwrap()
+                .lengths,
+            [612, 792]
+        );
+        assert!(HyperRectangle::<2>::from_str("B4@300dpi").is_err());
+        assert!(HyperRectangle::<2>::from_str("A4@0dpi").is_err());
+        assert!(HyperRectangle::<2>::from_str("A3@1100000dpi").is_ok());
+        assert!(HyperRectangle::<2>::from_str("A3@4000000000dpi").is_err());
+        assert!(HyperRectangle::<3>::from_str("A4@300dpi").is_err());
+    }
+
+    #[test]
+    fn we_can_parse_modes_and_aliases() {
+        let parse = |s: &str| HyperRectangle::<2>::from_str(s).map(|rectangle| rectangle.lengths);
+
+        assert_eq!(parse("1920x1080@60").unwrap(), [1920, 1080]);
+        assert_eq!(parse("2560x1440@143.91Hz").unwrap(), [2560, 1440]);
+        assert_eq!(parse("1440p").unwrap(), [2560, 1440]);
+        assert_eq!(parse("4K@120").unwrap(), [3840, 2160]);
+        assert!(parse("1920x1080@").is_err());
+        assert!(parse("1080i").is_err());
+        assert_eq!(parse("3_840x2_160").unwrap(), [3840, 2160]);
+        assert!(parse("_3840x2160").is_err());
+    }
+}
@@ -0,0 +1,180 @@
+//! A slow reference implementation of partitioning, to test faster or extended evaluation
+//! against.
+//!
+//! Every unit cell of the ratio grid is assigned to the addend it falls in along each dimension,
+//! found by walking the addends, and partitions are the groups of unit cells sharing addends.
+
+use std::collections::BTreeMap;
+
+use crate::{
+    layout::{Cell, Layout},
+    rectangle::HyperRectangle,
+    sums_in_ratio::{Partition, SumsInRatio},
+    units::RatioUnits,
+    Rpex,
+};
+
+fn addend_index(addends: &[u32], unit: u32) -> usize {
+    let mut end = 0;
+
+    for (index, addend) in addends.iter().enumerate() {
+        end += addend;
+
+        if unit < end {
+            return index;
+        }
+    }
+
+    unreachable!("unit {unit} is within the sum of the addends")
+}
+
+/// The position and ratio of every partition, in the order of [`SumsInRatio::iter_partitions`].
+pub fn reference_partitions<const D: usize>(
+    sums_in_ratio: &SumsInRatio<D>,
+) -> Vec<([u32; D], [u32; D])> {
+    let addends = sums_in_ratio.sums().each_ref().map(|sum| sum.addends());
+    let totals = sums_in_ratio.totals();
+
+    let mut partitions = BTreeMap::<[usize; D], ([u32; D], [u32; D])>::new();
+    let mut unit = [0; D];
+
+    if totals.contains(&0) {
+        return vec![];
+    }
+
+    loop {
+        let indices = std::array::from_fn(|dim| addend_index(addends[dim], unit[dim]));
+
+        let (start, end) = partitions.entry(indices).or_insert((unit, unit));
+        for dim in 0..D {
+            start[dim] = start[dim].min(unit[dim]);
+            end[dim] = end[dim].max(unit[dim]);
+        }
+
+        // Counts through every unit cell, with the last dimension changing fastest.
+        let Some(dim) = (0..D).rev().find(|&dim| unit[dim] + 1 < totals[dim]) else {
+            break;
+        };
+        unit[dim] += 1;
+        unit[dim + 1..].fill(0);
+    }
+
+    partitions
+        .into_values()
+        .map(|(start, end)| (start, std::array::from_fn(|dim| end[dim] - start[dim] + 1)))
+        .collect()
+}
+
+/// The cells of `layout`, in the order of [`Layout::iter_cells`]. Gutters aren't supported.
+pub fn reference_cells<const D: usize>(layout: &Layout<D>) -> Vec<Cell<D>> {
+    let scale = layout.scale();
+
+    reference_partitions(layout.sums_in_ratio())
+        .into_iter()
+        .map(|(position, ratio)| Cell {
+            position: position.map(|start| start * scale),
+            rectangle: HyperRectangle {
+                lengths: ratio.map(|length| length * scale),
+            },
+        })
+        .collect()
+}
+
+/// Panics unless [`SumsInRatio::iter_partitions`] agrees with [`reference_partitions`].
+pub fn assert_partitions_match_reference<const D: usize>(sums_in_ratio: &SumsInRatio<D>) {
+    let partitions = sums_in_ratio
+        .iter_partitions()
+        .map(
+            |Partition {
+                 ratio_position,
+                 ratio,
+                 ..
+             }| {
+                (
+                    ratio_position.map(|RatioUnits(offset)| offset),
+                    ratio.map(|RatioUnits(addend)| addend),
+                )
+            },
+        )
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        partitions,
+        reference_partitions(sums_in_ratio),
+        "partitions of {sums_in_ratio} differ from the reference"
+    );
+}
+
+/// Evaluates `rpex` on `rectangle`, and panics unless the evaluation fills the rectangle and its
+/// partitions and cells agree with the reference. Expressions that don't evaluate are skipped.
+pub fn assert_evaluation_matches_reference<const D: usize>(
+    rpex: Rpex<D>,
+    rectangle: HyperRectangle<D>,
+) {
+    let Ok((sums_in_ratio, scale)) = rpex.clone().evaluate(rectangle) else {
+        return;
+    };
+
+    assert_eq!(
+        sums_in_ratio.totals().map(|total| total * scale),
+        rectangle.lengths,
+        "{rpex} evaluated to {sums_in_ratio} with scale {scale}, which doesn't fill {rectangle}"
+    );
+
+    assert_partitions_match_reference(&sums_in_ratio);
+
+    let layout = Layout::from((sums_in_ratio, scale));
+    assert_eq!(
+        layout.iter_cells().collect::<Vec<_>>(),
+        reference_cells(&layout),
+        "cells of {rpex} on {rectangle} differ from the reference"
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    #[test]
+    fn evaluation_matches_reference() {
+        for (rpex, rectangle) in [
+            ("+:", "192x108"),
+            ("2@main+1:1+1", "300x200"),
+            ("1+2:", "192x108"),
+            ("+++:+", "384x108"),
+            ("3+1+2:1+3", "60x40"),
+            ("1+1:1+1:2", "4x4x4"),
+        ] {
+            match rectangle.matches('x').count() {
+                1 => assert_evaluation_matches_reference::<2>(
+                    Rpex::from_str(rpex).unwrap(),
+                    rectangle.parse().unwrap(),
+                ),
+                _ => assert_evaluation_matches_reference::<3>(
+                    Rpex::from_str(rpex).unwrap(),
+                    rectangle.parse().unwrap(),
+                ),
+            }
+        }
+    }
+
+    #[test]
+    fn we_can_find_reference_partitions() {
+        let (sums_in_ratio, _) = Rpex::<2>::from_str("2+1:1+1")
+            .unwrap()
+            .evaluate("300x200".parse().unwrap())
+            .unwrap();
+
+        assert_eq!(
+            reference_partitions(&sums_in_ratio),
+            vec![
+                ([0, 0], [2, 1]),
+                ([0, 1], [2, 1]),
+                ([2, 0], [1, 1]),
+                ([2, 1], [1, 1])
+            ]
+        );
+    }
+}
@@ -0,0 +1,363 @@
+use std::io::{self, BufRead};
+use std::str::FromStr;
+
+use clap::{Parser, Subcommand};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout as TuiLayout, Rect};
+use ratatui::style::{Color, Style};
+use ratatui::widgets::{Block, Paragraph};
+use ratatui::{DefaultTerminal, Frame};
+use rpex::presets::Preset;
+use rpex::Cell;
+use rpex::ExportFormat;
+use rpex::HyperRectangle;
+use rpex::Layout;
+use rpex::Rpex;
+use rpex::SpannedParseError;
+use rpex::SumsInRatioEvaluationError;
+use thiserror::Error;
+
+#[derive(Parser)]
+struct RpexArgs {
+    #[command(subcommand)]
+    command: RpexCommand,
+}
+
+#[derive(Subcommand)]
+enum RpexCommand {
+    /// Print the canonical, simplified form of an expression
+    Normalize {
+        /// Expression, or the name of a preset
+        #[arg(value_parser = parse_rpex)]
+        rpex: Rpex<2>,
+        /// Show how the normalized expression is scaled to fit this resolution
+        #[arg(short, long, value_name = "RESOLUTION")]
+        explain: Option<HyperRectangle<2>>,
+    },
+    /// Evaluate an expression for a resolution and print the resulting cells
+    Evaluate {
+        /// Expression, or the name of a preset
+        #[arg(value_parser = parse_rpex)]
+        rpex: Rpex<2>,
+        resolution: HyperRectangle<2>,
+        /// One of json, tsv, shell, xrandr-geometry, zellij, awesome, qtile, i3, html, geojson,
+        /// or cut-list
+        #[arg(short, long, default_value_t = ExportFormat::Tsv)]
+        format: ExportFormat,
+        /// Pixels to leave between neighbouring cells, e.g. the kerf of a cut list
+        #[arg(short, long, default_value_t = 0)]
+        gap: u32,
+    },
+    /// Validate an expression against one or more resolutions
+    Check {
+        /// Expression, or the name of a preset
+        #[arg(value_parser = parse_rpex)]
+        rpex: Rpex<2>,
+        #[arg(required = true)]
+        resolutions: Vec<HyperRectangle<2>>,
+    },
+    /// Read `EXPR [RESOLUTION]` lines from stdin, printing one result per line
+    ///
+    /// Lines with only an expression print its normalized form, lines with a resolution print
+    /// the evaluated expression followed by its scale. Failing lines print an error in place.
+    Batch,
+    /// Interactively edit an expression with a live preview, printing it on exit
+    Edit {
+        resolution: HyperRectangle<2>,
+        /// Expression, or the name of a preset, to start editing from
+        #[arg(value_parser = parse_rpex)]
+        rpex: Option<Rpex<2>>,
+    },
+    /// List the presets that can be given in place of an expression
+    Presets,
+}
+
+// Preset names are never valid expressions, so they can share an argument.
+fn parse_rpex(s: &str) -> Result<Rpex<2>, nom::error::Error<String>> {
+    match Preset::from_str(s) {
+        Ok(preset) => Ok(preset.rpex()),
+        Err(_) => Rpex::from_str(s),
+    }
+}
+
+#[derive(Error, Debug)]
+enum RpexError {
+    #[error(transparent)]
+    RpexEvaluation(#[from] SumsInRatioEvaluationError),
+    #[error("expression failed to evaluate for {0} resolution(s)")]
+    CheckFailed(usize),
+    #[error("{0} line(s) of input failed")]
+    BatchFailed(usize),
+    #[error("encountered io error: {0}")]
+    Io(#[from] io::Error),
+}
+
+#[derive(Error, Debug)]
+enum BatchLineError {
+    #[error("unable to parse: {0}")]
+    Parse(#[from] nom::error::Error<String>),
+    #[error(transparent)]
+    RpexEvaluation(#[from] SumsInRatioEvaluationError),
+    #[error("expected an expression and an optional resolution")]
+    FieldCount,
+}
+
+fn main() -> Result<(), RpexError> {
+    let args = RpexArgs::parse();
+
+    match args.command {
+        RpexCommand::Normalize { rpex, explain } => normalize(rpex, explain),
+        RpexCommand::Evaluate {
+            rpex,
+            resolution,
+            format,
+            gap,
+        } => evaluate(rpex, resolution, format, gap),
+        RpexCommand::Check { rpex, resolutions } => check(rpex, resolutions),
+        RpexCommand::Batch => batch(),
+        RpexCommand::Edit { resolution, rpex } => edit(resolution, rpex),
+        RpexCommand::Presets => {
+            for preset in Preset::ALL {
+                println!("{preset}\t{}", preset.rpex());
+            }
+
+            Ok(())
+        }
+    }
+}
+
+fn normalize(rpex: Rpex<2>, explain: Option<HyperRectangle<2>>) -> Result<(), RpexError> {
+    let normalized = rpex.normalize();
+
+    println!("{normalized}");
+
+    if let Some(resolution) = explain {
+        let (evaluated, scale) = normalized.evaluate(resolution)?;
+
+        println!("{resolution}: {evaluated} with scale {scale}");
+    }
+
+    Ok(())
+}
+
+fn evaluate(
+    rpex: Rpex<2>,
+    resolution: HyperRectangle<2>,
+    format: ExportFormat,
+    gap: u32,
+) -> Result<(), RpexError> {
+    let layout = Layout::from(rpex.evaluate(resolution)?).with_gutter([gap, gap]);
+
+    print!("{}", layout.export(format));
+
+    Ok(())
+}
+
+fn check(rpex: Rpex<2>, resolutions: Vec<HyperRectangle<2>>) -> Result<(), RpexError> {
+    let failures = resolutions
+        .into_iter()
+        .filter(|resolution| match rpex.clone().evaluate(*resolution) {
+            Ok(_) => {
+                println!("{resolution}: ok");
+                false
+            }
+            Err(e) => {
+                println!("{resolution}: {e}");
+                true
+            }
+        })
+        .count();
+
+    if failures != 0 {
+        return Err(RpexError::CheckFailed(failures));
+    }
+
+    Ok(())
+}
+
+fn batch() -> Result<(), RpexError> {
+    let mut failures = 0;
+
+    for line in io::stdin().lock().lines() {
+        match batch_line(&line?) {
+            Ok(result) => println!("{result}"),
+            Err(e) => {
+                println!("error: {e}");
+                failures += 1;
+            }
+        }
+    }
+
+    if failures != 0 {
+        return Err(RpexError::BatchFailed(failures));
+    }
+
+    Ok(())
+}
+
+fn batch_line(line: &str) -> Result<String, BatchLineError> {
+    let fields = line.split_whitespace().collect::<Vec<_>>();
+
+    match fields[..] {
+        [rpex] => Ok(Rpex::<2>::from_str(rpex)?.normalize().to_string()),
+        [rpex, resolution] => {
+            let rpex = Rpex::<2>::from_str(rpex)?;
+            let resolution = HyperRectangle::from_str(resolution)?;
+
+            let (evaluated, scale) = rpex.evaluate(resolution)?;
+
+            Ok(format!("{evaluated} {scale}"))
+        }
+        _ => Err(BatchLineError::FieldCount),
+    }
+}
+
+fn edit(resolution: HyperRectangle<2>, rpex: Option<Rpex<2>>) -> Result<(), RpexError> {
+    let mut editor = ExpressionEditor::new(resolution, rpex);
+
+    let mut terminal = ratatui::init();
+    let result = editor.run(&mut terminal);
+    ratatui::restore();
+
+    if let Some(rpex) = result? {
+        println!("{rpex}");
+    }
+
+    Ok(())
+}
+
+struct ExpressionEditor {
+    resolution: HyperRectangle<2>,
+    input: String,
+    preview: Option<Layout<2>>,
+    error: Option<String>,
+}
+
+impl ExpressionEditor {
+    fn new(resolution: HyperRectangle<2>, rpex: Option<Rpex<2>>) -> ExpressionEditor {
+        let mut editor = ExpressionEditor {
+            resolution,
+            input: rpex.map(|rpex| rpex.to_string()).unwrap_or_default(),
+            preview: None,
+            error: None,
+        };
+
+        editor.update();
+        editor
+    }
+
+    fn evaluate_input(&self) -> Result<(Rpex<2>, Layout<2>), String> {
+        let rpex = Rpex::<2>::from_str(&self.input).map_err(|e| format!("unable to parse: {e}"))?;
+
+        let layout = rpex
+            .clone()
+            .evaluate(self.resolution)
+            .map_err(|e| e.to_string())?;
+
+        Ok((rpex, layout.into()))
+    }
+
+    // Invalid addends are previewed as unknowns, so the preview follows the valid rest of the input
+    // while it is mid-edit. Otherwise it keeps showing the last valid layout.
+    fn update(&mut self) {
+        let (rpex, errors) = Rpex::<2>::parse_lenient(&self.input);
+        let evaluated = rpex.evaluate(self.resolution);
+
+        self.error = match (errors.first(), &evaluated) {
+            (Some(SpannedParseError { span, error }), _) => {
+                Some(format!("unable to parse at {}: {error}", span.start))
+            }
+            (None, Err(e)) => Some(e.to_string()),
+            (None, Ok(_)) => None,
+        };
+
+        if let Ok(layout) = evaluated {
+            self.preview = Some(layout.into());
+        }
+    }
+
+    fn run(&mut self, terminal: &mut DefaultTerminal) -> io::Result<Option<Rpex<2>>> {
+        loop {
+            terminal.draw(|frame| self.draw(frame))?;
+
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+
+            match key.code {
+                KeyCode::Enter => {
+                    if let Ok((rpex, _)) = self.evaluate_input() {
+                        return Ok(Some(rpex));
+                    }
+                }
+                KeyCode::Esc => return Ok(None),
+                KeyCode::Backspace => {
+                    self.input.pop();
+                    self.update();
+                }
+                KeyCode::Char(c) => {
+                    self.input.push(c);
+                    self.update();
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn draw(&self, frame: &mut Frame) {
+        let [input_area, preview_area, status_area] = TuiLayout::vertical([
+            Constraint::Length(3),
+            Constraint::Min(0),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+
+        frame.render_widget(
+            Paragraph::new(self.input.as_str()).block(Block::bordered().title("expression")),
+            input_area,
+        );
+        frame.set_cursor_position((input_area.x + 1 + self.input.len() as u16, input_area.y + 1));
+
+        let preview_block = Block::bordered().title(self.resolution.to_string());
+        let grid_area = preview_block.inner(preview_area);
+        frame.render_widget(preview_block, preview_area);
+
+        if let Some(layout) = &self.preview {
+            for cell in layout.iter_cells() {
+                let cell_area = self.project(cell, grid_area);
+                frame.render_widget(
+                    Block::bordered().title(cell.rectangle.to_string()),
+                    cell_area,
+                );
+            }
+        }
+
+        let status = match &self.error {
+            Some(e) => Paragraph::new(e.as_str()).style(Style::default().fg(Color::Red)),
+            None => Paragraph::new("enter: accept, esc: cancel"),
+        };
+        frame.render_widget(status, status_area);
+    }
+
+    fn project(&self, cell: Cell<2>, area: Rect) -> Rect {
+        let [resolution_width, resolution_height] = self.resolution.lengths.map(u64::from);
+
+        let scale = |px: u32, resolution_length: u64, area_length: u16| {
+            (u64::from(px) * u64::from(area_length) / resolution_length) as u16
+        };
+
+        let [x, y] = cell.position;
+        let [width, height] = cell.rectangle.lengths;
+
+        let left = scale(x, resolution_width, area.width);
+        let right = scale(x + width, resolution_width, area.width);
+        let top = scale(y, resolution_height, area.height);
+        let bottom = scale(y + height, resolution_height, area.height);
+
+        Rect::new(area.x + left, area.y + top, right - left, bottom - top)
+    }
+}
@@ -0,0 +1,422 @@
+use std::collections::HashMap;
+use std::io;
+use std::str::FromStr;
+
+use lsp_server::{Connection, ExtractError, Message, Notification, Request, Response};
+use lsp_types::notification::{
+    DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument,
+    Notification as NotificationTrait, PublishDiagnostics,
+};
+use lsp_types::request::{Completion, HoverRequest, Request as RequestTrait};
+use lsp_types::{
+    CompletionItem, CompletionItemKind, CompletionOptions, CompletionParams, CompletionResponse,
+    Diagnostic, DiagnosticSeverity, Hover, HoverContents, HoverParams, HoverProviderCapability,
+    InitializeParams, MarkupContent, MarkupKind, Position, PublishDiagnosticsParams, Range,
+    ServerCapabilities, TextDocumentSyncCapability, TextDocumentSyncKind, Uri,
+};
+use rpex::presets::Preset;
+use rpex::{HyperRectangle, Rpex, SpannedParseError};
+use thiserror::Error;
+
+const DEFAULT_RESOLUTION: HyperRectangle<2> = HyperRectangle {
+    lengths: [1920, 1080],
+};
+
+#[derive(Error, Debug)]
+enum RpexLspError {
+    #[error(transparent)]
+    Protocol(#[from] lsp_server::ProtocolError),
+    #[error("invalid message: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("the client disconnected")]
+    Disconnected,
+    #[error("encountered io error: {0}")]
+    Io(#[from] io::Error),
+}
+
+/// An expression in a string literal, with its range in UTF-16 code units like LSP positions.
+#[derive(Debug, PartialEq, Eq)]
+struct Embedded<'a> {
+    line: u32,
+    start: u32,
+    end: u32,
+    text: &'a str,
+}
+
+impl Embedded<'_> {
+    fn range(&self) -> Range {
+        Range {
+            start: Position::new(self.line, self.start),
+            end: Position::new(self.line, self.end),
+        }
+    }
+
+    // Expressions are ASCII, so byte offsets in them are also UTF-16 offsets.
+    fn subrange(&self, span: std::ops::Range<usize>) -> Range {
+        Range {
+            start: Position::new(self.line, self.start + span.start as u32),
+            end: Position::new(self.line, self.start + span.end as u32),
+        }
+    }
+
+    fn contains(&self, position: Position) -> bool {
+        position.line == self.line && (self.start..=self.end).contains(&position.character)
+    }
+}
+
+// Every two-dimensional expression has a `:`, and nothing but digits, labels, annotations and
+// separators, which tells them apart from the other strings of config files well enough.
+fn looks_like_rpex(text: &str) -> bool {
+    text.contains(':')
+        && text
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "+:@_[]=,.-".contains(c))
+}
+
+// Both basic and literal TOML strings are searched, and escapes are ignored since expressions
+// never have any.
+fn embedded_expressions(text: &str) -> Vec<Embedded<'_>> {
+    let mut expressions = vec![];
+
+    for (line, contents) in text.lines().enumerate() {
+        let mut column = 0;
+        let mut open: Option<(char, usize, u32)> = None;
+
+        for (index, c) in contents.char_indices() {
+            match open {
+                Some((quote, start, start_column)) if c == quote => {
+                    let text = &contents[start..index];
+
+                    if looks_like_rpex(text) {
+                        expressions.push(Embedded {
+                            line: line as u32,
+                            start: start_column,
+                            end: column,
+                            text,
+                        });
+                    }
+
+                    open = None;
+                }
+                None if c == '"' || c == '\'' => open = Some((c, index + 1, column + 1)),
+                None if c == '#' => break,
+                _ => {}
+            }
+
+            column += c.len_utf16() as u32;
+        }
+    }
+
+    expressions
+}
+
+// Every mistake in an expression is reported where it is, and only valid expressions are
+// evaluated.
+fn diagnostics(text: &str, resolution: HyperRectangle<2>) -> Vec<Diagnostic> {
+    embedded_expressions(text)
+        .into_iter()
+        .flat_map(|embedded| {
+            let diagnostic = |span: Option<std::ops::Range<usize>>, severity, message| Diagnostic {
+                range: span.map_or(embedded.range(), |span| embedded.subrange(span)),
+                severity: Some(severity),
+                source: Some("rpex".to_string()),
+                message,
+                ..Default::default()
+            };
+
+            let (rpex, errors) = Rpex::<2>::parse_lenient(embedded.text);
+
+            if !errors.is_empty() {
+                return errors
+                    .into_iter()
+                    .map(|SpannedParseError { span, error }| {
+                        diagnostic(
+                            Some(span),
+                            DiagnosticSeverity::ERROR,
+                            format!("unable to parse expression: {error}"),
+                        )
+                    })
+                    .collect();
+            }
+
+            match rpex.evaluate(resolution) {
+                Ok(_) => vec![],
+                Err(e) => vec![diagnostic(
+                    None,
+                    DiagnosticSeverity::WARNING,
+                    format!("unable to evaluate expression on {resolution}: {e}"),
+                )],
+            }
+        })
+        .collect()
+}
+
+// Every addend is resolved to pixels, so the hover shows the size of each column and row.
+fn hover(text: &str, position: Position, resolution: HyperRectangle<2>) -> Option<Hover> {
+    let embedded = embedded_expressions(text)
+        .into_iter()
+        .find(|embedded| embedded.contains(position))?;
+    let rpex = Rpex::<2>::from_str(embedded.text).ok()?;
+
+    let value = match rpex.clone().resolve_unknowns(resolution) {
+        Ok(resolved) => format!("`{rpex}` on {resolution} is `{resolved}` in pixels"),
+        Err(e) => format!("`{rpex}` doesn't evaluate on {resolution}: {e}"),
+    };
+
+    Some(Hover {
+        contents: HoverContents::Markup(MarkupContent {
+            kind: MarkupKind::Markdown,
+            value,
+        }),
+        range: Some(embedded.range()),
+    })
+}
+
+fn completions() -> Vec<CompletionItem> {
+    Preset::ALL
+        .into_iter()
+        .map(|preset| CompletionItem {
+            label: preset.name().to_string(),
+            kind: Some(CompletionItemKind::VALUE),
+            detail: Some(preset.rpex().to_string()),
+            insert_text: Some(preset.rpex().to_string()),
+            ..Default::default()
+        })
+        .collect()
+}
+
+// Completions are only offered inside strings, found by the number of quotes before the cursor.
+fn in_string(text: &str, position: Position) -> bool {
+    let Some(line) = text.lines().nth(position.line as usize) else {
+        return false;
+    };
+
+    let mut column = 0;
+    let mut quotes = 0;
+
+    for c in line.chars() {
+        if column >= position.character {
+            break;
+        }
+        if c == '"' || c == '\'' {
+            quotes += 1;
+        }
+        column += c.len_utf16() as u32;
+    }
+
+    quotes % 2 == 1
+}
+
+struct Server {
+    connection: Connection,
+    resolution: HyperRectangle<2>,
+    documents: HashMap<String, String>,
+}
+
+impl Server {
+    fn send(&self, message: impl Into<Message>) -> Result<(), RpexLspError> {
+        self.connection
+            .sender
+            .send(message.into())
+            .map_err(|_| RpexLspError::Disconnected)
+    }
+
+    fn publish_diagnostics(&self, uri: Uri) -> Result<(), RpexLspError> {
+        let diagnostics = self
+            .documents
+            .get(uri.as_str())
+            .map(|text| diagnostics(text, self.resolution))
+            .unwrap_or_default();
+
+        self.send(Notification::new(
+            PublishDiagnostics::METHOD.to_string(),
+            PublishDiagnosticsParams {
+                uri,
+                diagnostics,
+                version: None,
+            },
+        ))
+    }
+
+    fn handle_request(&self, request: Request) -> Result<(), RpexLspError> {
+        let request = match request.extract::<HoverParams>(HoverRequest::METHOD) {
+            Ok((id, params)) => {
+                let position = params.text_document_position_params;
+                let result = self
+                    .documents
+                    .get(position.text_document.uri.as_str())
+                    .and_then(|text| hover(text, position.position, self.resolution));
+
+                return self.send(Response::new_ok(id, result));
+            }
+            Err(ExtractError::MethodMismatch(request)) => request,
+            Err(ExtractError::JsonError { error, .. }) => return Err(error.into()),
+        };
+
+        let request = match request.extract::<CompletionParams>(Completion::METHOD) {
+            Ok((id, params)) => {
+                let position = params.text_document_position;
+                let items = self
+                    .documents
+                    .get(position.text_document.uri.as_str())
+                    .filter(|text| in_string(text, position.position))
+                    .map(|_| completions())
+                    .unwrap_or_default();
+
+                return self.send(Response::new_ok(id, CompletionResponse::Array(items)));
+            }
+            Err(ExtractError::MethodMismatch(request)) => request,
+            Err(ExtractError::JsonError { error, .. }) => return Err(error.into()),
+        };
+
+        self.send(Response::new_err(
+            request.id,
+            lsp_server::ErrorCode::MethodNotFound as i32,
+            format!("unsupported request {}", request.method),
+        ))
+    }
+
+    // Documents are synced in full, so every change carries the whole text.
+    fn handle_notification(&mut self, notification: Notification) -> Result<(), RpexLspError> {
+        match notification.method.as_str() {
+            DidOpenTextDocument::METHOD => {
+                let params: lsp_types::DidOpenTextDocumentParams =
+                    serde_json::from_value(notification.params)?;
+                let uri = params.text_document.uri;
+
+                self.documents
+                    .insert(uri.as_str().to_string(), params.text_document.text);
+                self.publish_diagnostics(uri)
+            }
+            DidChangeTextDocument::METHOD => {
+                let params: lsp_types::DidChangeTextDocumentParams =
+                    serde_json::from_value(notification.params)?;
+                let uri = params.text_document.uri;
+
+                if let Some(change) = params.content_changes.into_iter().last() {
+                    self.documents.insert(uri.as_str().to_string(), change.text);
+                }
+                self.publish_diagnostics(uri)
+            }
+            DidCloseTextDocument::METHOD => {
+                let params: lsp_types::DidCloseTextDocumentParams =
+                    serde_json::from_value(notification.params)?;
+
+                self.documents.remove(params.text_document.uri.as_str());
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn run(mut self) -> Result<(), RpexLspError> {
+        while let Ok(message) = self.connection.receiver.recv() {
+            match message {
+                Message::Request(request) => {
+                    if self.connection.handle_shutdown(&request)? {
+                        return Ok(());
+                    }
+
+                    self.handle_request(request)?;
+                }
+                Message::Notification(notification) => self.handle_notification(notification)?,
+                Message::Response(_) => {}
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Serves diagnostics, hovers and completions for expressions in strings over stdio.
+///
+/// Expressions are evaluated on the `resolution` initialization option, e.g. `"2560x1440"`, or
+/// 1920x1080 without one.
+fn main() -> Result<(), RpexLspError> {
+    let (connection, io_threads) = Connection::stdio();
+
+    let capabilities = ServerCapabilities {
+        text_document_sync: Some(TextDocumentSyncCapability::Kind(TextDocumentSyncKind::FULL)),
+        hover_provider: Some(HoverProviderCapability::Simple(true)),
+        completion_provider: Some(CompletionOptions {
+            trigger_characters: Some(vec!["\"".to_string(), "'".to_string()]),
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+
+    let params: InitializeParams =
+        serde_json::from_value(connection.initialize(serde_json::to_value(capabilities)?)?)?;
+
+    let resolution = params
+        .initialization_options
+        .as_ref()
+        .and_then(|options| options.get("resolution")?.as_str()?.parse().ok())
+        .unwrap_or(DEFAULT_RESOLUTION);
+
+    Server {
+        connection,
+        resolution,
+        documents: HashMap::new(),
+    }
+    .run()?;
+
+    io_threads.join()?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = "[monitors]\n\
+                          \"DP-1\" = \"2+1:1\" # main\n\
+                          HDMI-1 = '+:x+'\n\
+                          name_template = \"{parent}-{x}\"\n";
+
+    #[test]
+    fn we_can_find_embedded_expressions() {
+        assert_eq!(
+            embedded_expressions(CONFIG)
+                .into_iter()
+                .map(|embedded| (embedded.line, embedded.start, embedded.end, embedded.text))
+                .collect::<Vec<_>>(),
+            vec![(1, 10, 15, "2+1:1"), (2, 10, 14, "+:x+")]
+        );
+    }
+
+    #[test]
+    fn we_can_diagnose_embedded_expressions() {
+        let diagnostics = diagnostics(CONFIG, DEFAULT_RESOLUTION);
+
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(diagnostics[0].severity, Some(DiagnosticSeverity::WARNING));
+        assert_eq!(diagnostics[1].severity, Some(DiagnosticSeverity::ERROR));
+        assert_eq!(
+            diagnostics[1].range,
+            Range {
+                start: Position::new(2, 12),
+                end: Position::new(2, 13),
+            }
+        );
+
+        let Some(Hover {
+            contents: HoverContents::Markup(MarkupContent { value, .. }),
+            ..
+        }) = hover(
+            "a = \"+:1\"",
+            Position::new(0, 6),
+            HyperRectangle {
+                lengths: [1920, 1080],
+            },
+        )
+        else {
+            panic!("expected a hover");
+        };
+
+        assert_eq!(value, "`+:1` on 1920x1080 is `960+960:1080` in pixels");
+        assert!(in_string("a = \"+", Position::new(0, 6)));
+        assert!(!in_string("a = \"+\"", Position::new(0, 7)));
+    }
+}
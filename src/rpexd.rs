@@ -0,0 +1,447 @@
+use std::any::Any;
+use std::collections::HashMap;
+use std::io::{self, Read};
+use std::panic::{self, AssertUnwindSafe};
+use std::str::FromStr;
+
+use clap::Parser;
+use rpex::presets::Preset;
+use rpex::{
+    Cell, HyperRectangle, Layout, ParseLimitError, ParseLimits, Rpex, SpannedParseError,
+    SumsInRatioEvaluationError,
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use thiserror::Error;
+use tiny_http::{Header, Method, Response, Server};
+
+/// Serve the evaluation of expressions over HTTP
+///
+/// `POST /evaluate` takes a JSON object with an `rpex` (or the name of a preset), a `rectangle`
+/// and an optional `gap`, and returns the evaluated cells, or only `limit` of them starting at
+/// `offset`. `GET /validate?rpex=...` returns the mistakes in an expression, and also whether it
+/// evaluates when given a `rectangle`.
+#[derive(Parser)]
+struct RpexdArgs {
+    /// Address to listen on
+    #[arg(short, long, env = "RPEXD_LISTEN", default_value = "127.0.0.1:8080")]
+    listen: String,
+    /// Longest expression to accept, in bytes
+    #[arg(long, env = "RPEXD_MAX_LENGTH", default_value_t = ParseLimits::default().max_length)]
+    max_length: usize,
+    /// Most addends to accept in any one dimension of an expression
+    #[arg(long, env = "RPEXD_MAX_ADDENDS", default_value_t = ParseLimits::default().max_addends)]
+    max_addends: usize,
+    /// Largest request body to read, in bytes
+    #[arg(long, env = "RPEXD_MAX_BODY", default_value_t = 64 * 1024)]
+    max_body: u64,
+}
+
+#[derive(Error, Debug)]
+enum RpexdError {
+    #[error("unable to listen: {0}")]
+    Listen(Box<dyn std::error::Error + Send + Sync>),
+}
+
+#[derive(Error, Debug)]
+enum RequestError {
+    #[error("no route for {0} {1}")]
+    NotFound(Method, String),
+    #[error("unable to read request: {0}")]
+    Io(#[from] io::Error),
+    #[error("request body is larger than {0} bytes")]
+    BodyTooLarge(u64),
+    #[error("invalid request body: {0}")]
+    Body(#[from] serde_json::Error),
+    #[error("unable to parse: {0}")]
+    Parse(#[from] nom::error::Error<String>),
+    #[error(transparent)]
+    Limit(#[from] ParseLimitError),
+    #[error("missing query parameter {0}")]
+    MissingParameter(&'static str),
+    #[error(transparent)]
+    RpexEvaluation(#[from] SumsInRatioEvaluationError),
+    #[error("unable to handle request: {0}")]
+    Panic(String),
+}
+
+impl RequestError {
+    fn status(&self) -> u16 {
+        match self {
+            RequestError::NotFound(..) => 404,
+            RequestError::Panic(_) => 500,
+            RequestError::RpexEvaluation(_) => 422,
+            RequestError::Limit(ParseLimitError::Parse(_)) => 400,
+            RequestError::Limit(_) | RequestError::BodyTooLarge(_) => 413,
+            _ => 400,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct EvaluateRequest {
+    rpex: String,
+    rectangle: String,
+    #[serde(default)]
+    gap: u32,
+    #[serde(default)]
+    offset: usize,
+    limit: Option<usize>,
+}
+
+// Preset names are never valid expressions, so they can share a field.
+fn parse_rpex(s: &str, limits: &ParseLimits) -> Result<Rpex<2>, ParseLimitError> {
+    match Preset::from_str(s) {
+        Ok(preset) => Ok(preset.rpex()),
+        Err(_) => Rpex::parse_with_limits(s, limits),
+    }
+}
+
+// Ids are given as hex strings, since JSON numbers can't hold every u64.
+fn evaluate(
+    EvaluateRequest {
+        rpex,
+        rectangle,
+        gap,
+        offset,
+        limit,
+    }: EvaluateRequest,
+    limits: &ParseLimits,
+) -> Result<Value, RequestError> {
+    let rpex = parse_rpex(&rpex, limits)?;
+    let rectangle = HyperRectangle::<2>::from_str(&rectangle)?;
+    let layout = Layout::from(rpex.clone().evaluate(rectangle)?).with_gutter([gap; 2]);
+
+    let cells = layout
+        .iter_cells_from(offset)
+        .zip(layout.sums_in_ratio().iter_partitions_from(offset))
+        .take(limit.unwrap_or(usize::MAX))
+        .map(
+            |(
+                Cell {
+                    position: [x, y],
+                    rectangle,
+                },
+                partition,
+            )| {
+                let [width, height] = rectangle.lengths;
+
+                json!({
+                    "x": x,
+                    "y": y,
+                    "width": width,
+                    "height": height,
+                    "label": partition.label(),
+                    "id": format!("{:016x}", partition.id()),
+                })
+            },
+        )
+        .collect::<Vec<_>>();
+
+    Ok(json!({
+        "rpex": rpex.to_string(),
+        "rectangle": rectangle.to_string(),
+        "scale": layout.scale(),
+        "total": layout.sums_in_ratio().partition_count(),
+        "cells": cells,
+    }))
+}
+
+fn percent_decode(s: &str) -> String {
+    let mut bytes = vec![];
+    let mut rest = s.as_bytes();
+
+    while let Some((&byte, tail)) = rest.split_first() {
+        let escaped = tail
+            .get(..2)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+
+        match escaped {
+            Some(escaped) if byte == b'%' => {
+                bytes.push(escaped);
+                rest = &tail[2..];
+            }
+            _ => {
+                bytes.push(byte);
+                rest = tail;
+            }
+        }
+    }
+
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+// Reads one byte past `max`, to tell a body of exactly `max` bytes from a larger one without
+// reading the rest of it.
+fn read_body(reader: impl Read, max: u64) -> Result<String, RequestError> {
+    let mut body = String::new();
+    reader.take(max + 1).read_to_string(&mut body)?;
+
+    if body.len() as u64 > max {
+        return Err(RequestError::BodyTooLarge(max));
+    }
+
+    Ok(body)
+}
+
+// Unlike in forms, `+` is left as is rather than read as a space, since expressions are full of
+// them.
+fn query_parameters(query: &str) -> HashMap<String, String> {
+    query
+        .split('&')
+        .filter(|parameter| !parameter.is_empty())
+        .map(|parameter| {
+            let (name, value) = parameter.split_once('=').unwrap_or((parameter, ""));
+
+            (percent_decode(name), percent_decode(value))
+        })
+        .collect()
+}
+
+fn validate(query: &str, limits: &ParseLimits) -> Result<Value, RequestError> {
+    let parameters = query_parameters(query);
+    let rpex = parameters
+        .get("rpex")
+        .ok_or(RequestError::MissingParameter("rpex"))?;
+
+    let (parsed, errors) = match Preset::from_str(rpex) {
+        Ok(preset) => (preset.rpex(), vec![]),
+        Err(_) => {
+            limits.check(rpex)?;
+            Rpex::<2>::parse_lenient(rpex)
+        }
+    };
+
+    let mut errors = errors
+        .into_iter()
+        .map(|SpannedParseError { span, error }| {
+            json!({ "start": span.start, "end": span.end, "message": error.to_string() })
+        })
+        .collect::<Vec<_>>();
+
+    if let (true, Some(rectangle)) = (errors.is_empty(), parameters.get("rectangle")) {
+        if let Err(e) = parsed.evaluate(HyperRectangle::from_str(rectangle)?) {
+            errors.push(json!({ "message": e.to_string() }));
+        }
+    }
+
+    Ok(json!({ "valid": errors.is_empty(), "errors": errors }))
+}
+
+fn route(
+    method: &Method,
+    url: &str,
+    body: &str,
+    limits: &ParseLimits,
+) -> Result<Value, RequestError> {
+    let (path, query) = url.split_once('?').unwrap_or((url, ""));
+
+    match (method, path) {
+        (Method::Post, "/evaluate") => evaluate(serde_json::from_str(body)?, limits),
+        (Method::Get, "/validate") => validate(query, limits),
+        _ => Err(RequestError::NotFound(method.clone(), path.to_string())),
+    }
+}
+
+fn panic_message(panic: &(dyn Any + Send)) -> String {
+    panic
+        .downcast_ref::<&str>()
+        .map(|message| message.to_string())
+        .or_else(|| panic.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "evaluation panicked".to_string())
+}
+
+fn main() -> Result<(), RpexdError> {
+    let args = RpexdArgs::parse();
+    let limits = ParseLimits {
+        max_length: args.max_length,
+        max_addends: args.max_addends,
+    };
+
+    let server = Server::http(&args.listen).map_err(RpexdError::Listen)?;
+    let content_type = Header::from_bytes("Content-Type", "application/json")
+        .expect("content type header is valid");
+
+    for mut request in server.incoming_requests() {
+        let result = read_body(request.as_reader(), args.max_body).and_then(|body| {
+            // A bug in evaluating one request shouldn't take down the service for every other
+            // client. Requests share no state, so there is nothing a panic could leave broken.
+            panic::catch_unwind(AssertUnwindSafe(|| {
+                route(request.method(), request.url(), &body, &limits)
+            }))
+            .unwrap_or_else(|panic| Err(RequestError::Panic(panic_message(&*panic))))
+        });
+
+        let (status, value) = match result {
+            Ok(value) => (200, value),
+            Err(e) => (e.status(), json!({ "error": e.to_string() })),
+        };
+
+        let response = Response::from_string(value.to_string())
+            .with_status_code(status)
+            .with_header(content_type.clone());
+
+        // The client hanging up only affects its own request.
+        if let Err(e) = request.respond(response) {
+            eprintln!("unable to respond: {e}");
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn we_can_evaluate_over_http() {
+        let value = route(
+            &Method::Post,
+            "/evaluate",
+            r#"{"rpex": "1@left+1:1", "rectangle": "200x100"}"#,
+            &ParseLimits::default(),
+        )
+        .unwrap();
+
+        assert_eq!(value["scale"], 100);
+        assert_eq!(value["cells"][1]["x"], 100);
+        assert_eq!(value["cells"][0]["label"], "left");
+        assert_eq!(value["cells"][1]["label"], Value::Null);
+
+        let value = route(
+            &Method::Post,
+            "/evaluate",
+            r#"{"rpex": "1+1+1:1", "rectangle": "300x100", "offset": 1, "limit": 1}"#,
+            &ParseLimits::default(),
+        )
+        .unwrap();
+
+        assert_eq!(value["total"], 3);
+        assert_eq!(value["cells"].as_array().unwrap().len(), 1);
+        assert_eq!(value["cells"][0]["x"], 100);
+
+        let error = route(
+            &Method::Post,
+            "/evaluate",
+            r#"{"rpex": "1+1:1", "rectangle": "300x100"}"#,
+            &ParseLimits::default(),
+        )
+        .unwrap_err();
+
+        assert_eq!(error.status(), 422);
+        assert_eq!(
+            route(&Method::Get, "/evaluate", "", &ParseLimits::default())
+                .unwrap_err()
+                .status(),
+            404
+        );
+    }
+
+    #[test]
+    fn we_cannot_overflow_evaluation_over_http() {
+        let status = |body| {
+            route(&Method::Post, "/evaluate", body, &ParseLimits::default())
+                .map_or_else(|e| e.status(), |_| 200)
+        };
+
+        for body in [
+            r#"{"rpex": "4000000000+4000000000:1", "rectangle": "300x100"}"#,
+            r#"{"rpex": "1:3+", "rectangle": "1080x1080"}"#,
+            r#"{"rpex": "+:", "rectangle": "A3@1100000dpi"}"#,
+        ] {
+            assert_eq!(status(body), 422);
+        }
+        assert_eq!(
+            status(r#"{"rpex": "+:", "rectangle": "A3@4000000000dpi"}"#),
+            400
+        );
+        assert_eq!(
+            status(r#"{"rpex": "+:", "rectangle": "300x100", "gap": 4000000000}"#),
+            200
+        );
+    }
+
+    #[test]
+    fn we_cannot_send_bodies_over_the_limit() {
+        assert_eq!(read_body(&b"{}"[..], 2).unwrap(), "{}");
+        assert!(matches!(
+            read_body(&b"{} "[..], 2),
+            Err(RequestError::BodyTooLarge(2))
+        ));
+    }
+
+    #[test]
+    fn we_cannot_exceed_parse_limits_over_http() {
+        let limits = ParseLimits {
+            max_length: 8,
+            max_addends: 2,
+        };
+
+        let error = route(
+            &Method::Post,
+            "/evaluate",
+            r#"{"rpex": "1+1+1:1", "rectangle": "300x100"}"#,
+            &limits,
+        )
+        .unwrap_err();
+
+        assert_eq!(error.status(), 413);
+        assert_eq!(
+            route(&Method::Get, "/validate?rpex=1:1%2B1%2B1", "", &limits)
+                .unwrap_err()
+                .status(),
+            413
+        );
+        assert_eq!(
+            route(
+                &Method::Post,
+                "/evaluate",
+                r#"{"rpex": "1+x:1", "rectangle": "300x100"}"#,
+                &limits,
+            )
+            .unwrap_err()
+            .status(),
+            400
+        );
+    }
+
+    #[test]
+    fn we_can_validate_over_http() {
+        assert_eq!(
+            route(
+                &Method::Get,
+                "/validate?rpex=2+x%3A1",
+                "",
+                &ParseLimits::default()
+            )
+            .unwrap(),
+            json!({
+                "valid": false,
+                "errors": [{ "start": 2, "end": 3, "message": "invalid addend \"x\"" }],
+            })
+        );
+        assert_eq!(
+            route(
+                &Method::Get,
+                "/validate?rpex=2%2B1:1&rectangle=300x100",
+                "",
+                &ParseLimits::default()
+            )
+            .unwrap()["valid"],
+            true
+        );
+        assert_eq!(
+            route(
+                &Method::Get,
+                "/validate?rpex=2+1:1&rectangle=200x100",
+                "",
+                &ParseLimits::default()
+            )
+            .unwrap()["valid"],
+            false
+        );
+    }
+}
@@ -0,0 +1,111 @@
+use crate::{
+    dimension_sum::UnknownStrategy,
+    layout::{Cell, Layout},
+    rectangle::HyperRectangle,
+    Rpex,
+};
+
+/// Splits `rectangle` like a Fibonacci spiral, halving what is left of it `depth` times, turning
+/// clockwise from the left, then top, right and bottom half.
+///
+/// Yields `depth + 1` cells, the half kept by each split in order followed by what is left. Odd
+/// lengths give the extra pixel to the right or bottom half. Splitting stops early once what is
+/// left is less than 2 pixels across the side to halve, so small rectangles yield fewer cells.
+pub fn fibonacci_spiral(rectangle: HyperRectangle<2>, depth: usize) -> Vec<Cell<2>> {
+    let splits: [Rpex<2>; 2] = ["+:", ":+"].map(|rpex| rpex.parse().expect("rpex is valid"));
+    let mut cells = vec![];
+    let mut rest = Cell {
+        position: [0, 0],
+        rectangle,
+    };
+
+    for step in 0..depth {
+        if rest.rectangle.lengths[step % 2] < 2 {
+            break;
+        }
+
+        let (sums_in_ratio, scale) = splits[step % 2]
+            .clone()
+            .evaluate_with_strategy(rest.rectangle, UnknownStrategy::FillLast)
+            .expect("halves fit any rectangle");
+        let [first, second] = Layout::from((sums_in_ratio, scale))
+            .iter_cells()
+            .map(|cell| Cell {
+                position: [0, 1].map(|dim| rest.position[dim] + cell.position[dim]),
+                rectangle: cell.rectangle,
+            })
+            .collect::<Vec<_>>()
+            .try_into()
+            .expect("halves have two cells");
+
+        // The spiral keeps the first half on its way down and right, and the second on its way
+        // back up and left.
+        let (kept, left) = if step % 4 < 2 {
+            (first, second)
+        } else {
+            (second, first)
+        };
+
+        cells.push(kept);
+        rest = left;
+    }
+
+    cells.push(rest);
+
+    cells
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn we_can_split_rectangles_like_a_spiral() {
+        let cell = |position, lengths| Cell {
+            position,
+            rectangle: HyperRectangle { lengths },
+        };
+
+        assert_eq!(
+            fibonacci_spiral(
+                HyperRectangle {
+                    lengths: [800, 400]
+                },
+                4
+            ),
+            vec![
+                cell([0, 0], [400, 400]),
+                cell([400, 0], [400, 200]),
+                cell([600, 200], [200, 200]),
+                cell([400, 300], [200, 100]),
+                cell([400, 200], [200, 100]),
+            ]
+        );
+        assert_eq!(
+            fibonacci_spiral(HyperRectangle { lengths: [7, 5] }, 0).len(),
+            1
+        );
+    }
+
+    #[test]
+    fn we_can_stop_spirals_that_run_out_of_pixels() {
+        let cells = fibonacci_spiral(
+            HyperRectangle {
+                lengths: [1920, 1080],
+            },
+            30,
+        );
+
+        assert_eq!(cells.len(), 22);
+        assert!(cells
+            .iter()
+            .all(|cell| cell.rectangle.lengths.iter().all(|&length| length != 0)));
+        assert_eq!(
+            cells
+                .iter()
+                .map(|cell| cell.rectangle.lengths.iter().product::<u32>())
+                .sum::<u32>(),
+            1920 * 1080
+        );
+    }
+}
@@ -0,0 +1,95 @@
+use crate::Rpex;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SplitDirection {
+    Right,
+    Down,
+}
+
+/// Splits `pane` in two, giving `percent` of it to a new pane on its right or below it.
+///
+/// Panes are numbered in the order they are created, starting from 0 for the pane that is split
+/// first, so the `n`th split creates pane `n + 1`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Split {
+    pub pane: usize,
+    pub direction: SplitDirection,
+    pub percent: u32,
+}
+
+// Each split takes what is left of a column or row for the new pane, so the percentage is that of
+// the remainder after the pane being split.
+fn split_off(
+    pane: usize,
+    direction: SplitDirection,
+    weights: &[u32],
+    splits: &mut Vec<Split>,
+) -> Vec<usize> {
+    let mut panes = vec![pane];
+
+    for index in 1..weights.len() {
+        let remainder: u32 = weights[index - 1..].iter().sum();
+        let rest: u32 = weights[index..].iter().sum();
+        let percent = ((rest * 200 + remainder) / (remainder * 2)).clamp(1, 99);
+
+        splits.push(Split {
+            pane: panes[index - 1],
+            direction,
+            percent,
+        });
+        panes.push(splits.len());
+    }
+
+    panes
+}
+
+impl Rpex<2> {
+    /// Splits a single pane into the cells of this expression, first into columns and then each
+    /// column into rows.
+    ///
+    /// Terminal panes are sized in percentages rather than pixels, so every dimension is taken as
+    /// ratios of its own, and expressions don't have to fit any particular size.
+    pub fn splits(&self) -> Vec<Split> {
+        let [columns, rows] = [0, 1].map(|dim| self.sums[dim].weights());
+        let mut splits = vec![];
+
+        for column in split_off(0, SplitDirection::Right, &columns, &mut splits) {
+            split_off(column, SplitDirection::Down, &rows, &mut splits);
+        }
+
+        splits
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    #[test]
+    fn we_can_split_panes_by_expression() {
+        let split = |pane, direction, percent| Split {
+            pane,
+            direction,
+            percent,
+        };
+
+        assert_eq!(
+            Rpex::from_str("2+1:1+1").unwrap().splits(),
+            vec![
+                split(0, SplitDirection::Right, 33),
+                split(0, SplitDirection::Down, 50),
+                split(1, SplitDirection::Down, 50),
+            ]
+        );
+        assert_eq!(
+            Rpex::from_str("++:").unwrap().splits(),
+            vec![
+                split(0, SplitDirection::Right, 67),
+                split(1, SplitDirection::Right, 50),
+            ]
+        );
+        assert!(Rpex::from_str(":").unwrap().splits().is_empty());
+    }
+}
@@ -1,47 +1,376 @@
-use std::{collections::HashSet, str::FromStr};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashSet},
+    fmt::Display,
+    ops::{Div, Mul, RangeBounds},
+    str::FromStr,
+};
 
-use fraction::Integer;
+use fraction::{Integer, Ratio};
 use itertools::Itertools;
 use nom::{character::complete::char as char_parser, IResult};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
 use crate::{
     dimension_sum::{
-        AddendWithOffset, DimensionSum, DimensionSumEvaluationError, IndeterminateDimensionSum,
+        AddendWithOffset, Annotations, DimensionSum, DimensionSumEvaluationError, EditError,
+        IndeterminateDimensionSum, UnknownStrategy,
     },
     impl_from_str_for_nom_parsable,
+    layout::Cell,
     nom_parsable::NomParsable,
     parser_combinators::separated_list_m_n,
     ratio_ext::NotAnInteger,
-    rectangle::HyperRectangle,
+    rectangle::{HyperRectangle, ZeroLength},
+    units::RatioUnits,
 };
 use thiserror::Error;
 
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct SumsInRatio<const D: usize> {
     sums: [DimensionSum; D],
 }
 
 pub struct Partition<'a, const D: usize> {
-    pub ratio_position: [u32; D],
-    pub ratio: [&'a u32; D],
+    pub ratio_position: [RatioUnits; D],
+    pub ratio: [RatioUnits; D],
+    pub labels: [Option<&'a str>; D],
+    pub annotations: [Option<&'a Annotations>; D],
+    /// Totals of the dimension sums this partition is part of.
+    pub totals: [RatioUnits; D],
+}
+
+impl<'a, const D: usize> Partition<'a, D> {
+    /// Joins the labels of the addends this partition spans with `_`, if any are labelled.
+    pub fn label(&self) -> Option<String> {
+        let labels = self.labels.iter().flatten().join("_");
+
+        (!labels.is_empty()).then_some(labels)
+    }
+
+    /// The annotations of the addends this partition spans, merged so that those of later
+    /// dimensions win when the same key is annotated in several.
+    pub fn metadata(&self) -> BTreeMap<&'a str, &'a str> {
+        self.annotations
+            .iter()
+            .flatten()
+            .flat_map(|annotations| annotations.iter())
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+            .collect()
+    }
+
+    /// This partition's share of the total of dimension `dim`.
+    pub fn fraction(&self, dim: usize) -> Ratio<u32> {
+        Ratio::new(self.ratio[dim].0, self.totals[dim].0)
+    }
+
+    /// Where this partition starts along dimension `dim`, as a share of its total.
+    pub fn cumulative_fraction(&self, dim: usize) -> Ratio<u32> {
+        Ratio::new(self.ratio_position[dim].0, self.totals[dim].0)
+    }
+
+    /// A key for this partition that is the same across runs, versions and equivalent
+    /// expressions, e.g. the partitions of `1+1:1` have the same ids as those of `2+2:2` or `+:`,
+    /// on any rectangle.
+    ///
+    /// It is the 64-bit FNV-1a hash of the little-endian numerators and denominators of
+    /// [`Partition::cumulative_fraction`] and [`Partition::fraction`] of every dimension in order,
+    /// so partitions covering the same share of their layouts have the same id. Labels are left
+    /// out.
+    pub fn id(&self) -> u64 {
+        const FNV_OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+        const FNV_PRIME: u64 = 0x100000001b3;
+
+        (0..D)
+            .flat_map(|dim| [self.cumulative_fraction(dim), self.fraction(dim)])
+            .flat_map(|fraction| [*fraction.numer(), *fraction.denom()])
+            .flat_map(u32::to_le_bytes)
+            .fold(FNV_OFFSET_BASIS, |hash, byte| {
+                (hash ^ byte as u64).wrapping_mul(FNV_PRIME)
+            })
+    }
 }
 
 impl<const D: usize> SumsInRatio<D> {
-    pub fn iter_partitions(&self) -> impl Iterator<Item = Partition<D>> {
+    #[cfg(any(test, feature = "test-support"))]
+    pub(crate) fn sums(&self) -> &[DimensionSum; D] {
+        &self.sums
+    }
+
+    pub fn totals(&self) -> [u32; D] {
+        std::array::from_fn(|dim| self.sums[dim].sum())
+    }
+
+    pub fn iter_partitions(&self) -> impl Iterator<Item = Partition<'_, D>> {
+        let totals = self.totals().map(RatioUnits);
+
+        #[cfg(feature = "tracing")]
+        tracing::trace!(
+            sums = %self.sums.iter().join(":"),
+            ?totals,
+            "iterating partitions"
+        );
+
         self.sums
             .iter()
-            .map(|dim_sum| dim_sum.iter_with_offsets().collect::<Vec<_>>())
+            .map(DimensionSum::iter_with_offsets)
             .multi_cartesian_product()
-            .map(|dimension_sums_with_offsets| {
-                let (addends, offsets): (Vec<&u32>, Vec<u32>) = dimension_sums_with_offsets
-                    .into_iter()
-                    .map(|AddendWithOffset { addend, offset }| (addend, offset))
-                    .unzip();
+            .map(move |dimension_sums_with_offsets| {
+                let (addends, offsets, labels, annotations): (Vec<_>, Vec<_>, Vec<_>, Vec<_>) =
+                    dimension_sums_with_offsets
+                        .into_iter()
+                        .map(
+                            |AddendWithOffset {
+                                 addend,
+                                 offset,
+                                 label,
+                                 annotations,
+                             }| {
+                                (RatioUnits(*addend), RatioUnits(offset), label, annotations)
+                            },
+                        )
+                        .multiunzip();
 
                 Partition {
                     ratio_position: offsets.try_into().expect(""),
                     ratio: addends.try_into().expect(""),
+                    labels: labels.try_into().expect(""),
+                    annotations: annotations.try_into().expect(""),
+                    totals,
+                }
+            })
+    }
+
+    /// Replaces the addends of the partition at `index` in [`SumsInRatio::iter_partitions`] with
+    /// the sums of `inner`, see [`DimensionSum::refine`].
+    pub(crate) fn refine(
+        &self,
+        index: usize,
+        factor: u32,
+        inner: &SumsInRatio<D>,
+        inner_factor: u32,
+    ) -> SumsInRatio<D> {
+        let indices = self.addend_indices(index);
+
+        SumsInRatio {
+            sums: std::array::from_fn(|dim| {
+                self.sums[dim].refine(indices[dim], factor, &inner.sums[dim], inner_factor)
+            }),
+        }
+    }
+
+    /// Yields the strips that splitting only along dimension `dim` produces, one per addend of that
+    /// dimension, each spanning the full length of every other dimension. Only the addend of `dim`
+    /// contributes labels and annotations.
+    pub fn iter_strips(&self, dim: usize) -> impl Iterator<Item = Partition<'_, D>> {
+        let totals = self.totals().map(RatioUnits);
+
+        self.sums[dim].iter_with_offsets().map(
+            move |AddendWithOffset {
+                      addend,
+                      offset,
+                      label,
+                      annotations: addend_annotations,
+                  }| {
+                let mut ratio_position = [RatioUnits(0); D];
+                let mut ratio = totals;
+                let mut labels = [None; D];
+                let mut annotations = [None; D];
+
+                ratio_position[dim] = RatioUnits(offset);
+                ratio[dim] = RatioUnits(*addend);
+                labels[dim] = label;
+                annotations[dim] = addend_annotations;
+
+                Partition {
+                    ratio_position,
+                    ratio,
+                    labels,
+                    annotations,
+                    totals,
                 }
+            },
+        )
+    }
+
+    pub fn partition_count(&self) -> usize {
+        self.sums.iter().map(|sum| sum.addends().len()).product()
+    }
+
+    // Partitions are in row-major order, so the last dimension varies fastest.
+    fn addend_indices(&self, index: usize) -> [usize; D] {
+        let mut indices = [0; D];
+        let mut rest = index;
+        for dim in (0..D).rev() {
+            let len = self.sums[dim].addends().len();
+            indices[dim] = rest % len;
+            rest /= len;
+        }
+
+        indices
+    }
+
+    // Builds the partition at `index` in `iter_partitions` directly, without the ones before it.
+    fn partition(&self, index: usize) -> Partition<'_, D> {
+        let indices = self.addend_indices(index);
+
+        Partition {
+            ratio_position: std::array::from_fn(|dim| {
+                RatioUnits(self.sums[dim].boundaries()[indices[dim]])
+            }),
+            ratio: std::array::from_fn(|dim| RatioUnits(self.sums[dim].addends()[indices[dim]])),
+            labels: std::array::from_fn(|dim| self.sums[dim].label(indices[dim])),
+            annotations: std::array::from_fn(|dim| self.sums[dim].annotations(indices[dim])),
+            totals: self.totals().map(RatioUnits),
+        }
+    }
+
+    /// Like [`SumsInRatio::iter_partitions`], but starting at the partition at `index`, which is
+    /// found without going through the ones before it. The cursor can be paged through with
+    /// [`PartitionCursor::take_page`], and resumed later from [`PartitionCursor::position`].
+    pub fn iter_partitions_from(&self, index: usize) -> PartitionCursor<'_, D> {
+        PartitionCursor {
+            sums_in_ratio: self,
+            index,
+            count: self.partition_count(),
+        }
+    }
+
+    /// Index in [`SumsInRatio::iter_partitions`] of the partition covering `position`, found by
+    /// binary search in every dimension.
+    pub fn partition_at(&self, position: [RatioUnits; D]) -> Option<usize> {
+        self.sums
+            .iter()
+            .zip(position)
+            .try_fold(0, |index, (sum, RatioUnits(offset))| {
+                Some(index * sum.addends().len() + sum.addend_at(offset)?)
+            })
+    }
+
+    /// Yields only the partitions whose index in [`SumsInRatio::iter_partitions`] is `true` in
+    /// `mask`. Partitions past the end of `mask` are left out.
+    pub fn iter_partitions_masked<'a>(
+        &'a self,
+        mask: &'a [bool],
+    ) -> impl Iterator<Item = Partition<'a, D>> {
+        self.iter_partitions()
+            .zip(mask)
+            .filter_map(|(partition, &selected)| selected.then_some(partition))
+    }
+
+    /// Yields only the partitions `predicate` is `true` for, in the order of
+    /// [`SumsInRatio::iter_partitions`].
+    pub fn iter_partitions_where<'a>(
+        &'a self,
+        mut predicate: impl FnMut(&Partition<'a, D>) -> bool + 'a,
+    ) -> impl Iterator<Item = Partition<'a, D>> {
+        self.iter_partitions()
+            .filter(move |partition| predicate(partition))
+    }
+
+    /// Yields the partitions spanning an addend labelled `label` in any dimension.
+    pub fn iter_partitions_labelled<'a>(
+        &'a self,
+        label: &'a str,
+    ) -> impl Iterator<Item = Partition<'a, D>> {
+        self.iter_partitions_where(move |partition| partition.labels.contains(&Some(label)))
+    }
+
+    /// Yields the partitions that lie entirely within `range` of dimension `dim`, e.g.
+    /// `RatioUnits(0)..RatioUnits(2)` for those in the first two units of the first dimension.
+    pub fn iter_partitions_within<'a>(
+        &'a self,
+        dim: usize,
+        range: impl RangeBounds<RatioUnits> + 'a,
+    ) -> impl Iterator<Item = Partition<'a, D>> {
+        self.iter_partitions_where(move |partition| {
+            let RatioUnits(start) = partition.ratio_position[dim];
+            let last = start + partition.ratio[dim].0.saturating_sub(1);
+
+            range.contains(&RatioUnits(start)) && range.contains(&RatioUnits(last))
+        })
+    }
+
+    /// Yields the partitions at least `min` long in every dimension.
+    pub fn iter_partitions_at_least(
+        &self,
+        min: [RatioUnits; D],
+    ) -> impl Iterator<Item = Partition<'_, D>> {
+        self.iter_partitions_where(move |partition| {
+            partition
+                .ratio
+                .iter()
+                .zip(&min)
+                .all(|(ratio, min)| ratio >= min)
+        })
+    }
+}
+
+/// Iterator over the partitions of a [`SumsInRatio`] from any index, see
+/// [`SumsInRatio::iter_partitions_from`].
+#[derive(Clone)]
+pub struct PartitionCursor<'a, const D: usize> {
+    sums_in_ratio: &'a SumsInRatio<D>,
+    index: usize,
+    count: usize,
+}
+
+impl<'a, const D: usize> PartitionCursor<'a, D> {
+    /// Index in [`SumsInRatio::iter_partitions`] of the next partition, to resume from.
+    pub fn position(&self) -> usize {
+        self.index
+    }
+
+    /// Takes up to `n` partitions, fewer only at the end.
+    pub fn take_page(&mut self, n: usize) -> Vec<Partition<'a, D>> {
+        self.by_ref().take(n).collect()
+    }
+}
+
+impl<'a, const D: usize> Iterator for PartitionCursor<'a, D> {
+    type Item = Partition<'a, D>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.index >= self.count {
+            return None;
+        }
+
+        let partition = self.sums_in_ratio.partition(self.index);
+        self.index += 1;
+
+        Some(partition)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.count.saturating_sub(self.index);
+
+        (remaining, Some(remaining))
+    }
+
+    fn nth(&mut self, n: usize) -> Option<Self::Item> {
+        self.index = self.index.saturating_add(n);
+        self.next()
+    }
+}
+
+impl<const D: usize> ExactSizeIterator for PartitionCursor<'_, D> {}
+
+impl SumsInRatio<2> {
+    /// Like [`SumsInRatio::iter_partitions`], in the same order, but with a loop over the rows of
+    /// every column instead of a cartesian product of any number of dimensions. This is faster
+    /// when iterating many partitions, e.g. when tiling images.
+    pub fn iter_partitions_2d(&self) -> impl Iterator<Item = Partition<'_, 2>> {
+        let [columns, rows] = &self.sums;
+        let totals = self.totals().map(RatioUnits);
+
+        columns.iter_with_offsets().flat_map(move |column| {
+            rows.iter_with_offsets().map(move |row| Partition {
+                ratio_position: [column.offset, row.offset].map(RatioUnits),
+                ratio: [*column.addend, *row.addend].map(RatioUnits),
+                labels: [column.label, row.label],
+                annotations: [column.annotations, row.annotations],
+                totals,
             })
+        })
     }
 }
 
@@ -58,28 +387,260 @@ pub enum SumsInRatioEvaluationError {
     DoesNotDivide(#[from] NotAnInteger<u32>),
     #[error("unable to evaluate dimension sum: {0}")]
     DimensionSumEvaluation(#[from] DimensionSumEvaluationError),
+    #[error(transparent)]
+    ZeroLength(#[from] ZeroLength),
+}
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum ScalarDivisionError {
+    #[error("cannot divide an expression by zero")]
+    ByZero,
+    #[error("addend {addend} is not divisible by {divisor}")]
+    Indivisible { addend: u32, divisor: u32 },
+}
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum RpexInferenceError {
+    #[error("no cells to infer an expression from")]
+    NoCells,
+    #[error("cells do not form a complete grid")]
+    NotAGrid,
 }
 
 impl<const D: usize> IndeterminateSumsInRatio<D> {
+    pub fn infer(cells: &[Cell<D>]) -> Result<IndeterminateSumsInRatio<D>, RpexInferenceError> {
+        if cells.is_empty() {
+            return Err(RpexInferenceError::NoCells);
+        }
+
+        let boundaries: [Vec<u32>; D] = std::array::from_fn(|dim| {
+            cells
+                .iter()
+                .flat_map(
+                    |Cell {
+                         position,
+                         rectangle,
+                     }| {
+                        [position[dim], position[dim] + rectangle.lengths[dim]]
+                    },
+                )
+                .chain([0])
+                .collect::<BTreeSet<_>>()
+                .into_iter()
+                .collect()
+        });
+
+        // Every cell must span exactly one step between boundaries in every dimension, and
+        // together the cells must occupy every position of the resulting grid exactly once.
+        let grid_positions = cells
+            .iter()
+            .map(
+                |Cell {
+                     position,
+                     rectangle,
+                 }| {
+                    (0..D)
+                        .map(|dim| {
+                            let start = boundaries[dim].binary_search(&position[dim]).ok()?;
+                            let end = position[dim] + rectangle.lengths[dim];
+
+                            (boundaries[dim].get(start + 1) == Some(&end)).then_some(start)
+                        })
+                        .collect::<Option<Vec<_>>>()
+                },
+            )
+            .collect::<Option<HashSet<_>>>()
+            .ok_or(RpexInferenceError::NotAGrid)?;
+
+        let grid_size = boundaries
+            .iter()
+            .map(|dim_boundaries| dim_boundaries.len() - 1)
+            .product::<usize>();
+
+        if grid_positions.len() != cells.len() || grid_size != cells.len() {
+            return Err(RpexInferenceError::NotAGrid);
+        }
+
+        let sums = boundaries.map(|dim_boundaries| IndeterminateDimensionSum {
+            addends: dim_boundaries
+                .windows(2)
+                .map(|window| Some(window[1] - window[0]))
+                .collect(),
+            ..Default::default()
+        });
+
+        Ok(IndeterminateSumsInRatio { sums }.normalize())
+    }
+
+    fn edit_sum(
+        mut self,
+        dim: usize,
+        edit: impl FnOnce(IndeterminateDimensionSum) -> Result<IndeterminateDimensionSum, EditError>,
+    ) -> Result<IndeterminateSumsInRatio<D>, EditError> {
+        let sum = self.sums.get_mut(dim).ok_or(EditError::NoDimension(dim))?;
+
+        *sum = edit(std::mem::take(sum))?;
+
+        Ok(self)
+    }
+
+    /// Inserts `addend` into dimension `dim` before the addend at `index`, or at the end if
+    /// `index` is the number of addends.
+    pub fn insert_addend(
+        self,
+        dim: usize,
+        index: usize,
+        addend: Option<u32>,
+    ) -> Result<IndeterminateSumsInRatio<D>, EditError> {
+        self.edit_sum(dim, |sum| sum.insert_addend(index, addend))
+    }
+
+    pub fn remove_addend(
+        self,
+        dim: usize,
+        index: usize,
+    ) -> Result<IndeterminateSumsInRatio<D>, EditError> {
+        self.edit_sum(dim, |sum| sum.remove_addend(index))
+    }
+
+    /// Splits an addend of dimension `dim` into two equal halves in its place. Known addends are
+    /// doubled first when the addend is odd, which keeps the layout the same as long as some
+    /// dimension has no unknowns, as with [`IndeterminateSumsInRatio::normalize`].
+    pub fn split_addend(
+        mut self,
+        dim: usize,
+        index: usize,
+    ) -> Result<IndeterminateSumsInRatio<D>, EditError> {
+        let addend = self
+            .sums
+            .get(dim)
+            .and_then(|sum| sum.addends.get(index).copied());
+
+        if matches!(addend, Some(Some(addend)) if addend % 2 == 1) {
+            self.sums = self.sums.map(|sum| sum * 2);
+        }
+
+        self.edit_sum(dim, |sum| sum.split_addend(index))
+    }
+
+    /// Merges the addends at `index` and `index + 1` of dimension `dim`.
+    pub fn merge_addends(
+        self,
+        dim: usize,
+        index: usize,
+    ) -> Result<IndeterminateSumsInRatio<D>, EditError> {
+        self.edit_sum(dim, |sum| sum.merge_addends(index))
+    }
+
+    /// Cuts the layout across dimension `dim` at `at` ratio units, into the expressions for the
+    /// parts before and after the cut, e.g. `2+1:1` at 1 into `1:1` and `1+1:1`. Other dimensions
+    /// are the same in both.
+    pub fn split_dimension(
+        self,
+        dim: usize,
+        at: RatioUnits,
+    ) -> Result<(IndeterminateSumsInRatio<D>, IndeterminateSumsInRatio<D>), EditError> {
+        let sum = self.sums.get(dim).ok_or(EditError::NoDimension(dim))?;
+        let (first, second) = sum.clone().split_at(at.0)?;
+
+        let mut before = self.clone();
+        let mut after = self;
+        before.sums[dim] = first;
+        after.sums[dim] = second;
+
+        Ok((before, after))
+    }
+
+    /// Like [`reduce`](Self::reduce), but leaves expressions without a fully-known dimension
+    /// alone, since their addends are measured in pixels rather than ratio units and dividing
+    /// them would change the layout.
+    pub fn normalize(self) -> IndeterminateSumsInRatio<D> {
+        if self.sums.iter().all(|sum| sum.count_unknowns() != 0) {
+            return self;
+        }
+
+        self.reduce()
+    }
+
+    /// Divides every known addend by their greatest common divisor, e.g. `4+2:6` into `2+1:3`.
+    pub fn reduce(self) -> IndeterminateSumsInRatio<D> {
+        let gcd = self
+            .sums
+            .iter()
+            .fold(0, |gcd, sum| gcd.gcd(&sum.gcd_knowns()));
+
+        if gcd <= 1 {
+            return self;
+        }
+
+        IndeterminateSumsInRatio {
+            sums: self.sums.map(|sum| sum / gcd),
+        }
+    }
+
     pub fn evaluate(
         self,
         rectangle: HyperRectangle<D>,
     ) -> Result<(SumsInRatio<D>, u32), SumsInRatioEvaluationError> {
-        let inferred_scales = self
-            .sums
-            .iter()
-            .zip(rectangle.lengths)
-            .flat_map(|(sum, length)| sum.infer_scale(length).transpose())
-            .collect::<Result<HashSet<_>, _>>()?;
+        self.evaluate_with_strategy(rectangle, UnknownStrategy::default())
+    }
 
-        let known_scale = match inferred_scales.len() {
-            0 => 1,
-            1 => inferred_scales
-                .into_iter()
-                .last()
-                .expect("inferred_scales has length 1"),
-            _ => return Err(SumsInRatioEvaluationError::UnequalScales(inferred_scales)),
-        };
+    /// Fills in every unknown with its value on `rectangle`, e.g. `+:1` on 1920x1080 becomes
+    /// `960+960:1080`. Every addend is given in pixels, and labels are kept.
+    pub fn resolve_unknowns(
+        self,
+        rectangle: HyperRectangle<D>,
+    ) -> Result<IndeterminateSumsInRatio<D>, SumsInRatioEvaluationError> {
+        let (evaluated, scale) = self.evaluate(rectangle)?;
+
+        Ok(IndeterminateSumsInRatio {
+            sums: evaluated.sums.map(|sum| {
+                sum.iter_with_offsets()
+                    .map(
+                        |AddendWithOffset {
+                             addend,
+                             label,
+                             annotations,
+                             ..
+                         }| {
+                            (Some(addend * scale), label, annotations.cloned())
+                        },
+                    )
+                    .collect()
+            }),
+        })
+    }
+
+    /// Like [`IndeterminateSumsInRatio::evaluate`], sizing addends without a value by `strategy`.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(rpex = %self, %rectangle, ?strategy))
+    )]
+    pub fn evaluate_with_strategy(
+        self,
+        rectangle: HyperRectangle<D>,
+        strategy: UnknownStrategy,
+    ) -> Result<(SumsInRatio<D>, u32), SumsInRatioEvaluationError> {
+        rectangle.nonzero_lengths()?;
+
+        // Scales are inferred into an array rather than a set, so that evaluating doesn't allocate
+        // more than the evaluated sums themselves. The set is only built to report a mismatch.
+        let mut inferred_scales = [None; D];
+        for (inferred, (sum, length)) in inferred_scales
+            .iter_mut()
+            .zip(self.sums.iter().zip(rectangle.lengths))
+        {
+            *inferred = sum.infer_scale(length)?;
+        }
+
+        let mut known_scales = inferred_scales.iter().flatten().copied();
+        let known_scale = known_scales.next().unwrap_or(1);
+
+        if known_scales.any(|scale| scale != known_scale) {
+            return Err(SumsInRatioEvaluationError::UnequalScales(
+                inferred_scales.into_iter().flatten().collect(),
+            ));
+        }
 
         let scale = rectangle
             .lengths
@@ -88,22 +649,121 @@ impl<const D: usize> IndeterminateSumsInRatio<D> {
 
         let scale_factor = known_scale / scale;
 
-        let evaluated_sums = self
-            .sums
-            .map(|dim_sum| dim_sum * scale_factor)
-            .into_iter()
-            .zip(rectangle.lengths)
-            .map(|(sum, length)| sum.evaluate(length / scale))
-            .collect::<Result<Vec<_>, _>>()?;
-
-        Ok((
-            SumsInRatio {
-                sums: evaluated_sums
-                    .try_into()
-                    .expect("sums is built from arrays of length D"),
-            },
+        #[cfg(feature = "tracing")]
+        tracing::debug!(
+            ?inferred_scales,
+            known_scale,
             scale,
-        ))
+            scale_factor,
+            "inferred scale"
+        );
+
+        let mut error = None;
+        let mut lengths = rectangle.lengths.into_iter();
+        let sums = self.sums.map(|sum| {
+            let length = lengths.next().expect("lengths has length D");
+
+            sum.checked_mul(scale_factor)
+                .and_then(|sum| sum.evaluate(length / scale, strategy))
+                .unwrap_or_else(|e| {
+                    error.get_or_insert(e);
+                    DimensionSum::default()
+                })
+        });
+
+        if let Some(e) = error {
+            return Err(e.into());
+        }
+
+        Ok((SumsInRatio { sums }, scale))
+    }
+}
+
+impl IndeterminateSumsInRatio<2> {
+    /// Splits `rectangle` into a grid of `n` equal cells, choosing the columns and rows whose
+    /// cells are closest to square. Only exact factorizations of `n` are considered, so a prime
+    /// `n` yields a single row or column.
+    pub fn grid_for(n: usize, rectangle: HyperRectangle<2>) -> IndeterminateSumsInRatio<2> {
+        assert!(n != 0, "a grid needs at least one cell");
+
+        let [width, height] = rectangle.lengths.map(|length| length as f64);
+
+        let (columns, rows) = (1..=n)
+            .filter(|columns| n.is_multiple_of(*columns))
+            .map(|columns| (columns, n / columns))
+            .min_by(|(a_columns, a_rows), (b_columns, b_rows)| {
+                let squareness = |columns: usize, rows: usize| {
+                    ((width * rows as f64) / (height * columns as f64))
+                        .ln()
+                        .abs()
+                };
+
+                squareness(*a_columns, *a_rows).total_cmp(&squareness(*b_columns, *b_rows))
+            })
+            .expect("1 divides n");
+
+        IndeterminateSumsInRatio {
+            sums: [columns, rows].map(|count| IndeterminateDimensionSum {
+                addends: vec![None; count],
+                ..Default::default()
+            }),
+        }
+    }
+}
+
+impl IndeterminateSumsInRatio<2> {
+    // Each denominator is only worth trying with its closest numerator that fits.
+    fn ratio_candidates(ratio: f64, max_units: u32) -> impl Iterator<Item = (u32, u32, f64)> {
+        assert!(
+            ratio.is_finite() && ratio > 0.0,
+            "ratio must be positive and finite"
+        );
+        assert!(max_units >= 2, "two columns need at least 2 units");
+
+        (1..max_units).map(move |right| {
+            let left = (ratio * right as f64)
+                .round()
+                .clamp(1.0, (max_units - right) as f64) as u32;
+
+            (left, right, (left as f64 / right as f64 - ratio).abs())
+        })
+    }
+
+    fn two_columns(left: u32, right: u32) -> IndeterminateSumsInRatio<2> {
+        IndeterminateSumsInRatio {
+            sums: [
+                IndeterminateDimensionSum {
+                    addends: vec![Some(left), Some(right)],
+                    ..Default::default()
+                },
+                IndeterminateDimensionSum {
+                    addends: vec![None],
+                    ..Default::default()
+                },
+            ],
+        }
+    }
+
+    /// Splits into two columns whose widths are closest to `ratio` using at most `max_units`
+    /// units in total, e.g. `5+3:` for the golden ratio `1.618` in 10 units.
+    pub fn approximate_ratio(ratio: f64, max_units: u32) -> IndeterminateSumsInRatio<2> {
+        let (left, right, _) = IndeterminateSumsInRatio::ratio_candidates(ratio, max_units)
+            .min_by(|(_, _, a_error), (_, _, b_error)| a_error.total_cmp(b_error))
+            .expect("max_units is at least 2");
+
+        IndeterminateSumsInRatio::two_columns(left, right)
+    }
+
+    /// Like [`IndeterminateSumsInRatio::approximate_ratio`], but uses the fewest units that are
+    /// within `tolerance` of `ratio`, if any up to `max_units` are.
+    pub fn approximate_ratio_within(
+        ratio: f64,
+        tolerance: f64,
+        max_units: u32,
+    ) -> Option<IndeterminateSumsInRatio<2>> {
+        IndeterminateSumsInRatio::ratio_candidates(ratio, max_units)
+            .find(|(_, _, error)| *error <= tolerance)
+            .map(|(left, right, _)| IndeterminateSumsInRatio::two_columns(left, right))
     }
 }
 
@@ -123,10 +783,128 @@ impl<const D: usize> NomParsable for IndeterminateSumsInRatio<D> {
     }
 }
 
+/// Scales every known addend, leaving unknowns and labels as they are.
+impl<const D: usize> Mul<u32> for IndeterminateSumsInRatio<D> {
+    type Output = IndeterminateSumsInRatio<D>;
+
+    fn mul(self, rhs: u32) -> Self::Output {
+        IndeterminateSumsInRatio {
+            sums: self.sums.map(|sum| sum * rhs),
+        }
+    }
+}
+
+/// Divides every known addend, failing rather than rounding when one isn't divisible.
+impl<const D: usize> Div<u32> for IndeterminateSumsInRatio<D> {
+    type Output = Result<IndeterminateSumsInRatio<D>, ScalarDivisionError>;
+
+    fn div(self, rhs: u32) -> Self::Output {
+        if rhs == 0 {
+            return Err(ScalarDivisionError::ByZero);
+        }
+
+        if let Some(&addend) = self
+            .sums
+            .iter()
+            .flat_map(|sum| sum.addends.iter().flatten())
+            .find(|&&addend| !addend.is_multiple_of(rhs))
+        {
+            return Err(ScalarDivisionError::Indivisible {
+                addend,
+                divisor: rhs,
+            });
+        }
+
+        Ok(IndeterminateSumsInRatio {
+            sums: self.sums.map(|sum| sum / rhs),
+        })
+    }
+}
+
 impl<const D: usize> FromStr for IndeterminateSumsInRatio<D> {
     impl_from_str_for_nom_parsable!();
 }
 
+impl<const D: usize> Display for IndeterminateSumsInRatio<D> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.sums.iter().join(":").as_str())
+    }
+}
+
+impl<const D: usize> Serialize for IndeterminateSumsInRatio<D> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de, const D: usize> Deserialize<'de> for IndeterminateSumsInRatio<D> {
+    fn deserialize<De: Deserializer<'de>>(deserializer: De) -> Result<Self, De::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+// Expressions are serialized as strings, so their schema can only describe them.
+#[cfg(feature = "schemars")]
+impl<const D: usize> schemars::JsonSchema for IndeterminateSumsInRatio<D> {
+    fn schema_name() -> String {
+        format!("Rpex{D}")
+    }
+
+    fn json_schema(_: &mut schemars::gen::SchemaGenerator) -> schemars::schema::Schema {
+        schemars::schema::SchemaObject {
+            instance_type: Some(schemars::schema::InstanceType::String.into()),
+            metadata: Some(Box::new(schemars::schema::Metadata {
+                description: Some(format!(
+                    "Expression of {D} `:`-separated sums of `+`-separated addends, e.g. `2+1:1`"
+                )),
+                ..Default::default()
+            })),
+            ..Default::default()
+        }
+        .into()
+    }
+}
+
+impl<const D: usize> Display for SumsInRatio<D> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.sums.iter().join(":").as_str())
+    }
+}
+
+// Evaluated sums are written like the expressions they are evaluated from, only without unknowns,
+// so that they can be cached and loaded again without the resolution they were evaluated for.
+impl<const D: usize> Serialize for SumsInRatio<D> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de, const D: usize> Deserialize<'de> for SumsInRatio<D> {
+    fn deserialize<De: Deserializer<'de>>(deserializer: De) -> Result<Self, De::Error> {
+        let rpex = IndeterminateSumsInRatio::<D>::deserialize(deserializer)?;
+
+        if rpex.sums.iter().any(|sum| sum.count_unknowns() != 0) {
+            return Err(serde::de::Error::custom(
+                "evaluated sums can't have unknown addends",
+            ));
+        }
+
+        // Without unknowns, evaluating for the totals of the sums keeps every addend as it is.
+        let totals = rpex
+            .sums
+            .each_ref()
+            .map(|sum| sum.addends.iter().flatten().sum());
+
+        let (sums_in_ratio, _) = rpex
+            .evaluate(HyperRectangle { lengths: totals })
+            .map_err(serde::de::Error::custom)?;
+
+        Ok(sums_in_ratio)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -138,10 +916,12 @@ mod tests {
             IndeterminateSumsInRatio {
                 sums: [
                     IndeterminateDimensionSum {
-                        addends: vec![None, None]
+                        addends: vec![None, None],
+                        ..Default::default()
                     },
                     IndeterminateDimensionSum {
-                        addends: vec![None]
+                        addends: vec![None],
+                        ..Default::default()
                     }
                 ]
             }
@@ -151,10 +931,12 @@ mod tests {
             IndeterminateSumsInRatio {
                 sums: [
                     IndeterminateDimensionSum {
-                        addends: vec![None, None]
+                        addends: vec![None, None],
+                        ..Default::default()
                     },
                     IndeterminateDimensionSum {
-                        addends: vec![None, None, None]
+                        addends: vec![None, None, None],
+                        ..Default::default()
                     }
                 ]
             }
@@ -168,10 +950,12 @@ mod tests {
             IndeterminateSumsInRatio {
                 sums: [
                     IndeterminateDimensionSum {
-                        addends: vec![Some(1), Some(2)]
+                        addends: vec![Some(1), Some(2)],
+                        ..Default::default()
                     },
                     IndeterminateDimensionSum {
-                        addends: vec![Some(3)]
+                        addends: vec![Some(3)],
+                        ..Default::default()
                     }
                 ]
             }
@@ -181,10 +965,12 @@ mod tests {
             IndeterminateSumsInRatio {
                 sums: [
                     IndeterminateDimensionSum {
-                        addends: vec![Some(12), Some(34)]
+                        addends: vec![Some(12), Some(34)],
+                        ..Default::default()
                     },
                     IndeterminateDimensionSum {
-                        addends: vec![Some(56), None, Some(789)]
+                        addends: vec![Some(56), None, Some(789)],
+                        ..Default::default()
                     }
                 ]
             }
@@ -203,9 +989,582 @@ mod tests {
         assert!(IndeterminateSumsInRatio::<2>::from_str("1+1-1+1").is_err());
     }
 
+    #[test]
+    fn we_can_display_ratio() {
+        for expression in ["+:", "1+2:3", "12+34:56++789"] {
+            assert_eq!(
+                IndeterminateSumsInRatio::<2>::from_str(expression)
+                    .unwrap()
+                    .to_string(),
+                expression
+            );
+        }
+    }
+
+    #[test]
+    fn we_can_round_trip_ratio_through_serde() {
+        let rpex = IndeterminateSumsInRatio::<2>::from_str("1++2:3").unwrap();
+
+        let serialized = serde_json::to_string(&rpex).unwrap();
+        assert_eq!(serialized, r#""1++2:3""#);
+        assert_eq!(
+            serde_json::from_str::<IndeterminateSumsInRatio<2>>(&serialized).unwrap(),
+            rpex
+        );
+        assert!(serde_json::from_str::<IndeterminateSumsInRatio<2>>(r#""1+x:3""#).is_err());
+    }
+
+    #[test]
+    fn we_can_normalize_ratio_with_known_dimension() {
+        assert_eq!(
+            IndeterminateSumsInRatio::<2>::from_str("2+2:4")
+                .unwrap()
+                .normalize(),
+            IndeterminateSumsInRatio::from_str("1+1:2").unwrap()
+        );
+        assert_eq!(
+            IndeterminateSumsInRatio::<2>::from_str("6++3:9")
+                .unwrap()
+                .normalize(),
+            IndeterminateSumsInRatio::from_str("2++1:3").unwrap()
+        );
+        assert_eq!(
+            IndeterminateSumsInRatio::<2>::from_str("2+3:4")
+                .unwrap()
+                .normalize(),
+            IndeterminateSumsInRatio::from_str("2+3:4").unwrap()
+        );
+    }
+
+    #[test]
+    fn we_do_not_normalize_ratio_without_known_dimension() {
+        assert_eq!(
+            IndeterminateSumsInRatio::<2>::from_str("2+:4+")
+                .unwrap()
+                .normalize(),
+            IndeterminateSumsInRatio::from_str("2+:4+").unwrap()
+        );
+    }
+
+    #[test]
+    fn we_can_scale_and_reduce_ratio() {
+        let rpex = IndeterminateSumsInRatio::<2>::from_str("2@a+:4+1").unwrap();
+
+        let scaled = rpex.clone() * 3;
+        assert_eq!(scaled.to_string(), "6@a+:12+3");
+        assert_eq!((scaled.clone() / 3).unwrap(), rpex);
+        assert_eq!(scaled.reduce().to_string(), "2@a+:4+1");
+
+        assert_eq!(
+            IndeterminateSumsInRatio::<2>::from_str("4+:6+")
+                .unwrap()
+                .reduce(),
+            IndeterminateSumsInRatio::from_str("2+:3+").unwrap()
+        );
+    }
+
+    #[test]
+    fn we_cannot_divide_ratio_unevenly() {
+        let rpex = IndeterminateSumsInRatio::<2>::from_str("4+:6+3").unwrap();
+
+        assert_eq!(
+            rpex.clone() / 2,
+            Err(ScalarDivisionError::Indivisible {
+                addend: 3,
+                divisor: 2
+            })
+        );
+        assert_eq!(rpex / 0, Err(ScalarDivisionError::ByZero));
+    }
+
+    #[test]
+    fn we_can_infer_ratio_from_evaluated_cells() {
+        let layout = crate::Layout::from(
+            IndeterminateSumsInRatio::<2>::from_str("2+1:1+1")
+                .unwrap()
+                .evaluate(HyperRectangle::from_str("300x200").unwrap())
+                .unwrap(),
+        );
+
+        let cells = layout.iter_cells().collect::<Vec<_>>();
+
+        assert_eq!(
+            IndeterminateSumsInRatio::infer(&cells).unwrap(),
+            IndeterminateSumsInRatio::from_str("2+1:1+1").unwrap()
+        );
+        assert_eq!(
+            IndeterminateSumsInRatio::infer(&cells[1..]),
+            Err(RpexInferenceError::NotAGrid)
+        );
+        assert_eq!(
+            IndeterminateSumsInRatio::<2>::infer(&[]),
+            Err(RpexInferenceError::NoCells)
+        );
+    }
+
+    #[test]
+    fn we_can_iter_masked_partitions() {
+        let sums_in_ratio = IndeterminateSumsInRatio::<2>::from_str("2+1:1+1")
+            .unwrap()
+            .evaluate(HyperRectangle::from_str("300x200").unwrap())
+            .unwrap()
+            .0;
+
+        assert_eq!(
+            sums_in_ratio
+                .iter_partitions_masked(&[true, false, false, true])
+                .map(|Partition { ratio_position, .. }| ratio_position)
+                .collect::<Vec<_>>(),
+            vec![
+                [RatioUnits(0), RatioUnits(0)],
+                [RatioUnits(2), RatioUnits(1)]
+            ]
+        );
+        assert_eq!(sums_in_ratio.iter_partitions_masked(&[]).count(), 0);
+    }
+
+    #[test]
+    fn we_can_iter_partitions_where() {
+        let (sums_in_ratio, _) = IndeterminateSumsInRatio::<2>::from_str("2@main+1+1:1@top+3")
+            .unwrap()
+            .evaluate(HyperRectangle::from_str("4x4").unwrap())
+            .unwrap();
+
+        let positions = |partitions: &mut dyn Iterator<Item = Partition<'_, 2>>| {
+            partitions
+                .map(|partition| partition.ratio_position.map(|RatioUnits(offset)| offset))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            positions(
+                &mut sums_in_ratio.iter_partitions_where(|partition| {
+                    partition.ratio[0] == partition.ratio[1]
+                })
+            ),
+            vec![[2, 0], [3, 0]]
+        );
+        assert_eq!(
+            positions(&mut sums_in_ratio.iter_partitions_labelled("top")),
+            vec![[0, 0], [2, 0], [3, 0]]
+        );
+        assert_eq!(
+            positions(&mut sums_in_ratio.iter_partitions_labelled("main")),
+            vec![[0, 0], [0, 1]]
+        );
+        assert_eq!(
+            positions(&mut sums_in_ratio.iter_partitions_within(0, RatioUnits(1)..)),
+            vec![[2, 0], [2, 1], [3, 0], [3, 1]]
+        );
+        assert_eq!(
+            positions(&mut sums_in_ratio.iter_partitions_within(1, RatioUnits(0)..RatioUnits(2))),
+            vec![[0, 0], [2, 0], [3, 0]]
+        );
+        assert_eq!(
+            positions(&mut sums_in_ratio.iter_partitions_at_least([RatioUnits(2), RatioUnits(2)])),
+            vec![[0, 1]]
+        );
+    }
+
+    #[test]
+    fn we_can_resume_partitions_from_any_index() {
+        let (sums_in_ratio, _) = IndeterminateSumsInRatio::<2>::from_str("2@main+1+1:1@top+3")
+            .unwrap()
+            .evaluate(HyperRectangle::from_str("4x4").unwrap())
+            .unwrap();
+
+        let summary = |partition: &Partition<'_, 2>| {
+            (
+                partition.ratio_position,
+                partition.ratio,
+                partition.label(),
+                partition.totals,
+            )
+        };
+        let all = sums_in_ratio
+            .iter_partitions()
+            .map(|partition| summary(&partition))
+            .collect::<Vec<_>>();
+
+        assert_eq!(sums_in_ratio.partition_count(), 6);
+
+        let mut cursor = sums_in_ratio.iter_partitions_from(0);
+        let mut pages = vec![];
+        while cursor.len() != 0 {
+            pages.push(cursor.take_page(4).iter().map(summary).collect::<Vec<_>>());
+        }
+
+        assert_eq!(pages, vec![all[..4].to_vec(), all[4..].to_vec()]);
+        assert_eq!(cursor.position(), 6);
+
+        assert_eq!(
+            sums_in_ratio
+                .iter_partitions_from(3)
+                .map(|partition| summary(&partition))
+                .collect::<Vec<_>>(),
+            all[3..]
+        );
+        assert_eq!(
+            sums_in_ratio
+                .iter_partitions_from(1)
+                .nth(2)
+                .map(|partition| summary(&partition)),
+            Some(all[3].clone())
+        );
+        assert_eq!(sums_in_ratio.iter_partitions_from(7).count(), 0);
+    }
+
+    #[test]
+    fn we_can_serialize_evaluated_sums() {
+        let (sums_in_ratio, _) = IndeterminateSumsInRatio::<2>::from_str("2@main[ws=1]+:1+1")
+            .unwrap()
+            .evaluate(HyperRectangle::from_str("600x200").unwrap())
+            .unwrap();
+
+        let json = serde_json::to_string(&sums_in_ratio).unwrap();
+
+        assert_eq!(json, r#""2@main[ws=1]+4:1+1""#);
+        assert_eq!(
+            serde_json::from_str::<SumsInRatio<2>>(&json).unwrap(),
+            sums_in_ratio
+        );
+        assert!(serde_json::from_str::<SumsInRatio<2>>(r#""2+:1""#).is_err());
+        assert!(serde_json::from_str::<SumsInRatio<2>>(r#""0:1""#).is_err());
+    }
+
+    #[test]
+    fn we_can_iter_strips() {
+        let (sums_in_ratio, _) = IndeterminateSumsInRatio::<2>::from_str("2@main+1+1:1@top+3")
+            .unwrap()
+            .evaluate(HyperRectangle::from_str("4x4").unwrap())
+            .unwrap();
+
+        let strips = |dim| {
+            sums_in_ratio
+                .iter_strips(dim)
+                .map(|strip| {
+                    (
+                        strip.ratio_position.map(|RatioUnits(offset)| offset),
+                        strip.ratio.map(|RatioUnits(addend)| addend),
+                        strip.label(),
+                    )
+                })
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            strips(0),
+            vec![
+                ([0, 0], [2, 4], Some("main".to_string())),
+                ([2, 0], [1, 4], None),
+                ([3, 0], [1, 4], None),
+            ]
+        );
+        assert_eq!(
+            strips(1),
+            vec![
+                ([0, 0], [4, 1], Some("top".to_string())),
+                ([0, 1], [4, 3], None),
+            ]
+        );
+    }
+
+    #[test]
+    fn we_can_iter_partitions_2d_like_partitions() {
+        let (sums_in_ratio, _) = IndeterminateSumsInRatio::<2>::from_str("2@a+1:1+1@b+1")
+            .unwrap()
+            .evaluate(HyperRectangle::from_str("3x3").unwrap())
+            .unwrap();
+
+        let describe = |partition: Partition<'_, 2>| {
+            (partition.ratio_position, partition.ratio, partition.label())
+        };
+
+        assert_eq!(
+            sums_in_ratio
+                .iter_partitions_2d()
+                .map(describe)
+                .collect::<Vec<_>>(),
+            sums_in_ratio
+                .iter_partitions()
+                .map(describe)
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn we_can_find_partition_at_position() {
+        let (sums_in_ratio, _) = IndeterminateSumsInRatio::<2>::from_str("2+1:1+1")
+            .unwrap()
+            .evaluate(HyperRectangle::from_str("3x2").unwrap())
+            .unwrap();
+
+        let ratio_positions = sums_in_ratio
+            .iter_partitions()
+            .map(|partition| partition.ratio_position)
+            .collect::<Vec<_>>();
+
+        for (index, position) in ratio_positions.into_iter().enumerate() {
+            assert_eq!(sums_in_ratio.partition_at(position), Some(index));
+        }
+        assert_eq!(
+            sums_in_ratio.partition_at([RatioUnits(1), RatioUnits(1)]),
+            Some(1)
+        );
+        assert_eq!(
+            sums_in_ratio.partition_at([RatioUnits(3), RatioUnits(0)]),
+            None
+        );
+    }
+
+    #[test]
+    fn we_can_get_fractions_of_partitions() {
+        let sums_in_ratio = IndeterminateSumsInRatio::<2>::from_str("2+1:1+3")
+            .unwrap()
+            .evaluate(HyperRectangle::from_str("300x400").unwrap())
+            .unwrap()
+            .0;
+        let last = sums_in_ratio.iter_partitions().last().unwrap();
+
+        assert_eq!(last.fraction(0), Ratio::new(1, 3));
+        assert_eq!(last.fraction(1), Ratio::new(3, 4));
+        assert_eq!(last.cumulative_fraction(0), Ratio::new(2, 3));
+        assert_eq!(last.cumulative_fraction(1), Ratio::new(1, 4));
+    }
+
+    #[test]
+    fn we_can_identify_partitions_of_equivalent_expressions() {
+        let ids = |rpex: &str, rectangle: &str| {
+            IndeterminateSumsInRatio::<2>::from_str(rpex)
+                .unwrap()
+                .evaluate(HyperRectangle::from_str(rectangle).unwrap())
+                .unwrap()
+                .0
+                .iter_partitions()
+                .map(|partition| partition.id())
+                .collect::<Vec<_>>()
+        };
+
+        let halves = ids("1+1:1", "200x100");
+
+        assert_eq!(halves, ids("2+2:2", "200x100"));
+        assert_eq!(halves, ids("+:", "1920x1080"));
+        assert_ne!(halves[0], halves[1]);
+        assert_ne!(halves, ids("2+1:1", "300x100")[..2]);
+        assert_eq!(ids(":", "1x1"), [0x707f61cf5ba01565]);
+    }
+
+    #[test]
+    fn we_can_read_partition_metadata() {
+        let sums_in_ratio =
+            IndeterminateSumsInRatio::<2>::from_str("2@main[ws=1,dpi=2]+1@side[ws=9]:[dpi=1]")
+                .unwrap()
+                .evaluate(HyperRectangle::from_str("300x100").unwrap())
+                .unwrap()
+                .0;
+
+        assert_eq!(
+            sums_in_ratio
+                .iter_partitions()
+                .map(|partition| partition.metadata())
+                .collect::<Vec<_>>(),
+            vec![
+                BTreeMap::from([("dpi", "1"), ("ws", "1")]),
+                BTreeMap::from([("dpi", "1"), ("ws", "9")]),
+            ]
+        );
+        assert!(sums_in_ratio
+            .iter_partitions()
+            .zip(sums_in_ratio.iter_partitions_2d())
+            .all(|(partition, partition_2d)| partition.metadata() == partition_2d.metadata()));
+    }
+
+    #[test]
+    fn we_can_approximate_ratios() {
+        let golden_ratio = (1.0 + 5f64.sqrt()) / 2.0;
+
+        assert_eq!(
+            IndeterminateSumsInRatio::approximate_ratio(golden_ratio, 10).to_string(),
+            "5+3:"
+        );
+        assert_eq!(
+            IndeterminateSumsInRatio::approximate_ratio(golden_ratio, 100).to_string(),
+            "55+34:"
+        );
+        assert_eq!(
+            IndeterminateSumsInRatio::approximate_ratio(100.0, 10).to_string(),
+            "9+1:"
+        );
+        assert_eq!(
+            IndeterminateSumsInRatio::approximate_ratio_within(golden_ratio, 0.01, 100)
+                .unwrap()
+                .to_string(),
+            "13+8:"
+        );
+        assert!(
+            IndeterminateSumsInRatio::approximate_ratio_within(golden_ratio, 0.01, 20).is_none()
+        );
+    }
+
+    #[test]
+    fn we_can_choose_grid_closest_to_square() {
+        let grid_for = |n, rectangle| {
+            IndeterminateSumsInRatio::grid_for(n, HyperRectangle::from_str(rectangle).unwrap())
+                .to_string()
+        };
+
+        assert_eq!(grid_for(1, "1920x1080"), ":");
+        assert_eq!(grid_for(6, "1920x1080"), "++:+");
+        assert_eq!(grid_for(6, "1080x1920"), "+:++");
+        assert_eq!(grid_for(7, "1920x1080"), "++++++:");
+        assert_eq!(grid_for(4, "3840x1080"), "+++:");
+    }
+
+    #[test]
+    fn we_can_resolve_unknowns() {
+        let resolve = |rpex: &str, rectangle: &str| {
+            IndeterminateSumsInRatio::<2>::from_str(rpex)
+                .unwrap()
+                .resolve_unknowns(HyperRectangle::from_str(rectangle).unwrap())
+                .map(|rpex| rpex.to_string())
+        };
+
+        assert_eq!(resolve("+:1", "1920x1080").unwrap(), "960+960:1080");
+        assert_eq!(
+            resolve("2@main+:", "1920x1080").unwrap(),
+            "2@main+1918:1080"
+        );
+        assert!(resolve("+:", "1921x1080").is_err());
+    }
+
+    #[test]
+    fn we_can_split_dimension() {
+        let rpex = |rpex: &str| IndeterminateSumsInRatio::<2>::from_str(rpex).unwrap();
+        let split = |rpex: IndeterminateSumsInRatio<2>, dim, at| {
+            let (before, after) = rpex.split_dimension(dim, RatioUnits(at)).unwrap();
+
+            (before.to_string(), after.to_string())
+        };
+
+        assert_eq!(split(rpex("2+1:1"), 0, 2), ("2:1".into(), "1:1".into()));
+        assert_eq!(
+            split(rpex("3@main[ws=1]+:1+1"), 0, 1),
+            ("1@main[ws=1]:1+1".into(), "2@main[ws=1]+:1+1".into())
+        );
+        assert_eq!(
+            split(rpex("2+1@side:1+2"), 1, 2),
+            ("2+1@side:1+1".into(), "2+1@side:1".into())
+        );
+        assert_eq!(split(rpex("1+:1"), 0, 1), ("1:1".into(), ":1".into()));
+
+        assert_eq!(
+            rpex("2+1:1").split_dimension(0, RatioUnits(3)),
+            Err(EditError::CutOutOfRange(3))
+        );
+        assert_eq!(
+            rpex("2+1:1").split_dimension(0, RatioUnits(0)),
+            Err(EditError::CutOutOfRange(0))
+        );
+        assert_eq!(
+            rpex("1++1:1").split_dimension(0, RatioUnits(2)),
+            Err(EditError::CutPastUnknown(2))
+        );
+    }
+
+    #[test]
+    fn we_can_edit_ratio() {
+        let rpex = |rpex: &str| IndeterminateSumsInRatio::<2>::from_str(rpex).unwrap();
+
+        assert_eq!(
+            rpex("2@main+1:1")
+                .insert_addend(0, 0, Some(1))
+                .unwrap()
+                .to_string(),
+            "1+2@main+1:1"
+        );
+        assert_eq!(
+            rpex("1+2@main+1:1")
+                .remove_addend(0, 0)
+                .unwrap()
+                .to_string(),
+            "2@main+1:1"
+        );
+        assert_eq!(
+            rpex("2@main+1:1").split_addend(0, 0).unwrap().to_string(),
+            "1@main+1+1:1"
+        );
+        assert_eq!(
+            rpex("2+1@side:1").split_addend(0, 1).unwrap().to_string(),
+            "4+1@side+1:2"
+        );
+        assert_eq!(
+            rpex("1+@side+1:1").merge_addends(0, 0).unwrap().to_string(),
+            "@side+1:1"
+        );
+        assert_eq!(
+            rpex("1[ws=1]+[ws=2,dpi=2]:1")
+                .merge_addends(0, 0)
+                .unwrap()
+                .to_string(),
+            "[dpi=2,ws=1]:1"
+        );
+        assert_eq!(
+            rpex("2+1:1").merge_addends(1, 0),
+            Err(EditError::NoAddend(1))
+        );
+        assert_eq!(rpex("+:").remove_addend(1, 0), Err(EditError::LastAddend));
+        assert_eq!(
+            rpex("+:").split_dimension(2, RatioUnits(1)),
+            Err(EditError::NoDimension(2))
+        );
+        assert_eq!(
+            rpex("+:").insert_addend(2, 0, None),
+            Err(EditError::NoDimension(2))
+        );
+    }
+
     #[test]
     fn we_cannot_parse_ratio_with_extra_characters() {
         assert!(IndeterminateSumsInRatio::<2>::from_str("1+1::").is_err());
         assert!(IndeterminateSumsInRatio::<2>::from_str("x1+1:1+1").is_err());
     }
+
+    #[test]
+    fn we_cannot_evaluate_zero_length_rectangles() {
+        for (rpex, rectangle) in [("1+1:1", "0x0"), ("+:", "1920x0")] {
+            assert!(matches!(
+                IndeterminateSumsInRatio::<2>::from_str(rpex)
+                    .unwrap()
+                    .evaluate(HyperRectangle::from_str(rectangle).unwrap()),
+                Err(SumsInRatioEvaluationError::ZeroLength(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn we_cannot_evaluate_ratio_with_unequal_scales() {
+        let Err(SumsInRatioEvaluationError::UnequalScales(scales)) =
+            IndeterminateSumsInRatio::<2>::from_str("2:1")
+                .unwrap()
+                .evaluate(HyperRectangle::from_str("1920x1080").unwrap())
+        else {
+            panic!("expected unequal scales");
+        };
+
+        assert_eq!(scales, HashSet::from([960, 1080]));
+    }
+
+    #[test]
+    fn we_cannot_evaluate_ratio_with_overflowing_addends() {
+        for rpex in ["4000000000+4000000000:1", "1:300000000+", "4294967295+1+:1"] {
+            assert!(matches!(
+                IndeterminateSumsInRatio::<2>::from_str(rpex)
+                    .unwrap()
+                    .evaluate(HyperRectangle::from_str("1920x1080").unwrap()),
+                Err(SumsInRatioEvaluationError::DimensionSumEvaluation(
+                    DimensionSumEvaluationError::Overflow
+                ))
+            ));
+        }
+    }
 }
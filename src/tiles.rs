@@ -0,0 +1,124 @@
+use crate::{
+    export::CoordinateMapping,
+    layout::{Cell, Layout},
+    rectangle::HyperRectangle,
+    sums_in_ratio::SumsInRatioEvaluationError,
+    Rpex,
+};
+
+/// A tile of [`tile_scheme`], at column `x` and row `y` of the grid of its zoom level `z`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Tile {
+    pub z: usize,
+    pub x: u32,
+    pub y: u32,
+    pub cell: Cell<2>,
+}
+
+impl Tile {
+    /// The `[min_x, min_y, max_x, max_y]` bounds of the tile once mapped by `mapping`.
+    pub fn bounding_box(&self, mapping: &CoordinateMapping) -> [f64; 4] {
+        let [x, y] = self.cell.position;
+        let [width, height] = self.cell.rectangle.lengths;
+        let [x0, y0] = mapping.map([x, y]);
+        let [x1, y1] = mapping.map([x + width, y + height]);
+
+        [x0.min(x1), y0.min(y1), x0.max(x1), y0.max(y1)]
+    }
+}
+
+/// Tiles `extent` by zoom levels, where every tile of a level is split into the tiles of the next
+/// by the expression of that level, starting with a single tile at zoom 0.
+///
+/// Tiles are numbered across their whole level, so that a tile at `x, y` split into `c` columns
+/// and `r` rows has children from `x * c, y * r`, like in slippy maps. Tiles are yielded level by
+/// level, and in column-major order within a level.
+pub fn tile_scheme(
+    extent: HyperRectangle<2>,
+    levels: &[Rpex<2>],
+) -> Result<Vec<Tile>, SumsInRatioEvaluationError> {
+    let mut tiles = vec![Tile {
+        z: 0,
+        x: 0,
+        y: 0,
+        cell: Cell {
+            position: [0, 0],
+            rectangle: extent,
+        },
+    }];
+    let mut parents = 0..tiles.len();
+
+    for (level, rpex) in levels.iter().enumerate() {
+        let [columns, rows] = [0, 1].map(|dim| rpex.sums[dim].addends.len() as u32);
+
+        for parent in parents.clone() {
+            let Tile { x, y, cell, .. } = tiles[parent];
+            let layout = Layout::from(rpex.clone().evaluate(cell.rectangle)?);
+
+            let children = layout
+                .iter_cells()
+                .zip(0..)
+                .map(|(child, index)| Tile {
+                    z: level + 1,
+                    x: x * columns + index / rows,
+                    y: y * rows + index % rows,
+                    cell: Cell {
+                        position: [0, 1].map(|dim| cell.position[dim] + child.position[dim]),
+                        rectangle: child.rectangle,
+                    },
+                })
+                .collect::<Vec<_>>();
+
+            tiles.extend(children);
+        }
+
+        parents = parents.end..tiles.len();
+    }
+
+    Ok(tiles)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    #[test]
+    fn we_can_build_tile_schemes() {
+        let levels = [
+            Rpex::from_str("+:+").unwrap(),
+            Rpex::from_str("1+2:1").unwrap(),
+        ];
+        let tiles = tile_scheme(
+            HyperRectangle {
+                lengths: [600, 200],
+            },
+            &levels,
+        )
+        .unwrap();
+
+        assert_eq!(tiles.len(), 1 + 4 + 8);
+        assert_eq!(
+            tiles.last().unwrap(),
+            &Tile {
+                z: 2,
+                x: 3,
+                y: 1,
+                cell: Cell {
+                    position: [400, 100],
+                    rectangle: HyperRectangle {
+                        lengths: [200, 100]
+                    },
+                },
+            }
+        );
+
+        let mapping = CoordinateMapping {
+            origin: [0.0, 20.0],
+            pixel_size: [0.1, -0.1],
+        };
+
+        assert_eq!(tiles[4].bounding_box(&mapping), [30.0, 0.0, 60.0, 10.0]);
+    }
+}
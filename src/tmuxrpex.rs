@@ -0,0 +1,121 @@
+use std::io;
+use std::process::Command;
+
+use clap::Parser;
+use rpex::{Rpex, Split, SplitDirection};
+use thiserror::Error;
+
+/// Split the current tmux pane into panes by an expression
+#[derive(Parser)]
+struct TmuxRpexArgs {
+    rpex: Rpex<2>,
+    /// Id of the pane to split, e.g. %3, the pane tmuxrpex runs in by default
+    #[arg(long, env = "TMUX_PANE")]
+    pane: String,
+    /// Print the tmux commands instead of running them
+    #[arg(long)]
+    dry_run: bool,
+}
+
+#[derive(Error, Debug)]
+enum TmuxRpexError {
+    #[error("unable to run tmux: {0}")]
+    Io(#[from] io::Error),
+    #[error("tmux {0} failed: {1}")]
+    Failed(String, String),
+    #[error("tmux split-window printed {0:?} instead of a pane id")]
+    InvalidPaneId(String),
+}
+
+fn tmux(args: &[String]) -> Result<String, TmuxRpexError> {
+    let output = Command::new("tmux").args(args).output()?;
+
+    if !output.status.success() {
+        return Err(TmuxRpexError::Failed(
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr)
+                .trim_end()
+                .to_string(),
+        ));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+// Sizes given to -l as percentages are those of the split pane, and need tmux 3.1. The new pane
+// is printed so that later splits can target it.
+fn split_window_args(
+    Split {
+        direction, percent, ..
+    }: Split,
+    pane: &str,
+) -> Vec<String> {
+    let side = match direction {
+        SplitDirection::Right => "-h",
+        SplitDirection::Down => "-v",
+    };
+
+    [
+        "split-window",
+        side,
+        "-t",
+        pane,
+        "-l",
+        &format!("{percent}%"),
+        "-P",
+        "-F",
+        "#{pane_id}",
+    ]
+    .map(str::to_string)
+    .to_vec()
+}
+
+fn main() -> Result<(), TmuxRpexError> {
+    let args = TmuxRpexArgs::parse();
+
+    let mut panes = vec![args.pane];
+
+    // Panes created by a dry run don't exist, so they are referred to by their pane number.
+    for split in args.rpex.splits() {
+        let split_args = split_window_args(split, &panes[split.pane]);
+
+        if args.dry_run {
+            println!("tmux {}", split_args.join(" "));
+            panes.push(format!("$PANE_{}", panes.len()));
+            continue;
+        }
+
+        let pane = tmux(&split_args)?;
+        let pane = pane.trim();
+
+        if pane
+            .strip_prefix('%')
+            .is_none_or(|id| id.parse::<u32>().is_err())
+        {
+            return Err(TmuxRpexError::InvalidPaneId(pane.to_string()));
+        }
+
+        panes.push(pane.to_string());
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn we_can_build_split_window_args() {
+        let split = Split {
+            pane: 0,
+            direction: SplitDirection::Right,
+            percent: 33,
+        };
+
+        assert_eq!(
+            split_window_args(split, "%1").join(" "),
+            "split-window -h -t %1 -l 33% -P -F #{pane_id}"
+        );
+    }
+}
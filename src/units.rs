@@ -0,0 +1,50 @@
+use std::{fmt::Display, ops::Add};
+
+/// A length or position in units of the ratios of an expression, before scaling to pixels.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct RatioUnits(pub u32);
+
+/// A length or position in pixels.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Px(pub u32);
+
+impl RatioUnits {
+    /// Converts to pixels at `scale` pixels per ratio unit, see [`crate::Layout::scale`].
+    pub fn to_px(self, scale: u32) -> Px {
+        Px(self.0 * scale)
+    }
+}
+
+impl Add for RatioUnits {
+    type Output = RatioUnits;
+
+    fn add(self, rhs: RatioUnits) -> RatioUnits {
+        RatioUnits(self.0 + rhs.0)
+    }
+}
+
+impl Add for Px {
+    type Output = Px;
+
+    fn add(self, rhs: Px) -> Px {
+        Px(self.0 + rhs.0)
+    }
+}
+
+impl From<Px> for u32 {
+    fn from(Px(px): Px) -> u32 {
+        px
+    }
+}
+
+impl Display for RatioUnits {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl Display for Px {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}px", self.0)
+    }
+}
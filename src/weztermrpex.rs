@@ -0,0 +1,110 @@
+use std::io;
+use std::process::Command;
+
+use clap::Parser;
+use rpex::{Rpex, Split, SplitDirection};
+use thiserror::Error;
+
+/// Split the current WezTerm pane into panes by an expression
+#[derive(Parser)]
+struct WeztermRpexArgs {
+    rpex: Rpex<2>,
+    /// Id of the pane to split, the pane weztermrpex runs in by default
+    #[arg(long, env = "WEZTERM_PANE")]
+    pane: u32,
+    /// Print the wezterm commands instead of running them
+    #[arg(long)]
+    dry_run: bool,
+}
+
+#[derive(Error, Debug)]
+enum WeztermRpexError {
+    #[error("unable to run wezterm: {0}")]
+    Io(#[from] io::Error),
+    #[error("wezterm cli {0} failed: {1}")]
+    Failed(String, String),
+    #[error("wezterm cli split-pane printed {0:?} instead of a pane id")]
+    InvalidPaneId(String),
+}
+
+fn wezterm_cli(args: &[String]) -> Result<String, WeztermRpexError> {
+    let output = Command::new("wezterm").arg("cli").args(args).output()?;
+
+    if !output.status.success() {
+        return Err(WeztermRpexError::Failed(
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr)
+                .trim_end()
+                .to_string(),
+        ));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+// The percentage is that of the split pane which the new pane takes.
+fn split_pane_args(
+    Split {
+        direction, percent, ..
+    }: Split,
+    pane: &str,
+) -> Vec<String> {
+    let side = match direction {
+        SplitDirection::Right => "--right",
+        SplitDirection::Down => "--bottom",
+    };
+
+    vec![
+        "split-pane".to_string(),
+        format!("--pane-id={pane}"),
+        side.to_string(),
+        format!("--percent={percent}"),
+    ]
+}
+
+fn main() -> Result<(), WeztermRpexError> {
+    let args = WeztermRpexArgs::parse();
+
+    let mut panes = vec![args.pane.to_string()];
+
+    // Panes created by a dry run don't exist, so they are referred to by their pane number.
+    for split in args.rpex.splits() {
+        let split_args = split_pane_args(split, &panes[split.pane]);
+
+        if args.dry_run {
+            println!("wezterm cli {}", split_args.join(" "));
+            panes.push(format!("$PANE_{}", panes.len()));
+            continue;
+        }
+
+        let pane = wezterm_cli(&split_args)?;
+        let pane = pane.trim();
+
+        if pane.parse::<u32>().is_err() {
+            return Err(WeztermRpexError::InvalidPaneId(pane.to_string()));
+        }
+
+        panes.push(pane.to_string());
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn we_can_build_split_pane_args() {
+        let split = Split {
+            pane: 0,
+            direction: SplitDirection::Down,
+            percent: 50,
+        };
+
+        assert_eq!(
+            split_pane_args(split, "4").join(" "),
+            "split-pane --pane-id=4 --bottom --percent=50"
+        );
+    }
+}
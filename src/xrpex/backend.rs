@@ -0,0 +1,104 @@
+use std::ffi::OsString;
+use std::fmt::Display;
+
+use clap::ValueEnum;
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum Backend {
+    /// Detect the backend from the session environment
+    #[default]
+    Auto,
+    /// Virtual monitors through the X RandR extension
+    Xrandr,
+    /// Outputs of the sway compositor, which can be read but not split
+    Sway,
+    /// Logical monitors of GNOME's mutter through D-Bus, which can be read but not split
+    Mutter,
+    /// Heads of wlroots-based compositors through wlr-output-management, which can be read but not
+    /// split
+    Wlr,
+    /// In-memory monitors read from and written to the file at XRPEX_MOCK_STATE
+    #[cfg(any(test, feature = "test-support"))]
+    Mock,
+}
+
+impl Backend {
+    // Wayland sessions usually also run XWayland and set DISPLAY, so compositors are checked
+    // before falling back to X. Hyprland implements wlr-output-management, so it is treated like
+    // any other wlroots-based compositor.
+    pub fn detect(var: impl Fn(&str) -> Option<OsString>) -> Option<Backend> {
+        let is_set = |name: &str| var(name).is_some_and(|value| !value.is_empty());
+        let is_gnome = var("XDG_CURRENT_DESKTOP")
+            .is_some_and(|desktops| desktops.to_string_lossy().split(':').any(|d| d == "GNOME"));
+
+        if is_set("SWAYSOCK") {
+            Some(Backend::Sway)
+        } else if is_set("WAYLAND_DISPLAY") && is_gnome {
+            Some(Backend::Mutter)
+        } else if is_set("WAYLAND_DISPLAY") || is_set("HYPRLAND_INSTANCE_SIGNATURE") {
+            Some(Backend::Wlr)
+        } else if is_set("DISPLAY") {
+            Some(Backend::Xrandr)
+        } else {
+            None
+        }
+    }
+
+    pub fn resolve(self) -> Option<Backend> {
+        match self {
+            Backend::Auto => Backend::detect(|name| std::env::var_os(name)),
+            backend => Some(backend),
+        }
+    }
+}
+
+impl Display for Backend {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let value = self.to_possible_value().expect("no backend is skipped");
+
+        f.write_str(value.get_name())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn we_can_detect_backend_from_environment() {
+        let detect = |vars: &[(&str, &str)]| {
+            Backend::detect(|name| {
+                vars.iter()
+                    .find(|(var, _)| *var == name)
+                    .map(|(_, value)| OsString::from(value))
+            })
+        };
+
+        assert_eq!(detect(&[("DISPLAY", ":0")]), Some(Backend::Xrandr));
+        assert_eq!(
+            detect(&[("DISPLAY", ":0"), ("SWAYSOCK", "/run/sway.sock")]),
+            Some(Backend::Sway)
+        );
+        assert_eq!(
+            detect(&[("HYPRLAND_INSTANCE_SIGNATURE", "abc"), ("DISPLAY", "")]),
+            Some(Backend::Wlr)
+        );
+        assert_eq!(
+            detect(&[
+                ("DISPLAY", ":0"),
+                ("WAYLAND_DISPLAY", "wayland-0"),
+                ("XDG_CURRENT_DESKTOP", "ubuntu:GNOME")
+            ]),
+            Some(Backend::Mutter)
+        );
+        assert_eq!(
+            detect(&[("DISPLAY", ":0"), ("XDG_CURRENT_DESKTOP", "GNOME")]),
+            Some(Backend::Xrandr)
+        );
+        assert_eq!(
+            detect(&[("DISPLAY", ":0"), ("WAYLAND_DISPLAY", "wayland-1")]),
+            Some(Backend::Wlr)
+        );
+        assert_eq!(detect(&[("DISPLAY", "")]), None);
+    }
+}
@@ -0,0 +1,310 @@
+use std::path::Path;
+
+use rpex::{HyperRectangle, Rpex, SpannedParseError};
+use toml::{Table, Value};
+
+use crate::config::XrpexConfig;
+use crate::doctor::{Finding, Severity};
+use crate::layout_file::{migrate, LayoutFile};
+use crate::monitor::RpexMonitor;
+
+// The resolution an expression is evaluated on, and where it came from.
+type Resolution = (HyperRectangle<2>, &'static str);
+
+// Points at the mistake under the expression, like `2+x:1` over `  ^`.
+fn underline(rpex: &str, error: &SpannedParseError) -> String {
+    let width = error.span.len().max(1);
+
+    format!(
+        "\n    {rpex}\n    {}{}",
+        " ".repeat(error.span.start),
+        "^".repeat(width)
+    )
+}
+
+fn check_expression(
+    check: &'static str,
+    location: &str,
+    value: &Value,
+    resolution: Option<Resolution>,
+) -> Vec<Finding> {
+    let Value::String(source) = value else {
+        return vec![Finding::error(
+            check,
+            format!(
+                "{location}: expected an expression, found {}",
+                value.type_str()
+            ),
+            "quote the expression, e.g. \"1+1:1\"",
+        )];
+    };
+
+    let (rpex, errors) = Rpex::<2>::parse_lenient(source);
+
+    if !errors.is_empty() {
+        return errors
+            .iter()
+            .map(|error| {
+                Finding::error(
+                    check,
+                    format!("{location}: {}{}", error.error, underline(source, error)),
+                    "addends are integers or empty for unknowns, optionally followed by @label \
+                     and [key=value]",
+                )
+            })
+            .collect();
+    }
+
+    let finding = match resolution {
+        Some((rectangle, origin)) => match rpex.clone().evaluate(rectangle) {
+            Ok(_) => Finding::ok(
+                check,
+                format!("{location}: {rpex} fits the {origin} resolution {rectangle}"),
+            ),
+            Err(e) => Finding::error(
+                check,
+                format!("{location}: {rpex} doesn't fit the {origin} resolution {rectangle}: {e}"),
+                "make the expression's dimensions agree with the monitor's aspect ratio, or leave \
+                 an addend empty to absorb the difference",
+            ),
+        },
+        None => Finding::warning(
+            check,
+            format!("{location}: {rpex} parses, but its monitor isn't connected to evaluate it on"),
+            "check again with the monitor connected",
+        ),
+    };
+
+    vec![finding]
+}
+
+fn current_resolution(monitors: &[RpexMonitor], name: &str) -> Option<Resolution> {
+    monitors
+        .iter()
+        .find(|monitor| monitor.name == name)
+        .map(|monitor| (monitor.resolution, "current"))
+}
+
+// Only expressions are checked one by one, so that every mistake is reported at once. Anything
+// else that is wrong is reported as the first error loading the whole file runs into.
+fn check_rest(
+    check: &'static str,
+    path: &Path,
+    findings: &mut Vec<Finding>,
+    load: impl FnOnce() -> Result<(), String>,
+) {
+    let has_errors = findings
+        .iter()
+        .any(|finding| finding.severity == Severity::Error);
+
+    if let (false, Err(e)) = (has_errors, load()) {
+        findings.push(Finding::error(
+            check,
+            format!("{}: {e}", path.display()),
+            "fix or remove the setting",
+        ));
+    }
+}
+
+pub fn check_config_file(path: &Path, contents: &str, monitors: &[RpexMonitor]) -> Vec<Finding> {
+    let document = match contents.parse::<Table>() {
+        Ok(document) => document,
+        Err(e) => {
+            return vec![Finding::error(
+                "config",
+                format!("{}: {e}", path.display()),
+                "fix the TOML syntax",
+            )]
+        }
+    };
+
+    let mut findings = match document.get("monitors") {
+        Some(Value::Table(targets)) => targets
+            .iter()
+            .flat_map(|(name, value)| {
+                check_expression(
+                    "config",
+                    &format!("{}: {name}", path.display()),
+                    value,
+                    current_resolution(monitors, name),
+                )
+            })
+            .collect(),
+        _ => vec![],
+    };
+
+    check_rest("config", path, &mut findings, || {
+        toml::from_str::<XrpexConfig>(contents)
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+    });
+
+    findings
+}
+
+// Profiles record the geometry of each monitor, which expressions are evaluated on when the
+// monitor isn't connected.
+pub fn check_profile_file(path: &Path, contents: &str, monitors: &[RpexMonitor]) -> Vec<Finding> {
+    let document = match contents
+        .parse::<Table>()
+        .map_err(|e| e.to_string())
+        .and_then(|document| migrate(document).map_err(|e| e.to_string()))
+    {
+        Ok(document) => document,
+        Err(e) => {
+            return vec![Finding::error(
+                "profile",
+                format!("{}: {e}", path.display()),
+                "save the profile again with `xrpex profile save`",
+            )]
+        }
+    };
+
+    let mut findings = vec![];
+
+    if let Some(Value::Table(targets)) = document.get("monitors") {
+        for (name, layout) in targets {
+            let location = format!("{}: {name}", path.display());
+
+            let Some(rpex) = layout.get("rpex") else {
+                findings.push(Finding::error(
+                    "profile",
+                    format!("{location}: missing rpex"),
+                    "save the profile again with `xrpex profile save`",
+                ));
+                continue;
+            };
+
+            let recorded = layout
+                .get("geometry")
+                .and_then(|geometry| {
+                    let length = |key| u32::try_from(geometry.get(key)?.as_integer()?).ok();
+
+                    Some(HyperRectangle {
+                        lengths: [length("width")?, length("height")?],
+                    })
+                })
+                .map(|rectangle| (rectangle, "recorded"));
+
+            findings.extend(check_expression(
+                "profile",
+                &location,
+                rpex,
+                current_resolution(monitors, name).or(recorded),
+            ));
+        }
+    }
+
+    check_rest("profile", path, &mut findings, || {
+        LayoutFile::from_toml(contents)
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+    });
+
+    findings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn monitor(name: &str, lengths: [u32; 2]) -> RpexMonitor {
+        RpexMonitor {
+            name: name.to_string(),
+            position: [0, 0],
+            resolution: HyperRectangle { lengths },
+            physical_size: [0, 0],
+        }
+    }
+
+    #[test]
+    fn we_can_report_every_invalid_expression_in_config() {
+        let findings = check_config_file(
+            Path::new("config.toml"),
+            r#"
+            gaps = 8
+
+            [monitors]
+            DP-1 = "2+x:1"
+            DP-2 = "2+1:1"
+            HDMI-1 = "+:"
+            eDP-1 = "1:1:1"
+            "#,
+            &[
+                monitor("DP-2", [1920, 1080]),
+                monitor("HDMI-1", [1920, 1080]),
+            ],
+        );
+
+        // Unequal scales are listed in no particular order, so only what comes before them is
+        // compared.
+        let summary = findings
+            .iter()
+            .map(|finding| {
+                let first_line = finding.message.lines().next().unwrap();
+
+                (
+                    finding.severity,
+                    first_line.split(": inferred").next().unwrap(),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        // Other mistakes are only reported once the expressions are fixed.
+        assert_eq!(
+            summary,
+            vec![
+                (Severity::Error, "config.toml: DP-1: invalid addend \"x\""),
+                (
+                    Severity::Error,
+                    "config.toml: DP-2: 2+1:1 doesn't fit the current resolution 1920x1080"
+                ),
+                (
+                    Severity::Ok,
+                    "config.toml: HDMI-1: +: fits the current resolution 1920x1080"
+                ),
+                (
+                    Severity::Error,
+                    "config.toml: eDP-1: expected 2 dimensions, found 3"
+                ),
+            ]
+        );
+        assert!(findings[0].message.ends_with("\n    2+x:1\n      ^"));
+    }
+
+    #[test]
+    fn we_can_check_profiles_against_recorded_geometry() {
+        let findings = check_profile_file(
+            Path::new("work.rpexlayout"),
+            r#"
+            version = 1
+
+            [monitors.DP-1]
+            rpex = "2+1:1"
+            geometry = { x = 0, y = 0, width = 3840, height = 1280 }
+
+            [monitors.DP-2]
+            rpex = "+:"
+            "#,
+            &[],
+        );
+
+        assert_eq!(
+            findings
+                .iter()
+                .map(|finding| finding.severity)
+                .collect::<Vec<_>>(),
+            vec![Severity::Ok, Severity::Warning]
+        );
+        assert!(findings[0]
+            .message
+            .contains("recorded resolution 3840x1280"));
+
+        let findings = check_profile_file(Path::new("old.toml"), "[monitors]\nDP-1 = \"1@\"", &[]);
+
+        assert_eq!(findings[0].severity, Severity::Error);
+        assert!(findings[0]
+            .message
+            .starts_with("old.toml: DP-1: invalid addend"));
+    }
+}
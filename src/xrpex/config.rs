@@ -0,0 +1,205 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use clap::Args;
+use rpex::Rpex;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::hooks::Hooks;
+use crate::layout_file::{LayoutFile, LayoutFileError, LAYOUT_FILE_EXTENSION};
+use crate::name_template::NameTemplate;
+
+#[derive(Args, Clone, Debug, Default, Deserialize, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(deny_unknown_fields)]
+pub struct GapArgs {
+    /// Pixels to leave between neighbouring virtual monitors
+    #[arg(short, long, global = true, value_name = "PX")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    gap: Option<u32>,
+    /// Pixels to leave between horizontally neighbouring virtual monitors, overriding --gap
+    #[arg(long, global = true, value_name = "PX")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    gap_x: Option<u32>,
+    /// Pixels to leave between vertically neighbouring virtual monitors, overriding --gap
+    #[arg(long, global = true, value_name = "PX")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    gap_y: Option<u32>,
+}
+
+impl GapArgs {
+    pub fn or(self, fallback: GapArgs) -> GapArgs {
+        GapArgs {
+            gap: self.gap.or(fallback.gap),
+            gap_x: self.gap_x.or(fallback.gap_x),
+            gap_y: self.gap_y.or(fallback.gap_y),
+        }
+    }
+
+    pub fn gutter(&self) -> [u32; 2] {
+        let gap = self.gap.unwrap_or(0);
+
+        [self.gap_x.unwrap_or(gap), self.gap_y.unwrap_or(gap)]
+    }
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(deny_unknown_fields)]
+pub struct XrpexConfig {
+    /// Expression to split each monitor by, keyed by monitor name
+    #[serde(default)]
+    pub monitors: BTreeMap<String, Rpex<2>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name_template: Option<NameTemplate>,
+    #[serde(flatten)]
+    pub gap: GapArgs,
+    /// Workspaces to move onto the created virtual monitors through i3 or sway IPC, in order
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub workspaces: Vec<String>,
+    /// Size virtual monitors in millimetres as if they had this many dots per inch
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dpi: Option<u32>,
+    /// Shell commands to run around changes to virtual monitors, also when applying expressions
+    /// given on the command line
+    #[serde(default, skip_serializing_if = "Hooks::is_empty")]
+    pub hooks: Hooks,
+}
+
+#[derive(Error, Debug)]
+pub enum ConfigError {
+    #[error("unable to locate config directory")]
+    NoConfigDir,
+    #[error("unable to read config file {0}: {1}")]
+    Io(PathBuf, io::Error),
+    #[error("unable to parse config file: {0}")]
+    Toml(#[from] toml::de::Error),
+    #[error("unable to serialize config: {0}")]
+    TomlSer(#[from] toml::ser::Error),
+}
+
+impl XrpexConfig {
+    pub fn default_path() -> Result<PathBuf, ConfigError> {
+        Ok(dirs::config_dir()
+            .ok_or(ConfigError::NoConfigDir)?
+            .join("xrpex")
+            .join("config.toml"))
+    }
+
+    pub fn load(path: &Path) -> Result<XrpexConfig, ConfigError> {
+        let contents =
+            fs::read_to_string(path).map_err(|e| ConfigError::Io(path.to_path_buf(), e))?;
+
+        Ok(toml::from_str(&contents)?)
+    }
+
+    pub fn to_toml(&self) -> Result<String, ConfigError> {
+        Ok(toml::to_string(self)?)
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum ProfileError {
+    #[error("unable to locate config directory")]
+    NoConfigDir,
+    #[error("invalid profile name {0}")]
+    InvalidName(String),
+    #[error(transparent)]
+    Layout(#[from] LayoutFileError),
+}
+
+fn profiles_dir() -> Result<PathBuf, ProfileError> {
+    Ok(dirs::config_dir()
+        .ok_or(ProfileError::NoConfigDir)?
+        .join("xrpex")
+        .join("profiles"))
+}
+
+pub fn profile_path(name: &str) -> Result<PathBuf, ProfileError> {
+    if name.is_empty() || name.contains(std::path::is_separator) {
+        return Err(ProfileError::InvalidName(name.to_string()));
+    }
+
+    Ok(profiles_dir()?.join(format!("{name}.{LAYOUT_FILE_EXTENSION}")))
+}
+
+// Legacy profiles are left out when a profile of the same name has been saved since, like
+// `load_profile` does.
+pub fn profile_paths() -> Result<Vec<PathBuf>, ProfileError> {
+    let Ok(entries) = fs::read_dir(profiles_dir()?) else {
+        return Ok(vec![]);
+    };
+
+    let mut paths = entries
+        .filter_map(|entry| Some(entry.ok()?.path()))
+        .filter(|path| match path.extension().and_then(|e| e.to_str()) {
+            Some(LAYOUT_FILE_EXTENSION) => true,
+            Some("toml") => !path.with_extension(LAYOUT_FILE_EXTENSION).exists(),
+            _ => false,
+        })
+        .collect::<Vec<_>>();
+
+    paths.sort();
+
+    Ok(paths)
+}
+
+// Profiles used to be saved with a .toml extension, and are still loaded from there.
+pub fn load_profile(name: &str) -> Result<LayoutFile, ProfileError> {
+    let path = profile_path(name)?;
+    let legacy_path = path.with_extension("toml");
+
+    if !path.exists() && legacy_path.exists() {
+        return Ok(LayoutFile::load(&legacy_path)?);
+    }
+
+    Ok(LayoutFile::load(&path)?)
+}
+
+pub fn save_profile(name: &str, layout: &LayoutFile) -> Result<PathBuf, ProfileError> {
+    let path = profile_path(name)?;
+
+    layout.save(&path)?;
+
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    #[test]
+    fn we_can_parse_config() {
+        let config: XrpexConfig = toml::from_str(
+            r#"
+            name_template = "{parent}-{index}"
+            gap = 8
+            gap_y = 4
+            workspaces = ["1", "2: web"]
+
+            [monitors]
+            DP-1 = "2+1:1"
+
+            [hooks]
+            post_apply = ["polybar-msg cmd restart"]
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.monitors["DP-1"], Rpex::from_str("2+1:1").unwrap());
+        assert_eq!(
+            config.name_template,
+            Some(NameTemplate::from_str("{parent}-{index}").unwrap())
+        );
+        assert_eq!(config.gap.gutter(), [8, 4]);
+        assert_eq!(config.workspaces, vec!["1", "2: web"]);
+        assert_eq!(config.hooks.post_apply, vec!["polybar-msg cmd restart"]);
+        assert!(config.hooks.pre_reset.is_empty());
+        assert!(toml::from_str::<XrpexConfig>("gaps = 8").is_err());
+    }
+}
@@ -0,0 +1,215 @@
+use std::str::FromStr;
+
+use rpex::{Cell, HyperRectangle, Layout, Rpex};
+use tracing::info;
+use zbus::fdo;
+
+use crate::hooks::Hooks;
+use crate::monitor::RpexMonitorManager;
+use crate::name_template::NameTemplate;
+use crate::registry::BackendRegistry;
+use crate::{apply, reset, ApplyOptions, XrpexError};
+
+pub const SERVICE_NAME: &str = "dev.rpex.Layout";
+pub const OBJECT_PATH: &str = "/dev/rpex/Layout";
+
+enum Action {
+    Apply(String, Rpex<2>),
+    Reset(String),
+}
+
+fn perform<M: RpexMonitorManager>(
+    open: impl FnOnce() -> Result<M, M::ManagerError>,
+    action: Action,
+    options: ApplyOptions,
+) -> Result<(), XrpexError>
+where
+    XrpexError: From<M::ManagerError>,
+{
+    match action {
+        Action::Apply(monitor, rpex) => apply(open, vec![(monitor, rpex)], options),
+        Action::Reset(monitor) => reset(
+            &mut open()?,
+            Some(&monitor),
+            None,
+            &options.name_template,
+            &options.hooks,
+        ),
+    }
+}
+
+// A failed call is answered with the same stable code that xrpex prints with --output json.
+fn failed(e: XrpexError) -> fdo::Error {
+    fdo::Error::Failed(format!("{}: {e}", e.code()))
+}
+
+/// Options that every call applies and resets with, from the arguments xrpex was started with.
+pub struct LayoutService {
+    pub registry: BackendRegistry,
+    pub backend: Option<String>,
+    pub name_template: NameTemplate,
+    pub gap: [u32; 2],
+    pub dpi: Option<u32>,
+    pub force: bool,
+    pub hooks: Hooks,
+}
+
+impl LayoutService {
+    fn options(&self) -> ApplyOptions {
+        ApplyOptions {
+            name_template: self.name_template.clone(),
+            gap: self.gap,
+            record_history: true,
+            dpi: self.dpi,
+            force: self.force,
+            hooks: self.hooks.clone(),
+            ..Default::default()
+        }
+    }
+
+    // The backend is opened again for every call, so that monitors plugged in since are found.
+    fn dispatch(&self, action: Action) -> Result<(), XrpexError> {
+        let backend = self.backend.as_deref().ok_or(XrpexError::NoBackend)?;
+
+        // Every action changes monitors.
+        if self.registry.is_read_only(backend) {
+            return Err(XrpexError::ReadOnlyBackend(backend.to_string()));
+        }
+
+        let open = self
+            .registry
+            .get(backend)
+            .ok_or_else(|| XrpexError::UnsupportedBackend(backend.to_string()))?;
+
+        perform(open, action, self.options())
+    }
+}
+
+#[zbus::interface(name = "dev.rpex.Layout1")]
+impl LayoutService {
+    /// Evaluates an expression on a rectangle like `1920x1080`, returning the x, y, width and
+    /// height of every cell.
+    fn evaluate(&self, rpex: &str, rectangle: &str) -> fdo::Result<Vec<(u32, u32, u32, u32)>> {
+        let rpex = Rpex::<2>::from_str(rpex)
+            .map_err(|e| fdo::Error::InvalidArgs(format!("unable to parse expression: {e}")))?;
+        let rectangle = HyperRectangle::<2>::from_str(rectangle)
+            .map_err(|e| fdo::Error::InvalidArgs(format!("unable to parse rectangle: {e}")))?;
+
+        let layout = Layout::from(
+            rpex.evaluate(rectangle)
+                .map_err(|e| failed(XrpexError::RpexEvaluation(e)))?,
+        )
+        .with_gutter(self.gap);
+
+        Ok(layout
+            .iter_cells()
+            .map(
+                |Cell {
+                     position: [x, y],
+                     rectangle,
+                 }| {
+                    let [width, height] = rectangle.lengths;
+                    (x, y, width, height)
+                },
+            )
+            .collect())
+    }
+
+    /// Splits a monitor into virtual monitors by an expression, replacing its existing ones.
+    fn apply(&self, monitor: &str, rpex: &str) -> fdo::Result<()> {
+        let rpex = Rpex::<2>::from_str(rpex)
+            .map_err(|e| fdo::Error::InvalidArgs(format!("unable to parse expression: {e}")))?;
+
+        info!(monitor, %rpex, "applying over D-Bus");
+
+        self.dispatch(Action::Apply(monitor.to_string(), rpex))
+            .map_err(failed)
+    }
+
+    /// Deletes the virtual monitors of a monitor.
+    fn reset(&self, monitor: &str) -> fdo::Result<()> {
+        info!(monitor, "resetting over D-Bus");
+
+        self.dispatch(Action::Reset(monitor.to_string()))
+            .map_err(failed)
+    }
+}
+
+/// Serves `service` on the session bus until xrpex is stopped.
+pub fn serve(service: LayoutService) -> Result<(), zbus::Error> {
+    let _connection = zbus::blocking::connection::Builder::session()?
+        .name(SERVICE_NAME)?
+        .serve_at(OBJECT_PATH, service)?
+        .build()?;
+
+    info!(name = SERVICE_NAME, path = OBJECT_PATH, "serving");
+
+    loop {
+        std::thread::park();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::{MockMonitor, MockMonitorManager};
+
+    fn service() -> LayoutService {
+        LayoutService {
+            registry: BackendRegistry::builtin(),
+            backend: Some("mock".to_string()),
+            name_template: NameTemplate::default(),
+            gap: [0, 0],
+            dpi: None,
+            force: false,
+            hooks: Hooks::default(),
+        }
+    }
+
+    #[test]
+    fn we_can_evaluate_over_dbus() {
+        assert_eq!(
+            service().evaluate("2+1:1", "3840x1280").unwrap(),
+            vec![(0, 0, 2560, 1280), (2560, 0, 1280, 1280)]
+        );
+        assert!(matches!(
+            service().evaluate("2+1:1", "1920x1080"),
+            Err(fdo::Error::Failed(message)) if message.starts_with("evaluation: ")
+        ));
+        assert!(matches!(
+            service().evaluate("2+1;1", "1920x1080"),
+            Err(fdo::Error::InvalidArgs(_))
+        ));
+    }
+
+    #[test]
+    fn we_can_apply_over_dbus() {
+        let options = || ApplyOptions {
+            record_history: false,
+            ..service().options()
+        };
+        let open = || {
+            Ok(MockMonitorManager::new(vec![MockMonitor {
+                name: "DP-1".to_string(),
+                width: 1920,
+                height: 1080,
+                ..Default::default()
+            }]))
+        };
+
+        assert!(perform(
+            open,
+            Action::Apply("DP-1".to_string(), Rpex::from_str("+:").unwrap()),
+            options()
+        )
+        .is_ok());
+        assert!(matches!(
+            perform(
+                open,
+                Action::Apply("HDMI-1".to_string(), Rpex::from_str("+:").unwrap()),
+                options()
+            ),
+            Err(XrpexError::NoMonitor(_))
+        ));
+    }
+}
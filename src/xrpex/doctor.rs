@@ -0,0 +1,227 @@
+use std::env;
+use std::fmt::Display;
+use std::path::Path;
+
+use crate::backend::Backend;
+use crate::config::XrpexConfig;
+use crate::monitor::RpexMonitorManager;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Ok,
+    Warning,
+    Error,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Finding {
+    pub severity: Severity,
+    pub check: &'static str,
+    pub message: String,
+    pub hint: Option<String>,
+}
+
+impl Finding {
+    pub fn ok(check: &'static str, message: impl Into<String>) -> Finding {
+        Finding {
+            severity: Severity::Ok,
+            check,
+            message: message.into(),
+            hint: None,
+        }
+    }
+
+    pub fn warning(check: &'static str, message: impl Into<String>, hint: &str) -> Finding {
+        Finding {
+            severity: Severity::Warning,
+            check,
+            message: message.into(),
+            hint: Some(hint.to_string()),
+        }
+    }
+
+    pub fn error(check: &'static str, message: impl Into<String>, hint: &str) -> Finding {
+        Finding {
+            severity: Severity::Error,
+            check,
+            message: message.into(),
+            hint: Some(hint.to_string()),
+        }
+    }
+}
+
+impl Display for Finding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let severity = match self.severity {
+            Severity::Ok => "ok",
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+
+        write!(f, "[{severity}] {}: {}", self.check, self.message)?;
+
+        if let Some(hint) = &self.hint {
+            write!(f, "\n  hint: {hint}")?;
+        }
+
+        Ok(())
+    }
+}
+
+pub fn check_backend(requested: Backend, resolved: Option<Backend>) -> Finding {
+    match resolved {
+        None => Finding::error(
+            "backend",
+            "no DISPLAY, WAYLAND_DISPLAY or compositor socket is set",
+            "run xrpex from inside your graphical session, or export DISPLAY (e.g. DISPLAY=:0)",
+        ),
+        Some(backend) if requested == Backend::Auto => {
+            Finding::ok("backend", format!("detected {backend}"))
+        }
+        Some(backend) => Finding::ok("backend", format!("using {backend}")),
+    }
+}
+
+// The manager is returned when it could be opened, for backend specific checks.
+pub fn check_manager<M: RpexMonitorManager>(
+    open: impl FnOnce() -> Result<M, M::ManagerError>,
+) -> (Vec<Finding>, Option<M>)
+where
+    M::ManagerError: Display,
+{
+    let mut manager = match open() {
+        Ok(manager) => manager,
+        Err(e) => {
+            let finding = Finding::error(
+                "connection",
+                e.to_string(),
+                "check that the display server is running and that this user may connect to it \
+                 (for X, XAUTHORITY must point at the server's cookie)",
+            );
+
+            return (vec![finding], None);
+        }
+    };
+
+    let finding = match manager.get_monitors() {
+        Ok(monitors) => match monitors.len() {
+            0 => Finding::warning(
+                "monitors",
+                "no monitors are active",
+                "enable an output, e.g. with `xrandr --output NAME --auto`",
+            ),
+            count => Finding::ok("monitors", format!("{count} monitor(s) found")),
+        },
+        Err(e) => Finding::error(
+            "monitors",
+            e.to_string(),
+            "the display server refused to list monitors, check its logs",
+        ),
+    };
+
+    (
+        vec![Finding::ok("connection", "connected"), finding],
+        Some(manager),
+    )
+}
+
+pub fn check_randr_version(version: Result<(u32, u32), impl Display>) -> Finding {
+    match version {
+        Ok((major, minor)) if (major, minor) >= (1, 5) => {
+            Finding::ok("randr", format!("version {major}.{minor}"))
+        }
+        Ok((major, minor)) => Finding::error(
+            "randr",
+            format!("version {major}.{minor} is too old, virtual monitors need 1.5"),
+            "upgrade the X server",
+        ),
+        Err(e) => Finding::error(
+            "randr",
+            e.to_string(),
+            "the X server doesn't seem to support RandR",
+        ),
+    }
+}
+
+pub fn check_config(path: Option<&Path>) -> Finding {
+    let Some(path) = path else {
+        return Finding::warning(
+            "config",
+            "unable to locate config directory",
+            "set HOME or XDG_CONFIG_HOME, or pass --config",
+        );
+    };
+
+    if !path.exists() {
+        return Finding::ok("config", format!("no config file at {}", path.display()));
+    }
+
+    match XrpexConfig::load(path) {
+        Ok(config) => Finding::ok(
+            "config",
+            format!(
+                "{} configures {} monitor(s)",
+                path.display(),
+                config.monitors.len()
+            ),
+        ),
+        Err(e) => Finding::error("config", e.to_string(), "fix or remove the config file"),
+    }
+}
+
+fn find_in_path(program: &str) -> bool {
+    env::var_os("PATH")
+        .is_some_and(|paths| env::split_paths(&paths).any(|dir| dir.join(program).is_file()))
+}
+
+// None of these are needed to create virtual monitors, only by the commands that mention them.
+pub fn check_tools() -> Vec<Finding> {
+    [
+        ("wmctrl", "by `xrpex tile`"),
+        ("nvidia-settings", "to run what `xrpex meta-mode` prints"),
+    ]
+    .into_iter()
+    .map(|(program, usage)| {
+        if find_in_path(program) {
+            Finding::ok(program, "found in PATH")
+        } else {
+            Finding::warning(
+                program,
+                "not found in PATH",
+                &format!("only needed {usage}"),
+            )
+        }
+    })
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn we_can_check_randr_version() {
+        assert_eq!(
+            check_randr_version(Ok::<_, String>((1, 6))).severity,
+            Severity::Ok
+        );
+        assert_eq!(
+            check_randr_version(Ok::<_, String>((1, 4))).severity,
+            Severity::Error
+        );
+        assert_eq!(
+            check_randr_version(Err("RandR extension missing")).to_string(),
+            "[error] randr: RandR extension missing\n  \
+             hint: the X server doesn't seem to support RandR"
+        );
+    }
+
+    #[test]
+    fn we_can_check_backend() {
+        assert_eq!(
+            check_backend(Backend::Auto, Some(Backend::Xrandr)).to_string(),
+            "[ok] backend: detected xrandr"
+        );
+        assert_eq!(check_backend(Backend::Auto, None).severity, Severity::Error);
+    }
+}
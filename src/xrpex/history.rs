@@ -0,0 +1,164 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use rpex::Rpex;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+const HISTORY_LENGTH: usize = 10;
+
+// Each monitor keeps the layouts that were replaced on it, oldest first. An empty entry means the
+// monitor had no virtual monitors.
+#[derive(Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct XrpexHistory {
+    #[serde(default)]
+    pub monitors: BTreeMap<String, Vec<String>>,
+    /// What was last applied to each monitor
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub applied: BTreeMap<String, AppliedLayout>,
+}
+
+// Inferring an expression from virtual monitors loses its labels, and fails across gaps and
+// skipped partitions, so the expression that was applied is kept along with what else shaped the
+// virtual monitors.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct AppliedLayout {
+    pub rpex: String,
+    #[serde(default)]
+    pub gap: [u32; 2],
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub skip: Vec<usize>,
+}
+
+#[derive(Error, Debug)]
+pub enum HistoryError {
+    #[error("unable to locate state directory")]
+    NoStateDir,
+    #[error("unable to access history {0}: {1}")]
+    Io(PathBuf, io::Error),
+    #[error("unable to parse history: {0}")]
+    TomlDe(#[from] toml::de::Error),
+    #[error("unable to serialize history: {0}")]
+    TomlSer(#[from] toml::ser::Error),
+    #[error("no earlier layout of {0} to undo to")]
+    Empty(String),
+    #[error("invalid expression {1} in history of {0}")]
+    InvalidEntry(String, String),
+}
+
+impl XrpexHistory {
+    pub fn path() -> Result<PathBuf, HistoryError> {
+        Ok(dirs::state_dir()
+            .ok_or(HistoryError::NoStateDir)?
+            .join("xrpex")
+            .join("history.toml"))
+    }
+
+    pub fn load() -> Result<XrpexHistory, HistoryError> {
+        let path = XrpexHistory::path()?;
+
+        match fs::read_to_string(&path) {
+            Ok(contents) => Ok(toml::from_str(&contents)?),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(XrpexHistory::default()),
+            Err(e) => Err(HistoryError::Io(path, e)),
+        }
+    }
+
+    pub fn save(&self) -> Result<(), HistoryError> {
+        let path = XrpexHistory::path()?;
+
+        let contents = toml::to_string(self)?;
+
+        path.parent()
+            .map(fs::create_dir_all)
+            .transpose()
+            .and_then(|_| fs::write(&path, contents))
+            .map_err(|e| HistoryError::Io(path.clone(), e))
+    }
+
+    pub fn push(&mut self, monitor: &str, rpex: Option<&Rpex<2>>) {
+        let entries = self.monitors.entry(monitor.to_string()).or_default();
+
+        entries.push(rpex.map(Rpex::to_string).unwrap_or_default());
+
+        if entries.len() > HISTORY_LENGTH {
+            entries.drain(..entries.len() - HISTORY_LENGTH);
+        }
+    }
+
+    pub fn record_applied(&mut self, monitor: &str, rpex: &Rpex<2>, gap: [u32; 2], skip: &[usize]) {
+        self.applied.insert(
+            monitor.to_string(),
+            AppliedLayout {
+                rpex: rpex.to_string(),
+                gap,
+                skip: skip.to_vec(),
+            },
+        );
+    }
+
+    pub fn pop(&mut self, monitor: &str) -> Result<Option<Rpex<2>>, HistoryError> {
+        let entry = self
+            .monitors
+            .get_mut(monitor)
+            .and_then(Vec::pop)
+            .ok_or_else(|| HistoryError::Empty(monitor.to_string()))?;
+
+        if self.monitors.get(monitor).is_some_and(Vec::is_empty) {
+            self.monitors.remove(monitor);
+        }
+
+        if entry.is_empty() {
+            return Ok(None);
+        }
+
+        entry
+            .parse()
+            .map(Some)
+            .map_err(|_| HistoryError::InvalidEntry(monitor.to_string(), entry))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    #[test]
+    fn we_can_push_and_pop_history() {
+        let mut history = XrpexHistory::default();
+
+        history.push("DP-1", None);
+        for _ in 0..HISTORY_LENGTH {
+            history.push("DP-1", Some(&Rpex::from_str("+:").unwrap()));
+        }
+        history.push("DP-1", Some(&Rpex::from_str("2+1:1").unwrap()));
+
+        assert_eq!(history.monitors["DP-1"].len(), HISTORY_LENGTH);
+        assert_eq!(
+            history.pop("DP-1").unwrap(),
+            Some(Rpex::from_str("2+1:1").unwrap())
+        );
+        assert!(matches!(history.pop("HDMI-1"), Err(HistoryError::Empty(_))));
+
+        let mut history: XrpexHistory = toml::from_str(
+            r#"
+            [monitors]
+            DP-1 = ["", "1+1:1"]
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            history.pop("DP-1").unwrap(),
+            Some(Rpex::from_str("1+1:1").unwrap())
+        );
+        assert_eq!(history.pop("DP-1").unwrap(), None);
+        assert!(history.monitors.is_empty());
+    }
+}
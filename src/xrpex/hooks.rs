@@ -0,0 +1,82 @@
+use std::io;
+use std::process::{Command, ExitStatus};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tracing::{info, warn};
+
+/// Shell commands to run around changes to virtual monitors, e.g. to restart bars or notify the
+/// window manager. What they print goes to stderr.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(deny_unknown_fields)]
+pub struct Hooks {
+    /// Commands run before virtual monitors are reset, with XRPEX_MONITORS set to the
+    /// comma-separated monitors whose virtual monitors are reset
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pre_reset: Vec<String>,
+    /// Commands run after expressions are applied, with XRPEX_MONITORS set to the comma-separated
+    /// monitors they were applied to and XRPEX_LAYOUT to a JSON array of the created virtual
+    /// monitors
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub post_apply: Vec<String>,
+}
+
+#[derive(Error, Debug)]
+pub enum HookError {
+    #[error("unable to run hook {0}: {1}")]
+    Io(String, io::Error),
+    #[error("hook {0} failed with {1}")]
+    Failed(String, ExitStatus),
+}
+
+impl Hooks {
+    pub fn is_empty(&self) -> bool {
+        self.pre_reset.is_empty() && self.post_apply.is_empty()
+    }
+}
+
+fn run_hook(command: &str, env: &[(&str, String)]) -> Result<(), HookError> {
+    info!(command, "running hook");
+
+    // Hooks run right before --output prints, which their output would otherwise corrupt.
+    let status = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .envs(env.iter().map(|(name, value)| (name, value)))
+        .stdout(io::stderr())
+        .status()
+        .map_err(|e| HookError::Io(command.to_string(), e))?;
+
+    if !status.success() {
+        return Err(HookError::Failed(command.to_string(), status));
+    }
+
+    Ok(())
+}
+
+// Hooks are a convenience, so a failing hook doesn't fail the change it runs around, nor keep the
+// hooks after it from running.
+pub fn run_hooks(commands: &[String], env: &[(&str, String)]) {
+    for command in commands {
+        if let Err(e) = run_hook(command, env) {
+            warn!("{e}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn we_can_run_hooks_with_env() {
+        let env = [("XRPEX_MONITORS", "DP-1,HDMI-1".to_string())];
+
+        assert!(run_hook("test \"$XRPEX_MONITORS\" = DP-1,HDMI-1", &env).is_ok());
+        assert!(matches!(
+            run_hook("exit 3", &env),
+            Err(HookError::Failed(_, status)) if status.code() == Some(3)
+        ));
+    }
+}
@@ -0,0 +1,162 @@
+use std::env;
+use std::ffi::OsString;
+use std::io::{self, Read, Write};
+use std::os::unix::net::UnixStream;
+use std::process::Command;
+
+use serde::Deserialize;
+use thiserror::Error;
+use tracing::{info, trace};
+
+const IPC_MAGIC: &[u8; 6] = b"i3-ipc";
+pub const IPC_RUN_COMMAND: u32 = 0;
+pub const IPC_GET_OUTPUTS: u32 = 3;
+
+#[derive(Error, Debug)]
+pub enum I3IpcError {
+    #[error("unable to find the i3 or sway IPC socket, is either running?")]
+    NoSocket,
+    #[error("unable to talk to i3 or sway: {0}")]
+    Io(#[from] io::Error),
+    #[error("i3 or sway sent an invalid reply: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("i3 or sway sent a reply with an unexpected header")]
+    BadHeader,
+    #[error("i3 or sway failed to run `{0}`: {1}")]
+    Command(String, String),
+}
+
+#[derive(Deserialize)]
+struct CommandReply {
+    success: bool,
+    error: Option<String>,
+}
+
+pub struct I3Ipc {
+    socket: UnixStream,
+}
+
+impl I3Ipc {
+    pub fn connect(path: OsString) -> Result<I3Ipc, I3IpcError> {
+        Ok(I3Ipc {
+            socket: UnixStream::connect(path)?,
+        })
+    }
+
+    // i3 only exports its socket path to processes it starts, so it is asked for the path when
+    // neither variable is set.
+    pub fn connect_to_env() -> Result<I3Ipc, I3IpcError> {
+        let path = env::var_os("I3SOCK")
+            .or_else(|| env::var_os("SWAYSOCK"))
+            .filter(|path| !path.is_empty())
+            .or_else(|| {
+                let output = Command::new("i3").arg("--get-socketpath").output().ok()?;
+                let path = String::from_utf8(output.stdout).ok()?;
+
+                output
+                    .status
+                    .success()
+                    .then(|| OsString::from(path.trim_end()))
+            })
+            .ok_or(I3IpcError::NoSocket)?;
+
+        I3Ipc::connect(path)
+    }
+
+    pub fn request(&mut self, message_type: u32, payload: &[u8]) -> Result<Vec<u8>, I3IpcError> {
+        trace!(message_type, length = payload.len(), "sending ipc message");
+
+        let mut message = IPC_MAGIC.to_vec();
+        message.extend((payload.len() as u32).to_ne_bytes());
+        message.extend(message_type.to_ne_bytes());
+        message.extend(payload);
+
+        self.socket.write_all(&message)?;
+
+        let mut header = [0; 14];
+        self.socket.read_exact(&mut header)?;
+
+        let (magic, header) = header.split_at(IPC_MAGIC.len());
+        let (length, reply_type) = header.split_at(4);
+
+        if magic != IPC_MAGIC || reply_type != message_type.to_ne_bytes() {
+            return Err(I3IpcError::BadHeader);
+        }
+
+        let length = u32::from_ne_bytes(length.try_into().expect("length is 4 bytes"));
+
+        let mut reply = vec![0; length as usize];
+        self.socket.read_exact(&mut reply)?;
+
+        Ok(reply)
+    }
+
+    pub fn run_command(&mut self, command: &str) -> Result<(), I3IpcError> {
+        info!(command, "running ipc command");
+
+        let reply = self.request(IPC_RUN_COMMAND, command.as_bytes())?;
+
+        check_command_reply(command, &reply)
+    }
+}
+
+fn check_command_reply(command: &str, reply: &[u8]) -> Result<(), I3IpcError> {
+    let replies: Vec<CommandReply> = serde_json::from_slice(reply)?;
+
+    match replies.into_iter().find(|reply| !reply.success) {
+        Some(CommandReply { error, .. }) => Err(I3IpcError::Command(
+            command.to_string(),
+            error.unwrap_or_else(|| "unknown error".to_string()),
+        )),
+        None => Ok(()),
+    }
+}
+
+// Runtime commands can't bind a workspace to an output ahead of time, so each workspace is
+// focused and moved to its virtual monitor in turn.
+pub fn workspace_commands<'a>(
+    assignments: impl IntoIterator<Item = (&'a str, &'a str)>,
+) -> Vec<String> {
+    assignments
+        .into_iter()
+        .map(|(workspace, output)| {
+            format!(
+                "workspace --no-auto-back-and-forth {}; move workspace to output {}",
+                quote(workspace),
+                quote(output)
+            )
+        })
+        .collect()
+}
+
+fn quote(arg: &str) -> String {
+    format!("\"{}\"", arg.replace('\\', r"\\").replace('"', "\\\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn we_can_build_workspace_commands() {
+        assert_eq!(
+            workspace_commands([("1", "DP-1-XRPEX-0-0"), ("2: web", "DP-1-XRPEX-960-0")]),
+            vec![
+                r#"workspace --no-auto-back-and-forth "1"; move workspace to output "DP-1-XRPEX-0-0""#,
+                r#"workspace --no-auto-back-and-forth "2: web"; move workspace to output "DP-1-XRPEX-960-0""#,
+            ]
+        );
+    }
+
+    #[test]
+    fn we_cannot_ignore_failed_commands() {
+        assert!(check_command_reply("nop", br#"[{"success": true}]"#).is_ok());
+        assert!(matches!(
+            check_command_reply(
+                "workspace 1",
+                br#"[{"success": true}, {"success": false, "error": "No output matched"}]"#
+            ),
+            Err(I3IpcError::Command(_, error)) if error == "No output matched"
+        ));
+    }
+}
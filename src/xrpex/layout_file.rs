@@ -0,0 +1,217 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use rpex::Rpex;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use toml::{Table, Value};
+
+use crate::monitor::RpexMonitor;
+
+pub const LAYOUT_FILE_VERSION: u32 = 1;
+pub const LAYOUT_FILE_EXTENSION: &str = "rpexlayout";
+
+// Layout files record what monitors looked like when they were saved, next to the expressions
+// that produced it, so that tools can use the geometry without evaluating expressions.
+#[derive(Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(deny_unknown_fields)]
+pub struct LayoutFile {
+    /// Version of the format, increased whenever it changes
+    pub version: u32,
+    /// Layout of each monitor, keyed by monitor name
+    #[serde(default)]
+    pub monitors: BTreeMap<String, MonitorLayout>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(deny_unknown_fields)]
+pub struct MonitorLayout {
+    /// Expression the monitor is split by
+    pub rpex: Rpex<2>,
+    /// Geometry of the monitor when the layout was saved
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub geometry: Option<Geometry>,
+    /// Geometry of each virtual monitor the expression produced, keyed by name
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub virtual_monitors: BTreeMap<String, Geometry>,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(deny_unknown_fields)]
+pub struct Geometry {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl From<&RpexMonitor> for Geometry {
+    fn from(monitor: &RpexMonitor) -> Geometry {
+        let [x, y] = monitor.position;
+        let [width, height] = monitor.resolution.lengths;
+
+        Geometry {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum LayoutFileError {
+    #[error("unable to access layout file {0}: {1}")]
+    Io(PathBuf, io::Error),
+    #[error("unable to parse layout file: {0}")]
+    TomlDe(#[from] toml::de::Error),
+    #[error("unable to serialize layout file: {0}")]
+    TomlSer(#[from] toml::ser::Error),
+    #[error("layout file version {0} is newer than this xrpex supports ({LAYOUT_FILE_VERSION})")]
+    UnsupportedVersion(i64),
+    #[error("layout file version 0 maps monitor {0} to something other than an expression")]
+    InvalidLegacyMonitor(String),
+}
+
+impl LayoutFile {
+    pub fn new(monitors: BTreeMap<String, MonitorLayout>) -> LayoutFile {
+        LayoutFile {
+            version: LAYOUT_FILE_VERSION,
+            monitors,
+        }
+    }
+
+    pub fn targets(&self) -> Vec<(String, Rpex<2>)> {
+        self.monitors
+            .iter()
+            .map(|(name, MonitorLayout { rpex, .. })| (name.clone(), rpex.clone()))
+            .collect()
+    }
+
+    pub fn from_toml(contents: &str) -> Result<LayoutFile, LayoutFileError> {
+        let document = migrate(toml::from_str(contents)?)?;
+
+        Ok(document.try_into()?)
+    }
+
+    pub fn to_toml(&self) -> Result<String, LayoutFileError> {
+        Ok(toml::to_string(self)?)
+    }
+
+    pub fn load(path: &Path) -> Result<LayoutFile, LayoutFileError> {
+        let contents =
+            fs::read_to_string(path).map_err(|e| LayoutFileError::Io(path.to_path_buf(), e))?;
+
+        LayoutFile::from_toml(&contents)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), LayoutFileError> {
+        let contents = self.to_toml()?;
+
+        path.parent()
+            .map(fs::create_dir_all)
+            .transpose()
+            .and_then(|_| fs::write(path, contents))
+            .map_err(|e| LayoutFileError::Io(path.to_path_buf(), e))
+    }
+}
+
+// Each migration takes a document from one version to the next, so files of any earlier version
+// keep loading as the format changes. Files without a version predate layout files.
+pub fn migrate(mut document: Table) -> Result<Table, LayoutFileError> {
+    loop {
+        let version = match document.get("version") {
+            Some(Value::Integer(version)) => *version,
+            _ => 0,
+        };
+
+        document = match version {
+            0 => migrate_from_v0(document)?,
+            version if version == LAYOUT_FILE_VERSION as i64 => return Ok(document),
+            version => return Err(LayoutFileError::UnsupportedVersion(version)),
+        };
+    }
+}
+
+// Version 0 is the format profiles were saved in, mapping monitor names straight to expressions.
+fn migrate_from_v0(mut document: Table) -> Result<Table, LayoutFileError> {
+    if let Some(Value::Table(monitors)) = document.get_mut("monitors") {
+        for (name, monitor) in monitors.iter_mut() {
+            let Value::String(rpex) = monitor else {
+                return Err(LayoutFileError::InvalidLegacyMonitor(name.clone()));
+            };
+
+            *monitor = Value::Table(Table::from_iter([(
+                "rpex".to_string(),
+                Value::String(rpex.clone()),
+            )]));
+        }
+    }
+
+    document.insert("version".to_string(), Value::Integer(1));
+
+    Ok(document)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    #[test]
+    fn we_can_round_trip_layout_files() {
+        let layout = LayoutFile::new(BTreeMap::from([(
+            "DP-1".to_string(),
+            MonitorLayout {
+                rpex: Rpex::from_str("+:").unwrap(),
+                geometry: Some(Geometry {
+                    x: 0,
+                    y: 0,
+                    width: 1920,
+                    height: 1080,
+                }),
+                virtual_monitors: BTreeMap::from([(
+                    "DP-1-XRPEX-0-0".to_string(),
+                    Geometry {
+                        x: 0,
+                        y: 0,
+                        width: 960,
+                        height: 1080,
+                    },
+                )]),
+            },
+        )]));
+
+        assert_eq!(
+            LayoutFile::from_toml(&layout.to_toml().unwrap()).unwrap(),
+            layout
+        );
+    }
+
+    #[test]
+    fn we_can_migrate_legacy_profiles() {
+        let layout = LayoutFile::from_toml(
+            r#"
+            [monitors]
+            DP-1 = "2+1:1"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(layout.version, LAYOUT_FILE_VERSION);
+        assert_eq!(
+            layout.targets(),
+            vec![("DP-1".to_string(), Rpex::from_str("2+1:1").unwrap())]
+        );
+        assert!(matches!(
+            LayoutFile::from_toml("version = 2"),
+            Err(LayoutFileError::UnsupportedVersion(2))
+        ));
+    }
+}
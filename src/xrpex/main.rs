@@ -0,0 +1,2682 @@
+mod backend;
+mod check;
+mod config;
+mod dbus_service;
+mod doctor;
+mod history;
+mod hooks;
+mod i3ipc;
+mod layout_file;
+#[cfg(any(test, feature = "test-support"))]
+mod mock;
+mod monitor;
+mod mutter;
+mod name_template;
+mod persist;
+#[cfg(feature = "dynamic-backends")]
+mod plugin;
+mod preview;
+mod registry;
+mod sway;
+mod tile;
+mod wlr;
+mod xrandr;
+
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::fs;
+use std::io::{self, IsTerminal};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::str::FromStr;
+
+use clap::builder::{OsStringValueParser, TypedValueParser};
+use clap::{CommandFactory, Parser, Subcommand, ValueEnum};
+use clap_complete::engine::{ArgValueCandidates, CompletionCandidate};
+use clap_complete::CompleteEnv;
+use glob::Pattern;
+use itertools::Itertools;
+use rpex::Cell;
+use rpex::FromOsStr;
+use rpex::HyperRectangle;
+use rpex::Rpex;
+use rpex::RpexInferenceError;
+use rpex::SumsInRatioEvaluationError;
+use serde_json::json;
+use thiserror::Error;
+use tracing::{debug, info, warn, Level};
+
+use backend::Backend;
+use config::{ConfigError, GapArgs, ProfileError, XrpexConfig};
+use doctor::{Finding, Severity};
+use history::{HistoryError, XrpexHistory};
+use hooks::{run_hooks, Hooks};
+use i3ipc::{I3Ipc, I3IpcError};
+use layout_file::{Geometry, LayoutFile, LayoutFileError, MonitorLayout};
+#[cfg(any(test, feature = "test-support"))]
+use mock::{MockManagerError, MockMonitorManager};
+use monitor::{
+    nvidia_meta_mode, virtual_monitors_json, virtual_monitors_null, virtual_monitors_shell,
+    RpexMonitor, RpexMonitorManager, VirtualMonitor,
+};
+use mutter::MutterManager;
+use name_template::NameTemplate;
+use persist::{PersistError, PersistFormat};
+#[cfg(feature = "dynamic-backends")]
+use plugin::PluginError;
+use registry::{BackendError, BackendRegistry};
+use sway::SwayManager;
+use tile::TileError;
+use wlr::WlrManager;
+use xrandr::XrandrManager;
+
+// Scripts tell failures apart by these, so existing statuses and codes must never change meaning.
+const EXIT_STATUS: &str = "\
+Exit status:
+  0  success
+  1  any other failure
+  2  invalid arguments, expressions or config
+  3  a given monitor doesn't exist, nothing was changed
+  4  the display server or compositor failed or couldn't be reached
+
+With --output json, errors are printed to stdout as {\"error\": {\"code\": ..., \"message\": ...}}, \
+where code is a stable identifier of the failure.";
+
+#[derive(Parser)]
+#[command(
+    name = "xrpex",
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true,
+    after_long_help = EXIT_STATUS
+)]
+struct XrpexArgs {
+    #[command(subcommand)]
+    command: Option<XrpexCommand>,
+    /// Expressions to apply, either `EXPR` for the --monitor or `MONITOR=EXPR`
+    ///
+    /// When omitted, the monitors and expressions from the config file are applied.
+    #[arg(value_parser = monitor_rpex_parser())]
+    rpexes: Vec<MonitorRpex>,
+    /// Monitor to apply expressions without a `MONITOR=` prefix to
+    #[arg(short, long, env = "XRPEX_MONITOR", add = monitor_candidates())]
+    monitor: Option<String>,
+    /// Config file to use instead of ~/.config/xrpex/config.toml
+    #[arg(short, long, global = true, env = "XRPEX_CONFIG")]
+    config: Option<PathBuf>,
+    /// Print the virtual monitors that were created
+    #[arg(short, long, value_enum)]
+    output: Option<OutputFormat>,
+    /// Template for virtual monitor names, using {parent}, {index}, {x}, {y}, {row}, {col}, and
+    /// {label}, which falls back to {x}-{y} for unlabelled partitions
+    #[arg(short, long, global = true, env = "XRPEX_NAME_TEMPLATE")]
+    name_template: Option<NameTemplate>,
+    #[command(flatten)]
+    gap: GapArgs,
+    /// Index of a partition to leave without a virtual monitor, can be given multiple times
+    #[arg(short, long, global = true, value_name = "INDEX")]
+    skip: Vec<usize>,
+    /// Size virtual monitors in millimetres as if they had this many dots per inch, instead of
+    /// splitting the physical size of their parent
+    #[arg(long, global = true, value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+    dpi: Option<u32>,
+    /// Split monitors even when they're overlapped by monitors xrpex didn't create
+    #[arg(long, global = true)]
+    force: bool,
+    /// Preview the virtual monitors and ask before changing any monitors
+    #[arg(long, global = true)]
+    confirm: bool,
+    /// Workspaces to move onto the created virtual monitors through i3 or sway IPC, in order
+    #[arg(long, global = true, value_delimiter = ',', value_name = "WORKSPACES")]
+    assign_workspaces: Vec<String>,
+    /// Add virtual monitors alongside the existing ones instead of replacing them
+    #[arg(long, global = true)]
+    keep_existing: bool,
+    /// Log what xrpex does to stderr, -v for commands and -vv for queried and computed monitors
+    #[arg(short, long, global = true, action = clap::ArgAction::Count)]
+    verbose: u8,
+    /// Format of the log lines enabled by --verbose
+    #[arg(long, global = true, value_enum, default_value_t)]
+    log_format: LogFormat,
+    /// Display server or compositor to manage monitors through
+    #[arg(
+        long,
+        global = true,
+        value_enum,
+        default_value_t,
+        env = "XRPEX_BACKEND"
+    )]
+    backend: Backend,
+    /// X display to manage, such as :1, instead of DISPLAY; implies the xrandr backend
+    #[arg(long, global = true, value_name = "DISPLAY")]
+    display: Option<String>,
+    /// Shared library to load a backend from, which is used instead of --backend
+    #[cfg(feature = "dynamic-backends")]
+    #[arg(
+        long,
+        global = true,
+        env = "XRPEX_BACKEND_LIBRARY",
+        value_name = "PATH"
+    )]
+    backend_library: Option<PathBuf>,
+}
+
+#[derive(Default)]
+struct ApplyOptions {
+    name_template: NameTemplate,
+    gap: [u32; 2],
+    skip: Vec<usize>,
+    output: Option<OutputFormat>,
+    keep_existing: bool,
+    workspaces: Vec<String>,
+    confirm: bool,
+    record_history: bool,
+    dpi: Option<u32>,
+    force: bool,
+    hooks: Hooks,
+}
+
+impl ApplyOptions {
+    // Only the options that every command takes straight from its arguments, the rest are up to
+    // each command.
+    fn from_args(args: &XrpexArgs) -> ApplyOptions {
+        ApplyOptions {
+            name_template: args.name_template.clone().unwrap_or_default(),
+            gap: args.gap.gutter(),
+            skip: args.skip.clone(),
+            dpi: args.dpi,
+            force: args.force,
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+struct MonitorRpex {
+    monitor: Option<String>,
+    rpex: Rpex<2>,
+}
+
+impl FromStr for MonitorRpex {
+    type Err = nom::error::Error<String>;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (monitor, rpex) = match (Rpex::from_str(s), s.split_once('=')) {
+            (Ok(rpex), _) => (None, rpex),
+            (Err(_), Some((monitor, rpex))) => (Some(monitor.to_string()), rpex.parse()?),
+            (Err(e), None) => return Err(e),
+        };
+
+        Ok(MonitorRpex { monitor, rpex })
+    }
+}
+
+#[derive(Subcommand)]
+enum XrpexCommand {
+    /// Print the expression that the existing virtual monitors of a monitor correspond to
+    Status {
+        #[arg(env = "XRPEX_MONITOR", add = monitor_candidates())]
+        monitor: String,
+    },
+    /// Save or apply named monitor to expression mappings
+    Profile {
+        #[command(subcommand)]
+        command: ProfileCommand,
+    },
+    /// Delete virtual monitors of a monitor, or those matching a pattern
+    Reset {
+        /// Monitor whose virtual monitors, including nested ones, are deleted
+        #[arg(env = "XRPEX_MONITOR", add = monitor_candidates(), required_unless_present = "pattern")]
+        monitor: Option<String>,
+        /// Only delete virtual monitors whose names match this glob pattern
+        #[arg(long = "match", value_name = "PATTERN")]
+        pattern: Option<Pattern>,
+    },
+    /// Print a config file reproducing the current virtual monitors
+    Export {
+        /// Print a layout file, with the geometry of every monitor, instead of a config file
+        #[arg(long)]
+        layout: bool,
+    },
+    /// Check the environment for problems that keep xrpex from working
+    Doctor,
+    /// Check every expression in the config file and saved profiles, evaluating each on its
+    /// monitor's current resolution, or the one a profile recorded
+    CheckConfig,
+    /// Restore the layout of a monitor from before the last time expressions were applied to it
+    Undo {
+        #[arg(env = "XRPEX_MONITOR", add = monitor_candidates())]
+        monitor: String,
+    },
+    /// Reproduce the current virtual monitors at login
+    Persist {
+        /// How xrpex is run at login
+        #[arg(short, long, value_enum, default_value_t)]
+        format: PersistFormat,
+    },
+    /// Print the JSON Schema of config files, for editors to validate and complete them with
+    #[cfg(feature = "schemars")]
+    Schema {
+        /// Print the schema of saved profiles instead
+        #[arg(long)]
+        profile: bool,
+    },
+    /// Write a systemd user unit that applies the configured expressions at login
+    InstallService {
+        /// Also print a udev rule that applies them again when monitors are plugged in
+        #[arg(long)]
+        udev: bool,
+    },
+    /// Serve dev.rpex.Layout1 on the session bus, for applets and keybinding daemons to evaluate,
+    /// apply and reset expressions without running xrpex for each
+    ServeDbus,
+    /// Print wmctrl commands that move and resize windows into partitions instead of creating
+    /// virtual monitors
+    Tile {
+        /// Expressions to split monitors by, either `EXPR` for the --monitor or `MONITOR=EXPR`
+        #[arg(required = true, value_parser = monitor_rpex_parser())]
+        rpexes: Vec<MonitorRpex>,
+        /// Monitor to apply expressions without a `MONITOR=` prefix to
+        #[arg(short, long, env = "XRPEX_MONITOR", add = monitor_candidates())]
+        monitor: Option<String>,
+        /// Window id to place, in partition order, defaulting to the windows on the current
+        /// desktop
+        #[arg(short, long = "window", value_name = "ID")]
+        windows: Vec<String>,
+        /// Run the commands instead of printing them
+        #[arg(short, long)]
+        execute: bool,
+    },
+    /// Print an xrandr shell script that creates the virtual monitors, without needing xrpex
+    Script {
+        /// Expressions to split monitors by, either `EXPR` for the --monitor or `MONITOR=EXPR`,
+        /// defaulting to the current virtual monitors
+        #[arg(value_parser = monitor_rpex_parser())]
+        rpexes: Vec<MonitorRpex>,
+        /// Monitor to apply expressions without a `MONITOR=` prefix to
+        #[arg(short, long, env = "XRPEX_MONITOR", add = monitor_candidates())]
+        monitor: Option<String>,
+    },
+    /// Print an nvidia-settings command that shows only the area of each monitor that its virtual
+    /// monitors would cover, through MetaMode viewports; MetaModes can't split monitors
+    MetaMode {
+        /// Expressions to split monitors by, either `EXPR` for the --monitor or `MONITOR=EXPR`
+        #[arg(required = true, value_parser = monitor_rpex_parser())]
+        rpexes: Vec<MonitorRpex>,
+        /// Monitor to apply expressions without a `MONITOR=` prefix to
+        #[arg(short, long, env = "XRPEX_MONITOR", add = monitor_candidates())]
+        monitor: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum ProfileCommand {
+    /// Save the expressions of all monitors with virtual monitors under a name
+    Save { name: String },
+    /// Apply the expressions saved under a name
+    Apply {
+        name: String,
+        /// Print the virtual monitors that were created
+        #[arg(short, long, value_enum)]
+        output: Option<OutputFormat>,
+    },
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum)]
+enum OutputFormat {
+    /// A JSON array of virtual monitors
+    Json,
+    /// `RPEX_<INDEX>_<FIELD>=<VALUE>` assignments for `eval`
+    Shell,
+    /// Tab-separated name, parent and geometry of each virtual monitor, each ending in a null byte
+    Null,
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, ValueEnum)]
+enum LogFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+#[derive(Error, Debug)]
+enum XrpexError {
+    #[error("unable to find monitor {0}")]
+    NoMonitor(String),
+    #[error("no monitor given for expression {0}")]
+    MissingMonitor(Box<Rpex<2>>),
+    #[error("multiple expressions given for monitor {0}")]
+    DuplicateMonitor(String),
+    #[error("expressions given for both {0} and its virtual monitor {1}")]
+    NestedMonitor(String, String),
+    #[error("failed to evaluate rpex for this monitor: {0}")]
+    RpexEvaluation(#[from] SumsInRatioEvaluationError),
+    #[error("a gap of {}x{} leaves no room for some virtual monitors on {1}", .0[0], .0[1])]
+    GapTooLarge([u32; 2], String),
+    #[error("there is no partition {0} to skip on {1}, which has {2}")]
+    NoPartitionToSkip(usize, String, usize),
+    #[error("skipping every partition leaves no virtual monitors on {0}")]
+    SkippedAll(String),
+    #[error(
+        "unable to apply expressions to {} monitor(s): {}",
+        .0.len(),
+        .0.iter().map(|(monitor, e)| format!("{monitor}: {e}")).join("; ")
+    )]
+    Failed(Vec<(String, XrpexError)>),
+    #[error(transparent)]
+    Config(#[from] ConfigError),
+    #[error("no expressions given or configured")]
+    NoTargets,
+    #[error("found {0} problem(s)")]
+    Doctor(usize),
+    #[error("found {0} problem(s) in config files")]
+    CheckConfig(usize),
+    #[error("aborted without changing any monitors")]
+    Aborted,
+    #[error("unable to read confirmation: {0}")]
+    Confirm(io::Error),
+    #[error("multiple virtual monitors would be named {0}")]
+    DuplicateName(String),
+    #[error("virtual monitor {0} would overlap existing virtual monitor {1}")]
+    OverlappingMonitor(String, String),
+    #[error("{0} is overlapped by monitor {1}, which xrpex didn't create, use --force to split it anyway")]
+    ForeignMonitor(String, String),
+    #[error(transparent)]
+    Profile(#[from] ProfileError),
+    #[error(transparent)]
+    LayoutFile(#[from] LayoutFileError),
+    #[error(transparent)]
+    Persist(#[from] PersistError),
+    #[error(transparent)]
+    History(#[from] HistoryError),
+    #[error(transparent)]
+    I3Ipc(#[from] I3IpcError),
+    #[error(transparent)]
+    Tile(#[from] TileError),
+    #[error("unable to serve on D-Bus: {0}")]
+    DBusService(zbus::Error),
+    #[error("unable to infer expression from virtual monitors: {0}")]
+    RpexInference(#[from] RpexInferenceError),
+    #[error("unable to detect a backend for this session, use --backend to choose one")]
+    NoBackend,
+    #[error("the {0} backend is not supported yet")]
+    UnsupportedBackend(String),
+    #[error("the {0} backend can only read monitors, not split or reset them")]
+    ReadOnlyBackend(String),
+    #[error(transparent)]
+    Backend(#[from] BackendError),
+    #[cfg(feature = "dynamic-backends")]
+    #[error(transparent)]
+    Plugin(#[from] PluginError),
+    #[cfg(any(test, feature = "test-support"))]
+    #[error(transparent)]
+    MockManager(#[from] MockManagerError),
+}
+
+impl XrpexError {
+    fn code(&self) -> &'static str {
+        match self {
+            XrpexError::NoMonitor(_) => "no-monitor",
+            XrpexError::MissingMonitor(_) => "missing-monitor",
+            XrpexError::DuplicateMonitor(_) => "duplicate-monitor",
+            XrpexError::NestedMonitor(_, _) => "nested-monitor",
+            XrpexError::RpexEvaluation(_) => "evaluation",
+            XrpexError::GapTooLarge(_, _) => "gap-too-large",
+            XrpexError::NoPartitionToSkip(_, _, _) => "no-partition",
+            XrpexError::SkippedAll(_) => "skipped-all",
+            XrpexError::Failed(failures) => failures
+                .iter()
+                .map(|(_, e)| e.code())
+                .all_equal_value()
+                .unwrap_or("failed"),
+            XrpexError::Config(ConfigError::Toml(_)) => "invalid-config",
+            XrpexError::Config(_) => "config",
+            XrpexError::NoTargets => "no-targets",
+            XrpexError::Doctor(_) => "doctor",
+            XrpexError::CheckConfig(_) => "invalid-config",
+            XrpexError::Aborted => "aborted",
+            XrpexError::Confirm(_) => "confirm",
+            XrpexError::DuplicateName(_) => "duplicate-name",
+            XrpexError::OverlappingMonitor(_, _) => "overlapping-monitor",
+            XrpexError::ForeignMonitor(_, _) => "foreign-monitor",
+            XrpexError::Profile(ProfileError::Layout(
+                LayoutFileError::TomlDe(_)
+                | LayoutFileError::UnsupportedVersion(_)
+                | LayoutFileError::InvalidLegacyMonitor(_),
+            )) => "invalid-profile",
+            XrpexError::Profile(_) => "profile",
+            XrpexError::LayoutFile(_) => "layout-file",
+            XrpexError::Persist(_) => "persist",
+            XrpexError::History(_) => "history",
+            XrpexError::I3Ipc(_) => "ipc",
+            XrpexError::Tile(_) => "tile",
+            XrpexError::DBusService(_) => "dbus-service",
+            XrpexError::RpexInference(_) => "inference",
+            XrpexError::NoBackend => "no-backend",
+            XrpexError::UnsupportedBackend(_) => "unsupported-backend",
+            XrpexError::ReadOnlyBackend(_) => "read-only-backend",
+            XrpexError::Backend(_) => "backend",
+            #[cfg(feature = "dynamic-backends")]
+            XrpexError::Plugin(_) => "backend",
+            #[cfg(any(test, feature = "test-support"))]
+            XrpexError::MockManager(_) => "backend",
+        }
+    }
+
+    fn exit_code(&self) -> u8 {
+        if let XrpexError::Failed(failures) = self {
+            return failures
+                .iter()
+                .map(|(_, e)| e.exit_code())
+                .all_equal_value()
+                .unwrap_or(1);
+        }
+
+        match self.code() {
+            "missing-monitor" | "duplicate-monitor" | "nested-monitor" | "invalid-config"
+            | "no-targets" | "duplicate-name" | "invalid-profile" | "evaluation"
+            | "gap-too-large" | "no-partition" | "skipped-all" => 2,
+            "no-monitor" => 3,
+            "no-backend" | "unsupported-backend" | "read-only-backend" | "backend" => 4,
+            _ => 1,
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    // Shells register completions with e.g. `source <(COMPLETE=bash xrpex)`, and then call back
+    // into xrpex with COMPLETE set to complete each word.
+    CompleteEnv::with_factory(XrpexArgs::command).complete();
+
+    let args = XrpexArgs::parse();
+
+    init_logging(args.verbose, args.log_format);
+
+    // DISPLAY is replaced rather than passed along, so that commands xrpex spawns, like wmctrl,
+    // talk to the same display.
+    if let Some(display) = &args.display {
+        std::env::set_var("DISPLAY", display);
+    }
+
+    let backend = match (args.backend, &args.display) {
+        (Backend::Auto, Some(_)) => Some(Backend::Xrandr),
+        (backend, _) => backend.resolve(),
+    };
+    debug!(requested = %args.backend, resolved = ?backend, "selected backend");
+
+    let output = output_format(&args);
+
+    let result = match args.command {
+        Some(XrpexCommand::Doctor) => doctor(&args, backend),
+        Some(XrpexCommand::CheckConfig) => {
+            select_backend(&args, backend).and_then(|(registry, backend)| {
+                check_config(args.config.as_deref(), &registry, backend)
+            })
+        }
+        Some(XrpexCommand::InstallService { udev }) => {
+            install_service(args.config.as_deref(), udev)
+        }
+        Some(XrpexCommand::ServeDbus) => select_backend(&args, backend)
+            .and_then(|(registry, backend)| {
+                let config = args.config.as_deref();
+
+                Ok((
+                    registry,
+                    backend,
+                    configured_name_template(args.name_template, config)?,
+                    configured_hooks(config)?,
+                ))
+            })
+            .and_then(|(registry, backend, name_template, hooks)| {
+                dbus_service::serve(dbus_service::LayoutService {
+                    registry,
+                    backend,
+                    name_template: name_template.unwrap_or_default(),
+                    gap: args.gap.gutter(),
+                    dpi: args.dpi,
+                    force: args.force,
+                    hooks,
+                })
+                .map_err(XrpexError::DBusService)
+            }),
+        #[cfg(feature = "schemars")]
+        Some(XrpexCommand::Schema { profile }) => {
+            schema(profile);
+            Ok(())
+        }
+        _ => select_backend(&args, backend)
+            .and_then(|(registry, backend)| dispatch(args, &registry, backend)),
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            if output == Some(OutputFormat::Json) {
+                println!("{}", json!({ "error": error_json(&e) }));
+            } else {
+                eprintln!("error: {e}");
+            }
+
+            ExitCode::from(e.exit_code())
+        }
+    }
+}
+
+// Each monitor that failed is listed with its own code, since they can fail for different reasons.
+fn error_json(e: &XrpexError) -> serde_json::Value {
+    let mut error = json!({ "code": e.code(), "message": e.to_string() });
+
+    if let XrpexError::Failed(failures) = e {
+        error["monitors"] = failures
+            .iter()
+            .map(|(monitor, e)| json!({ "monitor": monitor, "code": e.code(), "message": e.to_string() }))
+            .collect();
+    }
+
+    error
+}
+
+// Subcommands with their own --output print errors in it instead of the top-level one.
+fn output_format(args: &XrpexArgs) -> Option<OutputFormat> {
+    match &args.command {
+        Some(XrpexCommand::Profile {
+            command: ProfileCommand::Apply { output, .. },
+        }) => *output,
+        _ => args.output,
+    }
+}
+
+// A backend library is only given to be used, so it takes precedence over --backend.
+#[cfg_attr(not(feature = "dynamic-backends"), allow(unused_variables))]
+fn select_backend(
+    args: &XrpexArgs,
+    backend: Option<Backend>,
+) -> Result<(BackendRegistry, Option<String>), XrpexError> {
+    #[cfg_attr(not(feature = "dynamic-backends"), allow(unused_mut))]
+    let mut registry = BackendRegistry::builtin();
+
+    #[cfg(feature = "dynamic-backends")]
+    if let Some(path) = &args.backend_library {
+        // SAFETY: the library is trusted like xrpex itself, since the user chose to load it.
+        let name = unsafe { plugin::load(&mut registry, path)? };
+        debug!(library = %path.display(), backend = name, "loaded backend library");
+
+        return Ok((registry, Some(name)));
+    }
+
+    Ok((registry, backend.map(|backend| backend.to_string())))
+}
+
+fn dispatch(
+    args: XrpexArgs,
+    registry: &BackendRegistry,
+    backend: Option<String>,
+) -> Result<(), XrpexError> {
+    let backend = backend.ok_or(XrpexError::NoBackend)?;
+
+    if registry.is_read_only(&backend) && changes_monitors(args.command.as_ref()) {
+        return Err(XrpexError::ReadOnlyBackend(backend));
+    }
+
+    let open = registry
+        .get(&backend)
+        .ok_or(XrpexError::UnsupportedBackend(backend))?;
+
+    run(args, open)
+}
+
+fn changes_monitors(command: Option<&XrpexCommand>) -> bool {
+    matches!(
+        command,
+        None | Some(
+            XrpexCommand::Undo { .. }
+                | XrpexCommand::Reset { .. }
+                | XrpexCommand::Profile {
+                    command: ProfileCommand::Apply { .. }
+                }
+        )
+    )
+}
+
+fn doctor(args: &XrpexArgs, backend: Option<Backend>) -> Result<(), XrpexError> {
+    let mut findings = vec![doctor::check_backend(args.backend, backend)];
+
+    match backend {
+        Some(Backend::Xrandr) => {
+            let (manager_findings, manager) = doctor::check_manager(XrandrManager::open);
+            findings.extend(manager_findings);
+
+            if let Some(manager) = manager {
+                findings.push(doctor::check_randr_version(manager.randr_version()));
+            }
+        }
+        Some(Backend::Sway) => findings.extend(doctor::check_manager(SwayManager::open).0),
+        Some(Backend::Mutter) => findings.extend(doctor::check_manager(MutterManager::open).0),
+        Some(Backend::Wlr) => findings.extend(doctor::check_manager(WlrManager::open).0),
+        #[cfg(any(test, feature = "test-support"))]
+        Some(Backend::Mock) => findings.extend(doctor::check_manager(MockMonitorManager::open).0),
+        _ => {}
+    }
+
+    let config_path = args.config.clone().or(XrpexConfig::default_path().ok());
+    findings.push(doctor::check_config(config_path.as_deref()));
+    findings.extend(doctor::check_tools());
+
+    for finding in &findings {
+        println!("{finding}");
+    }
+
+    let problems = findings
+        .iter()
+        .filter(|Finding { severity, .. }| *severity == Severity::Error)
+        .count();
+
+    if problems != 0 {
+        return Err(XrpexError::Doctor(problems));
+    }
+
+    Ok(())
+}
+
+// Expressions come straight from shells and display managers, so they are parsed from what the
+// OS gave rather than after clap converts them, to report where an argument isn't UTF-8.
+fn monitor_rpex_parser() -> impl TypedValueParser<Value = MonitorRpex> {
+    OsStringValueParser::new().try_map(|s| MonitorRpex::from_os_str(&s))
+}
+
+fn monitor_candidates() -> ArgValueCandidates {
+    ArgValueCandidates::new(|| {
+        monitor_names()
+            .into_iter()
+            .map(CompletionCandidate::new)
+            .collect()
+    })
+}
+
+// Completion runs before arguments are parsed, so the backend only comes from the environment,
+// and any failure to reach it just means no candidates.
+fn monitor_names() -> Vec<String> {
+    fn names<M: RpexMonitorManager>(
+        open: impl FnOnce() -> Result<M, M::ManagerError>,
+    ) -> Vec<String> {
+        open()
+            .ok()
+            .and_then(|mut manager| {
+                Some(
+                    manager
+                        .get_monitors()
+                        .ok()?
+                        .into_iter()
+                        .map(|RpexMonitor { name, .. }| name)
+                        .collect(),
+                )
+            })
+            .unwrap_or_default()
+    }
+
+    let backend = std::env::var("XRPEX_BACKEND")
+        .ok()
+        .and_then(|backend| Backend::from_str(&backend, true).ok())
+        .unwrap_or_default();
+
+    let registry = BackendRegistry::builtin();
+
+    backend
+        .resolve()
+        .and_then(|backend| registry.get(&backend.to_string()))
+        .map_or_else(Vec::new, names)
+}
+
+fn init_logging(verbose: u8, format: LogFormat) {
+    let level = match verbose {
+        0 => Level::WARN,
+        1 => Level::INFO,
+        2 => Level::DEBUG,
+        _ => Level::TRACE,
+    };
+
+    let subscriber = tracing_subscriber::fmt()
+        .with_max_level(level)
+        .with_ansi(std::io::stderr().is_terminal())
+        .with_writer(std::io::stderr);
+
+    match format {
+        LogFormat::Text => subscriber.init(),
+        LogFormat::Json => subscriber.json().init(),
+    }
+}
+
+fn query_monitors<M: RpexMonitorManager>(manager: &mut M) -> Result<Vec<RpexMonitor>, XrpexError>
+where
+    XrpexError: From<M::ManagerError>,
+{
+    let monitors = manager.get_monitors()?;
+
+    for RpexMonitor {
+        name,
+        position: [x, y],
+        resolution,
+        ..
+    } in &monitors
+    {
+        let [width, height] = resolution.lengths;
+
+        debug!(name, geometry = %format!("{width}x{height}+{x}+{y}"), "queried monitor");
+    }
+
+    Ok(monitors)
+}
+
+// The manager is only opened once the command line and config are known to be usable.
+fn run<M: RpexMonitorManager>(
+    mut args: XrpexArgs,
+    open: impl FnOnce() -> Result<M, M::ManagerError>,
+) -> Result<(), XrpexError>
+where
+    XrpexError: From<M::ManagerError>,
+{
+    args.name_template = configured_name_template(args.name_template, args.config.as_deref())?;
+
+    let options = ApplyOptions::from_args(&args);
+
+    match args.command {
+        Some(XrpexCommand::Status { monitor }) => status(
+            &mut open()?,
+            &monitor,
+            &args.name_template.unwrap_or_default(),
+        ),
+        Some(
+            XrpexCommand::Doctor
+            | XrpexCommand::CheckConfig
+            | XrpexCommand::InstallService { .. }
+            | XrpexCommand::ServeDbus,
+        ) => {
+            unreachable!("runs without a backend")
+        }
+        #[cfg(feature = "schemars")]
+        Some(XrpexCommand::Schema { .. }) => unreachable!("runs without a backend"),
+        Some(XrpexCommand::Undo { monitor }) => undo(
+            open,
+            monitor,
+            ApplyOptions {
+                output: args.output,
+                confirm: args.confirm,
+                hooks: configured_hooks(args.config.as_deref())?,
+                ..options
+            },
+        ),
+        Some(XrpexCommand::Export { layout }) => {
+            export(&mut open()?, args.name_template.as_ref(), layout)
+        }
+        Some(XrpexCommand::Persist { format }) => {
+            persist(&mut open()?, format, args.name_template.as_ref())
+        }
+        Some(XrpexCommand::Reset { monitor, pattern }) => reset(
+            &mut open()?,
+            monitor.as_deref(),
+            pattern.as_ref(),
+            &args.name_template.unwrap_or_default(),
+            &configured_hooks(args.config.as_deref())?,
+        ),
+        Some(XrpexCommand::Profile {
+            command: ProfileCommand::Save { name },
+        }) => save_profile(&mut open()?, &name, &args.name_template.unwrap_or_default()),
+        Some(XrpexCommand::Tile {
+            rpexes,
+            monitor,
+            windows,
+            execute,
+        }) => tile(
+            &mut open()?,
+            targets_from_rpexes(rpexes, monitor)?,
+            windows,
+            execute,
+            options,
+        ),
+        Some(XrpexCommand::Script { rpexes, monitor }) => {
+            script(&mut open()?, targets_from_rpexes(rpexes, monitor)?, options)
+        }
+        Some(XrpexCommand::MetaMode { rpexes, monitor }) => {
+            meta_mode(&mut open()?, targets_from_rpexes(rpexes, monitor)?, options)
+        }
+        Some(XrpexCommand::Profile {
+            command: ProfileCommand::Apply { name, output },
+        }) => {
+            let targets = config::load_profile(&name)?.targets();
+
+            apply(
+                open,
+                targets,
+                ApplyOptions {
+                    output,
+                    keep_existing: args.keep_existing,
+                    workspaces: args.assign_workspaces,
+                    confirm: args.confirm,
+                    record_history: true,
+                    hooks: configured_hooks(args.config.as_deref())?,
+                    ..options
+                },
+            )
+        }
+        None => {
+            let mut name_template = args.name_template;
+            let mut gap = args.gap;
+            let mut workspaces = args.assign_workspaces;
+            let mut dpi = args.dpi;
+            let hooks;
+
+            let targets = if args.rpexes.is_empty() {
+                let config_path = match args.config {
+                    Some(path) => path,
+                    None => XrpexConfig::default_path()?,
+                };
+
+                let config = XrpexConfig::load(&config_path)?;
+
+                name_template = name_template.or(config.name_template);
+                gap = gap.or(config.gap);
+                dpi = dpi.or(config.dpi);
+
+                if workspaces.is_empty() {
+                    workspaces = config.workspaces;
+                }
+
+                hooks = config.hooks;
+
+                config.monitors.into_iter().collect::<Vec<_>>()
+            } else {
+                hooks = configured_hooks(args.config.as_deref())?;
+
+                targets_from_rpexes(args.rpexes, args.monitor)?
+            };
+
+            apply(
+                open,
+                targets,
+                ApplyOptions {
+                    name_template: name_template.unwrap_or_default(),
+                    gap: gap.gutter(),
+                    output: args.output,
+                    keep_existing: args.keep_existing,
+                    workspaces,
+                    confirm: args.confirm,
+                    record_history: true,
+                    dpi,
+                    hooks,
+                    ..options
+                },
+            )
+        }
+    }
+}
+
+fn targets_from_rpexes(
+    rpexes: Vec<MonitorRpex>,
+    default_monitor: Option<String>,
+) -> Result<Vec<(String, Rpex<2>)>, XrpexError> {
+    rpexes
+        .into_iter()
+        .map(
+            |MonitorRpex { monitor, rpex }| match monitor.or(default_monitor.clone()) {
+                Some(parent_name) => Ok((parent_name, rpex)),
+                None => Err(XrpexError::MissingMonitor(Box::new(rpex))),
+            },
+        )
+        .collect()
+}
+
+fn apply<M: RpexMonitorManager>(
+    open: impl FnOnce() -> Result<M, M::ManagerError>,
+    targets: Vec<(String, Rpex<2>)>,
+    options: ApplyOptions,
+) -> Result<(), XrpexError>
+where
+    XrpexError: From<M::ManagerError>,
+{
+    if targets.is_empty() {
+        return Err(XrpexError::NoTargets);
+    }
+
+    let mut parent_names = HashSet::new();
+    for (parent_name, _) in &targets {
+        if !parent_names.insert(parent_name) {
+            return Err(XrpexError::DuplicateMonitor(parent_name.clone()));
+        }
+    }
+
+    for ((parent_name, _), (other_name, _)) in targets.iter().tuple_combinations() {
+        for (ancestor_name, name) in [(parent_name, other_name), (other_name, parent_name)] {
+            if options.name_template.is_descendant(name, ancestor_name) {
+                return Err(XrpexError::NestedMonitor(
+                    ancestor_name.clone(),
+                    name.clone(),
+                ));
+            }
+        }
+    }
+
+    for (parent_name, rpex) in &targets {
+        info!(monitor = parent_name, rpex = %rpex, "applying expression");
+    }
+
+    let mut manager = open()?;
+
+    let monitors = query_monitors(&mut manager)?;
+    let monitors_before = monitors.clone();
+
+    // A virtual monitor that was split has been replaced by its own virtual monitors, so it is
+    // restored from them before it is split again.
+    let restored = if options.keep_existing {
+        vec![]
+    } else {
+        targets
+            .iter()
+            .filter(|(parent_name, _)| !monitors.iter().any(|monitor| &monitor.name == parent_name))
+            .filter_map(|(parent_name, _)| {
+                restore_virtual_monitor(parent_name, &monitors, &options.name_template)
+            })
+            .collect::<Vec<_>>()
+    };
+
+    // Missing monitors are caught before anything is reset, so that nothing changes.
+    for (parent_name, _) in &targets {
+        if !monitors
+            .iter()
+            .any(|RpexMonitor { name, .. }| name == parent_name)
+            && !restored
+                .iter()
+                .any(|VirtualMonitor { name, .. }| name == parent_name)
+        {
+            return Err(XrpexError::NoMonitor(parent_name.clone()));
+        }
+    }
+
+    let monitors_with_restored = monitors
+        .iter()
+        .cloned()
+        .chain(restored.iter().map(RpexMonitor::from))
+        .collect::<Vec<_>>();
+
+    for (parent_name, foreign_name) in
+        foreign_monitors(&monitors_with_restored, &targets, &options.name_template)
+    {
+        if !options.force {
+            return Err(XrpexError::ForeignMonitor(parent_name, foreign_name));
+        }
+
+        warn!(
+            monitor = parent_name,
+            overlapped_by = foreign_name,
+            "splitting monitor overlapped by a monitor xrpex didn't create"
+        );
+    }
+
+    // The monitors as they will be once the targets are reset, which is what every expression is
+    // evaluated against before anything is reset, so that one bad expression changes nothing.
+    let monitors = if options.keep_existing {
+        monitors
+    } else {
+        monitors_with_restored
+            .iter()
+            .filter(|RpexMonitor { name, .. }| {
+                !targets
+                    .iter()
+                    .any(|(parent_name, _)| options.name_template.is_descendant(name, parent_name))
+            })
+            .cloned()
+            .collect()
+    };
+
+    let virtual_monitors = plan_targets(&monitors, &targets, &options)?;
+
+    let mut names = HashSet::new();
+    for VirtualMonitor { name, .. } in &virtual_monitors {
+        if !names.insert(name) {
+            return Err(XrpexError::DuplicateName(name.clone()));
+        }
+    }
+
+    if options.keep_existing {
+        check_existing_collisions(&monitors, &virtual_monitors, &options.name_template)?;
+    }
+
+    if options.confirm && !confirm_preview(&monitors, &targets, &virtual_monitors)? {
+        return Err(XrpexError::Aborted);
+    }
+
+    let parent_names = targets
+        .iter()
+        .map(|(parent_name, _)| parent_name.as_str())
+        .collect::<Vec<_>>()
+        .join(",");
+
+    if !options.keep_existing {
+        run_hooks(
+            &options.hooks.pre_reset,
+            &[("XRPEX_MONITORS", parent_names.clone())],
+        );
+
+        for (parent_name, _) in &targets {
+            manager.reset_rpex_monitors(parent_name, &options.name_template)?;
+        }
+
+        manager.set_virtual_monitors(&restored)?;
+    }
+
+    manager.set_virtual_monitors(&virtual_monitors)?;
+
+    let split = targets
+        .iter()
+        .map(|(parent_name, _)| parent_name.clone())
+        .filter(|parent_name| {
+            nearest_ancestor(parent_name, &monitors, &options.name_template).is_some()
+        })
+        .collect::<Vec<_>>();
+
+    manager.delete_monitors(&split)?;
+
+    if !options.workspaces.is_empty() {
+        let mut ipc = I3Ipc::connect_to_env()?;
+
+        let assignments =
+            options.workspaces.iter().zip(&virtual_monitors).map(
+                |(workspace, VirtualMonitor { name, .. })| (workspace.as_str(), name.as_str()),
+            );
+
+        for command in i3ipc::workspace_commands(assignments) {
+            ipc.run_command(&command)?;
+        }
+    }
+
+    if options.record_history {
+        record_history(&monitors_before, &targets, &options);
+    }
+
+    run_hooks(
+        &options.hooks.post_apply,
+        &[
+            ("XRPEX_MONITORS", parent_names),
+            ("XRPEX_LAYOUT", virtual_monitors_json(&virtual_monitors)),
+        ],
+    );
+
+    match options.output {
+        Some(OutputFormat::Json) => println!("{}", virtual_monitors_json(&virtual_monitors)),
+        Some(OutputFormat::Shell) => print!("{}", virtual_monitors_shell(&virtual_monitors)),
+        Some(OutputFormat::Null) => print!("{}", virtual_monitors_null(&virtual_monitors)),
+        None => {
+            for (parent_name, rpex) in &targets {
+                let count = virtual_monitors
+                    .iter()
+                    .filter(|VirtualMonitor { parent, .. }| parent == parent_name)
+                    .count();
+
+                println!("{parent_name}: applied {rpex} as {count} virtual monitor(s)");
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// History is a convenience, so failing to infer or save it doesn't fail an otherwise successful
+// apply. Each target is recorded on its own, so that one whose layout can't be told doesn't lose
+// the history of the others.
+fn record_history(monitors: &[RpexMonitor], targets: &[(String, Rpex<2>)], options: &ApplyOptions) {
+    let mut history = match XrpexHistory::load() {
+        Ok(history) => history,
+        Err(e) => {
+            warn!("unable to record history: {e}");
+            return;
+        }
+    };
+
+    record_layouts(&mut history, monitors, targets, options);
+
+    if let Err(e) = history.save() {
+        warn!("unable to record history: {e}");
+    }
+}
+
+fn record_layouts(
+    history: &mut XrpexHistory,
+    monitors: &[RpexMonitor],
+    targets: &[(String, Rpex<2>)],
+    options: &ApplyOptions,
+) {
+    let name_template = &options.name_template;
+
+    for (parent_name, rpex) in targets {
+        let is_split = monitors
+            .iter()
+            .any(|monitor| name_template.parent_of(&monitor.name) == Some(parent_name));
+
+        let previous = if is_split {
+            current_rpex(parent_name, monitors, name_template, history).map(Some)
+        } else {
+            Ok(None)
+        };
+
+        match previous {
+            Ok(previous) => history.push(parent_name, previous.as_ref()),
+            Err(e) => warn!(monitor = parent_name, "unable to record history: {e}"),
+        }
+
+        history.record_applied(parent_name, rpex, options.gap, &options.skip);
+    }
+}
+
+// Recorded layouts only spare inferring expressions, so history that can't be read is ignored.
+fn recorded_history() -> XrpexHistory {
+    XrpexHistory::load().unwrap_or_else(|e| {
+        warn!("unable to read history: {e}");
+        XrpexHistory::default()
+    })
+}
+
+fn undo<M: RpexMonitorManager>(
+    open: impl FnOnce() -> Result<M, M::ManagerError>,
+    parent_name: String,
+    options: ApplyOptions,
+) -> Result<(), XrpexError>
+where
+    XrpexError: From<M::ManagerError>,
+{
+    let mut history = XrpexHistory::load()?;
+
+    undo_in(open, parent_name, options, &mut history)?;
+
+    history.save()?;
+
+    Ok(())
+}
+
+// The layout undone to is recorded as applied, since its labels would be lost if a later apply
+// had to infer it.
+fn undo_in<M: RpexMonitorManager>(
+    open: impl FnOnce() -> Result<M, M::ManagerError>,
+    parent_name: String,
+    options: ApplyOptions,
+    history: &mut XrpexHistory,
+) -> Result<(), XrpexError>
+where
+    XrpexError: From<M::ManagerError>,
+{
+    match history.pop(&parent_name)? {
+        Some(rpex) => {
+            let (gap, skip) = (options.gap, options.skip.clone());
+
+            apply(open, vec![(parent_name.clone(), rpex.clone())], options)?;
+
+            history.record_applied(&parent_name, &rpex, gap, &skip);
+        }
+        None => {
+            open()?.reset_rpex_monitors(&parent_name, &options.name_template)?;
+
+            history.applied.remove(&parent_name);
+
+            println!("{parent_name}: removed all virtual monitors");
+        }
+    }
+
+    Ok(())
+}
+
+fn confirm_preview(
+    monitors: &[RpexMonitor],
+    targets: &[(String, Rpex<2>)],
+    virtual_monitors: &[VirtualMonitor],
+) -> Result<bool, XrpexError> {
+    for (parent_name, _) in targets {
+        let Some(monitor) = monitors.iter().find(|monitor| &monitor.name == parent_name) else {
+            continue;
+        };
+        let children = virtual_monitors
+            .iter()
+            .filter(|VirtualMonitor { parent, .. }| parent == parent_name)
+            .collect::<Vec<_>>();
+
+        eprintln!("{}", preview::render_preview(monitor, &children, 48));
+    }
+
+    eprint!("apply these virtual monitors? [y/N] ");
+
+    let mut answer = String::new();
+    io::stdin()
+        .read_line(&mut answer)
+        .map_err(XrpexError::Confirm)?;
+
+    Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+}
+
+// Monitors that overlap a target but aren't related to it by name were made by something else,
+// e.g. `xrandr --setmonitor`, and would be left stacked on top of the new virtual monitors.
+fn foreign_monitors(
+    monitors: &[RpexMonitor],
+    targets: &[(String, Rpex<2>)],
+    name_template: &NameTemplate,
+) -> Vec<(String, String)> {
+    let mut foreign = vec![];
+
+    for (parent_name, _) in targets {
+        let Some(parent) = monitors.iter().find(|monitor| &monitor.name == parent_name) else {
+            continue;
+        };
+
+        for monitor in monitors {
+            let is_related = monitor.name == *parent_name
+                || name_template.is_descendant(&monitor.name, parent_name)
+                || name_template.is_descendant(parent_name, &monitor.name);
+
+            if !is_related && parent.overlaps(monitor.position, monitor.resolution.lengths) {
+                foreign.push((parent_name.clone(), monitor.name.clone()));
+            }
+        }
+    }
+
+    foreign
+}
+
+// Existing virtual monitors are only kept when they don't clash with the new ones, either by
+// name or by covering the same part of their parent.
+fn check_existing_collisions(
+    monitors: &[RpexMonitor],
+    virtual_monitors: &[VirtualMonitor],
+    name_template: &NameTemplate,
+) -> Result<(), XrpexError> {
+    for virtual_monitor in virtual_monitors {
+        for monitor in monitors {
+            if monitor.name == virtual_monitor.name {
+                return Err(XrpexError::DuplicateName(monitor.name.clone()));
+            }
+
+            let overlaps =
+                monitor.overlaps(virtual_monitor.position, virtual_monitor.resolution.lengths);
+
+            if overlaps && name_template.is_descendant(&monitor.name, &virtual_monitor.parent) {
+                return Err(XrpexError::OverlappingMonitor(
+                    virtual_monitor.name.clone(),
+                    monitor.name.clone(),
+                ));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn plan_targets(
+    monitors: &[RpexMonitor],
+    targets: &[(String, Rpex<2>)],
+    options: &ApplyOptions,
+) -> Result<Vec<VirtualMonitor>, XrpexError> {
+    let mut virtual_monitors = vec![];
+    let mut failures = vec![];
+
+    for (parent_name, rpex) in targets {
+        let planned = monitors
+            .iter()
+            .find(|RpexMonitor { name, .. }| name == parent_name)
+            .ok_or_else(|| XrpexError::NoMonitor(parent_name.clone()))
+            .and_then(|monitor| monitor.plan_virtual_monitors(rpex.clone(), options));
+
+        match planned {
+            Ok(planned) => {
+                for VirtualMonitor {
+                    name,
+                    position: [x, y],
+                    resolution,
+                    ..
+                } in &planned
+                {
+                    let [width, height] = resolution.lengths;
+
+                    debug!(
+                        name,
+                        parent = parent_name,
+                        rpex = %rpex,
+                        geometry = %format!("{width}x{height}+{x}+{y}"),
+                        "planned virtual monitor"
+                    );
+                }
+
+                virtual_monitors.extend(planned)
+            }
+            Err(e) => failures.push((parent_name.clone(), e)),
+        }
+    }
+
+    if !failures.is_empty() {
+        return Err(XrpexError::Failed(failures));
+    }
+
+    Ok(virtual_monitors)
+}
+
+fn meta_mode<M: RpexMonitorManager>(
+    manager: &mut M,
+    targets: Vec<(String, Rpex<2>)>,
+    options: ApplyOptions,
+) -> Result<(), XrpexError>
+where
+    XrpexError: From<M::ManagerError>,
+{
+    let monitors = query_monitors(manager)?;
+
+    let virtual_monitors = plan_targets(&monitors, &targets, &options)?;
+
+    // MetaModes list displays, so existing virtual monitors are left out.
+    let monitor_names = monitors
+        .iter()
+        .map(|monitor| monitor.name.as_str())
+        .collect::<HashSet<_>>();
+    let history = recorded_history();
+    let displays = monitors
+        .iter()
+        .filter(|monitor| {
+            !is_virtual(
+                &monitor.name,
+                &monitor_names,
+                &options.name_template,
+                &history,
+            )
+        })
+        .cloned()
+        .collect::<Vec<_>>();
+
+    println!(
+        "nvidia-settings --assign CurrentMetaMode=\"{}\"",
+        nvidia_meta_mode(&displays, &virtual_monitors)
+    );
+
+    Ok(())
+}
+
+// Without expressions the current virtual monitors are reproduced as they are, since their
+// parents may no longer be listed as monitors.
+fn script<M: RpexMonitorManager>(
+    manager: &mut M,
+    targets: Vec<(String, Rpex<2>)>,
+    options: ApplyOptions,
+) -> Result<(), XrpexError>
+where
+    XrpexError: From<M::ManagerError>,
+{
+    let name_template = &options.name_template;
+    let monitors = query_monitors(manager)?;
+    let monitor_names = monitors
+        .iter()
+        .map(|monitor| monitor.name.as_str())
+        .collect::<HashSet<_>>();
+    let history = recorded_history();
+
+    let (virtual_monitors, split) = if targets.is_empty() {
+        let virtual_monitors = monitors
+            .iter()
+            .filter(|monitor| is_virtual(&monitor.name, &monitor_names, name_template, &history))
+            .filter_map(|monitor| {
+                Some(VirtualMonitor {
+                    name: monitor.name.clone(),
+                    parent: name_template.parent_of(&monitor.name)?.to_string(),
+                    position: monitor.position,
+                    resolution: monitor.resolution,
+                    physical_size: monitor.physical_size,
+                })
+            })
+            .collect::<Vec<_>>();
+
+        (virtual_monitors, vec![])
+    } else {
+        let restored = targets
+            .iter()
+            .filter(|(parent_name, _)| !monitors.iter().any(|monitor| &monitor.name == parent_name))
+            .filter_map(|(parent_name, _)| {
+                restore_virtual_monitor(parent_name, &monitors, name_template)
+            })
+            .collect::<Vec<_>>();
+        let monitors = monitors
+            .iter()
+            .cloned()
+            .chain(restored.iter().map(RpexMonitor::from))
+            .collect::<Vec<_>>();
+
+        let virtual_monitors = plan_targets(&monitors, &targets, &options)?
+            .into_iter()
+            .filter(|VirtualMonitor { name, .. }| {
+                !targets.iter().any(|(parent_name, _)| parent_name == name)
+            })
+            .collect::<Vec<_>>();
+        let split = targets
+            .into_iter()
+            .map(|(parent_name, _)| parent_name)
+            .filter(|parent_name| is_virtual(parent_name, &monitor_names, name_template, &history))
+            .collect::<Vec<_>>();
+
+        (virtual_monitors, split)
+    };
+
+    if virtual_monitors.is_empty() {
+        return Err(XrpexError::NoTargets);
+    }
+
+    print!(
+        "{}",
+        persist::xrandr_script(&virtual_monitors, &split, name_template)
+    );
+
+    Ok(())
+}
+
+fn tile<M: RpexMonitorManager>(
+    manager: &mut M,
+    targets: Vec<(String, Rpex<2>)>,
+    windows: Vec<String>,
+    execute: bool,
+    options: ApplyOptions,
+) -> Result<(), XrpexError>
+where
+    XrpexError: From<M::ManagerError>,
+{
+    let monitors = query_monitors(manager)?;
+
+    let virtual_monitors = plan_targets(&monitors, &targets, &options)?;
+
+    let windows = if windows.is_empty() {
+        tile::list_windows()?
+    } else {
+        windows
+    };
+
+    let commands = tile::tile_commands(&windows, &virtual_monitors);
+
+    if execute {
+        tile::run_tile_commands(&commands)?;
+    } else {
+        for args in &commands {
+            println!("wmctrl {}", args.join(" "));
+        }
+    }
+
+    Ok(())
+}
+
+fn nearest_ancestor<'a>(
+    name: &str,
+    monitors: &'a [RpexMonitor],
+    name_template: &NameTemplate,
+) -> Option<&'a RpexMonitor> {
+    name_template.ancestors_of(name).find_map(|ancestor_name| {
+        monitors
+            .iter()
+            .find(|monitor| monitor.name == ancestor_name)
+    })
+}
+
+fn restore_virtual_monitor(
+    name: &str,
+    monitors: &[RpexMonitor],
+    name_template: &NameTemplate,
+) -> Option<VirtualMonitor> {
+    let parent = nearest_ancestor(name, monitors, name_template)?;
+
+    let descendants = monitors
+        .iter()
+        .filter(|monitor| name_template.is_descendant(&monitor.name, name))
+        .collect::<Vec<_>>();
+
+    let [start, end] = [
+        [0, 1].map(|dim| {
+            descendants
+                .iter()
+                .map(|RpexMonitor { position, .. }| position[dim])
+                .min()
+        }),
+        [0, 1].map(|dim| {
+            descendants
+                .iter()
+                .map(
+                    |RpexMonitor {
+                         position,
+                         resolution,
+                         ..
+                     }| {
+                        position[dim].saturating_add_unsigned(resolution.lengths[dim])
+                    },
+                )
+                .max()
+        }),
+    ];
+
+    let position = [start[0]?, start[1]?];
+    let lengths = [end[0]?.abs_diff(position[0]), end[1]?.abs_diff(position[1])];
+
+    Some(VirtualMonitor {
+        name: name.to_string(),
+        parent: parent.name.clone(),
+        position,
+        resolution: HyperRectangle { lengths },
+        physical_size: parent.physical_size_of(lengths),
+    })
+}
+
+// Targets that reproduce the existing virtual monitors, keyed by the monitor they split.
+fn current_targets<M: RpexMonitorManager>(
+    manager: &mut M,
+    name_template: &NameTemplate,
+) -> Result<BTreeMap<String, Rpex<2>>, XrpexError>
+where
+    XrpexError: From<M::ManagerError>,
+{
+    infer_targets(
+        &query_monitors(manager)?,
+        name_template,
+        &recorded_history(),
+    )
+}
+
+fn infer_targets(
+    monitors: &[RpexMonitor],
+    name_template: &NameTemplate,
+    history: &XrpexHistory,
+) -> Result<BTreeMap<String, Rpex<2>>, XrpexError> {
+    let monitor_names = monitors
+        .iter()
+        .map(|monitor| monitor.name.as_str())
+        .collect::<HashSet<_>>();
+
+    monitors
+        .iter()
+        .filter(|monitor| is_virtual(&monitor.name, &monitor_names, name_template, history))
+        .filter_map(|monitor| name_template.parent_of(&monitor.name))
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .map(|parent_name| {
+            let rpex = current_rpex(parent_name, monitors, name_template, history)?;
+
+            Ok((parent_name.to_string(), rpex))
+        })
+        .collect()
+}
+
+// The expression last applied to a monitor while its virtual monitors are still the ones it
+// created, and otherwise one inferred from them.
+fn current_rpex(
+    parent_name: &str,
+    monitors: &[RpexMonitor],
+    name_template: &NameTemplate,
+    history: &XrpexHistory,
+) -> Result<Rpex<2>, XrpexError> {
+    let rpex_monitors = monitors
+        .iter()
+        .filter(|monitor| name_template.matches(&monitor.name, parent_name))
+        .cloned()
+        .collect::<Vec<_>>();
+
+    match recorded_rpex(
+        parent_name,
+        &rpex_monitors,
+        monitors,
+        name_template,
+        history,
+    ) {
+        Some(rpex) => Ok(rpex),
+        None => Ok(infer_rpex(rpex_monitors)?),
+    }
+}
+
+// Applying the recorded layout again must plan exactly the virtual monitors there are, or they
+// were changed since.
+fn recorded_rpex(
+    parent_name: &str,
+    rpex_monitors: &[RpexMonitor],
+    monitors: &[RpexMonitor],
+    name_template: &NameTemplate,
+    history: &XrpexHistory,
+) -> Option<Rpex<2>> {
+    let applied = history.applied.get(parent_name)?;
+    let rpex = applied.rpex.parse::<Rpex<2>>().ok()?;
+
+    let parent = match monitors.iter().find(|monitor| monitor.name == parent_name) {
+        Some(parent) => parent.clone(),
+        None => RpexMonitor::from(&restore_virtual_monitor(
+            parent_name,
+            monitors,
+            name_template,
+        )?),
+    };
+    let options = ApplyOptions {
+        name_template: name_template.clone(),
+        gap: applied.gap,
+        skip: applied.skip.clone(),
+        ..Default::default()
+    };
+
+    let planned = parent.plan_virtual_monitors(rpex.clone(), &options).ok()?;
+    let is_unchanged = planned.len() == rpex_monitors.len()
+        && planned.iter().all(|virtual_monitor| {
+            rpex_monitors.iter().any(|monitor| {
+                monitor.name == virtual_monitor.name
+                    && monitor.position == virtual_monitor.position
+                    && monitor.resolution == virtual_monitor.resolution
+            })
+        });
+
+    is_unchanged.then_some(rpex)
+}
+
+// The monitors and virtual monitors are listed as comments above the config, so that the
+// inferred expressions can be checked against them.
+fn export_config(
+    monitors: &[RpexMonitor],
+    name_template: Option<&NameTemplate>,
+    history: &XrpexHistory,
+) -> Result<String, XrpexError> {
+    let default_template = NameTemplate::default();
+    let template = name_template.unwrap_or(&default_template);
+
+    let config = XrpexConfig {
+        monitors: infer_targets(monitors, template, history)?,
+        name_template: name_template.cloned(),
+        ..Default::default()
+    };
+
+    let mut contents = String::new();
+
+    for monitor in monitors {
+        let RpexMonitor {
+            name,
+            position: [x, y],
+            resolution,
+            ..
+        } = monitor;
+        let [width, height] = resolution.lengths;
+        let indent = "  ".repeat(template.ancestors_of(name).count());
+
+        contents.push_str(&format!("# {indent}{name} {width}x{height}+{x}+{y}\n"));
+    }
+
+    if !contents.is_empty() {
+        contents.push('\n');
+    }
+
+    contents.push_str(&config.to_toml()?);
+
+    Ok(contents)
+}
+
+fn layout_file(
+    monitors: &[RpexMonitor],
+    name_template: &NameTemplate,
+    history: &XrpexHistory,
+) -> Result<LayoutFile, XrpexError> {
+    let monitor_layouts = infer_targets(monitors, name_template, history)?
+        .into_iter()
+        .map(|(parent_name, rpex)| {
+            let layout = MonitorLayout {
+                rpex,
+                geometry: monitors
+                    .iter()
+                    .find(|monitor| monitor.name == parent_name)
+                    .map(Geometry::from),
+                virtual_monitors: monitors
+                    .iter()
+                    .filter(|monitor| name_template.parent_of(&monitor.name) == Some(&parent_name))
+                    .map(|monitor| (monitor.name.clone(), Geometry::from(monitor)))
+                    .collect(),
+            };
+
+            (parent_name, layout)
+        })
+        .collect();
+
+    Ok(LayoutFile::new(monitor_layouts))
+}
+
+fn export<M: RpexMonitorManager>(
+    manager: &mut M,
+    name_template: Option<&NameTemplate>,
+    layout: bool,
+) -> Result<(), XrpexError>
+where
+    XrpexError: From<M::ManagerError>,
+{
+    let monitors = query_monitors(manager)?;
+    let history = recorded_history();
+
+    let contents = if layout {
+        let name_template = name_template.cloned().unwrap_or_default();
+
+        layout_file(&monitors, &name_template, &history)?.to_toml()?
+    } else {
+        export_config(&monitors, name_template, &history)?
+    };
+
+    print!("{contents}");
+
+    Ok(())
+}
+
+fn save_profile<M: RpexMonitorManager>(
+    manager: &mut M,
+    name: &str,
+    name_template: &NameTemplate,
+) -> Result<(), XrpexError>
+where
+    XrpexError: From<M::ManagerError>,
+{
+    let layout = layout_file(
+        &query_monitors(manager)?,
+        name_template,
+        &recorded_history(),
+    )?;
+
+    let path = config::save_profile(name, &layout)?;
+
+    println!("saved profile {name} to {}", path.display());
+
+    Ok(())
+}
+
+fn persist<M: RpexMonitorManager>(
+    manager: &mut M,
+    format: PersistFormat,
+    name_template: Option<&NameTemplate>,
+) -> Result<(), XrpexError>
+where
+    XrpexError: From<M::ManagerError>,
+{
+    let targets = current_targets(manager, &name_template.cloned().unwrap_or_default())?;
+
+    if targets.is_empty() {
+        return Err(XrpexError::NoTargets);
+    }
+
+    let program = std::env::current_exe()
+        .ok()
+        .and_then(|path| path.to_str().map(str::to_string))
+        .unwrap_or_else(|| "xrpex".to_string());
+    let command = persist::xrpex_command(&program, &targets, name_template);
+
+    match format {
+        PersistFormat::Desktop => {
+            let path = persist::write_autostart_entry(&persist::desktop_entry(&command))?;
+
+            println!("wrote autostart entry to {}", path.display());
+        }
+        PersistFormat::Shell => print!("{}", persist::shell_script(&command)),
+    }
+
+    Ok(())
+}
+
+#[cfg(feature = "schemars")]
+fn schema(profile: bool) {
+    let schema = if profile {
+        schemars::schema_for!(LayoutFile)
+    } else {
+        schemars::schema_for!(XrpexConfig)
+    };
+
+    println!(
+        "{}",
+        serde_json::to_string_pretty(&schema).expect("schemas serialize to JSON")
+    );
+}
+
+// Expressions are still checked when monitors can't be queried, e.g. before logging in, just only
+// against the resolutions profiles recorded.
+fn check_config(
+    config: Option<&Path>,
+    registry: &BackendRegistry,
+    backend: Option<String>,
+) -> Result<(), XrpexError> {
+    let mut findings = vec![];
+
+    let monitors = backend
+        .ok_or(XrpexError::NoBackend)
+        .and_then(|backend| {
+            let open = registry
+                .get(&backend)
+                .ok_or(XrpexError::UnsupportedBackend(backend))?;
+
+            Ok(open()?.get_monitors()?)
+        })
+        .unwrap_or_else(|e| {
+            findings.push(Finding::warning(
+                "monitors",
+                format!("unable to query monitors: {e}"),
+                "run check-config inside your graphical session to evaluate config expressions",
+            ));
+
+            vec![]
+        });
+
+    match config
+        .map(Path::to_path_buf)
+        .or(XrpexConfig::default_path().ok())
+    {
+        Some(path) if path.exists() => match fs::read_to_string(&path) {
+            Ok(contents) => findings.extend(check::check_config_file(&path, &contents, &monitors)),
+            Err(e) => findings.push(Finding::error(
+                "config",
+                ConfigError::Io(path, e).to_string(),
+                "check the permissions of the config file",
+            )),
+        },
+        Some(path) => findings.push(Finding::ok(
+            "config",
+            format!("no config file at {}", path.display()),
+        )),
+        None => findings.push(Finding::warning(
+            "config",
+            "unable to locate config directory",
+            "set HOME or XDG_CONFIG_HOME, or pass --config",
+        )),
+    }
+
+    for path in config::profile_paths().unwrap_or_default() {
+        match fs::read_to_string(&path) {
+            Ok(contents) => findings.extend(check::check_profile_file(&path, &contents, &monitors)),
+            Err(e) => findings.push(Finding::error(
+                "profile",
+                LayoutFileError::Io(path, e).to_string(),
+                "check the permissions of the profile",
+            )),
+        }
+    }
+
+    for finding in &findings {
+        println!("{finding}");
+    }
+
+    let problems = findings
+        .iter()
+        .filter(|Finding { severity, .. }| *severity == Severity::Error)
+        .count();
+
+    if problems != 0 {
+        return Err(XrpexError::CheckConfig(problems));
+    }
+
+    Ok(())
+}
+
+// Hooks are configured even when expressions are given on the command line, so they are read from
+// the config file if there is one.
+fn configured_hooks(config: Option<&Path>) -> Result<Hooks, XrpexError> {
+    Ok(optional_config(config)?
+        .map(|config| config.hooks)
+        .unwrap_or_default())
+}
+
+// Every subcommand has to recognise the virtual monitors that applying the config created, so
+// the template is read from the config file like hooks are, unless one was given.
+fn configured_name_template(
+    name_template: Option<NameTemplate>,
+    config: Option<&Path>,
+) -> Result<Option<NameTemplate>, XrpexError> {
+    match name_template {
+        Some(name_template) => Ok(Some(name_template)),
+        None => Ok(optional_config(config)?.and_then(|config| config.name_template)),
+    }
+}
+
+fn optional_config(config: Option<&Path>) -> Result<Option<XrpexConfig>, XrpexError> {
+    let path = match config {
+        Some(path) => path.to_path_buf(),
+        None => match XrpexConfig::default_path() {
+            Ok(path) if path.exists() => path,
+            _ => return Ok(None),
+        },
+    };
+
+    Ok(Some(XrpexConfig::load(&path)?))
+}
+
+fn install_service(config: Option<&Path>, udev: bool) -> Result<(), XrpexError> {
+    let program = std::env::current_exe()
+        .ok()
+        .and_then(|path| path.to_str().map(str::to_string))
+        .unwrap_or_else(|| "xrpex".to_string());
+
+    let mut command = vec![program];
+    if let Some(config) = config {
+        let config = std::path::absolute(config).map_err(|e| ConfigError::Io(config.into(), e))?;
+
+        command.extend(["--config".to_string(), config.display().to_string()]);
+    }
+
+    let path = persist::write_systemd_unit(&persist::systemd_unit(&command))?;
+
+    println!("wrote systemd unit to {}", path.display());
+    println!("enable it with `systemctl --user daemon-reload && systemctl --user enable xrpex`");
+
+    if udev {
+        print!("\n{}", persist::udev_rule(&persist::current_user()?));
+    }
+
+    Ok(())
+}
+
+fn reset<M: RpexMonitorManager>(
+    manager: &mut M,
+    parent_name: Option<&str>,
+    pattern: Option<&Pattern>,
+    name_template: &NameTemplate,
+    hooks: &Hooks,
+) -> Result<(), XrpexError>
+where
+    XrpexError: From<M::ManagerError>,
+{
+    let names = manager
+        .get_monitors()?
+        .into_iter()
+        .map(|RpexMonitor { name, .. }| name)
+        .collect::<Vec<_>>();
+
+    let names = names_to_reset(
+        names,
+        parent_name,
+        pattern,
+        name_template,
+        &recorded_history(),
+    );
+
+    if !names.is_empty() {
+        let parent_names = names
+            .iter()
+            .filter_map(|name| name_template.parent_of(name))
+            .unique()
+            .join(",");
+
+        run_hooks(&hooks.pre_reset, &[("XRPEX_MONITORS", parent_names)]);
+    }
+
+    manager.delete_monitors(&names)?;
+
+    for name in &names {
+        println!("{name}: deleted");
+    }
+
+    Ok(())
+}
+
+fn names_to_reset(
+    names: Vec<String>,
+    parent_name: Option<&str>,
+    pattern: Option<&Pattern>,
+    name_template: &NameTemplate,
+    history: &XrpexHistory,
+) -> Vec<String> {
+    let monitor_names = names.iter().map(String::as_str).collect::<HashSet<_>>();
+
+    names
+        .iter()
+        .filter(|name| is_virtual(name, &monitor_names, name_template, history))
+        .filter(|name| {
+            parent_name.is_none_or(|parent| {
+                name_template.is_descendant(name, parent)
+                    && (is_known(parent, &monitor_names, history)
+                        || is_virtual(parent, &monitor_names, name_template, history))
+            })
+        })
+        .filter(|name| pattern.is_none_or(|pattern| pattern.matches(name)))
+        .map(|name| name.to_string())
+        .collect()
+}
+
+// Physical monitors can fit the name template too, e.g. DP-1 for `{parent}-{index}`, so a name
+// only belongs to a virtual monitor if one of its ancestors is a monitor. X hides outputs covered
+// by virtual monitors, so monitors that xrpex applied a layout to count even when not listed.
+fn is_virtual(
+    name: &str,
+    monitor_names: &HashSet<&str>,
+    name_template: &NameTemplate,
+    history: &XrpexHistory,
+) -> bool {
+    name_template
+        .ancestors_of(name)
+        .any(|ancestor| is_known(ancestor, monitor_names, history))
+}
+
+fn is_known(name: &str, monitor_names: &HashSet<&str>, history: &XrpexHistory) -> bool {
+    monitor_names.contains(name) || history.applied.contains_key(name)
+}
+
+fn status<M: RpexMonitorManager>(
+    manager: &mut M,
+    parent_name: &str,
+    name_template: &NameTemplate,
+) -> Result<(), XrpexError>
+where
+    XrpexError: From<M::ManagerError>,
+{
+    let monitors = query_monitors(manager)?;
+
+    // X hides monitors split into virtual monitors, so a monitor exists while it has any.
+    if !monitors.iter().any(|monitor| {
+        monitor.name == parent_name || name_template.matches(&monitor.name, parent_name)
+    }) {
+        return Err(XrpexError::NoMonitor(parent_name.to_string()));
+    }
+
+    println!(
+        "{}",
+        current_rpex(parent_name, &monitors, name_template, &recorded_history())?
+    );
+
+    Ok(())
+}
+
+fn infer_rpex(rpex_monitors: Vec<RpexMonitor>) -> Result<Rpex<2>, RpexInferenceError> {
+    // Virtual monitors are positioned on the screen, so make them relative to their own corner.
+    let origin = [0, 1].map(|dim| {
+        rpex_monitors
+            .iter()
+            .map(|RpexMonitor { position, .. }| position[dim])
+            .min()
+            .unwrap_or(0)
+    });
+
+    let cells = rpex_monitors
+        .into_iter()
+        .map(
+            |RpexMonitor {
+                 position,
+                 resolution,
+                 ..
+             }| Cell {
+                position: [
+                    position[0].abs_diff(origin[0]),
+                    position[1].abs_diff(origin[1]),
+                ],
+                rectangle: resolution,
+            },
+        )
+        .collect::<Vec<_>>();
+
+    Rpex::infer(&cells)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn we_can_parse_rpex_arguments_from_os_str() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        let args = XrpexArgs::try_parse_from(["xrpex", "DP-1=2+1:1", "1:1"]).unwrap();
+
+        assert_eq!(
+            args.rpexes
+                .iter()
+                .map(|MonitorRpex { monitor, rpex }| (monitor.as_deref(), rpex.to_string()))
+                .collect::<Vec<_>>(),
+            vec![
+                (Some("DP-1"), "2+1:1".to_string()),
+                (None, "1:1".to_string())
+            ]
+        );
+
+        let error =
+            XrpexArgs::try_parse_from([OsStr::new("xrpex"), OsStr::from_bytes(b"DP-1=2+1:\xff")])
+                .err()
+                .unwrap();
+
+        assert_eq!(error.kind(), clap::error::ErrorKind::ValueValidation);
+        assert!(error
+            .to_string()
+            .contains("is not valid UTF-8 after byte 9"));
+    }
+
+    #[test]
+    fn we_can_read_the_name_template_from_config() {
+        let path =
+            std::env::temp_dir().join(format!("xrpex-name-template-{}.toml", std::process::id()));
+        std::fs::write(&path, "name_template = \"{parent}-side-{index}\"\n").unwrap();
+
+        let configured = configured_name_template(None, Some(&path)).unwrap();
+        let given =
+            configured_name_template(Some("{parent}-{index}".parse().unwrap()), Some(&path))
+                .unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(configured, Some("{parent}-side-{index}".parse().unwrap()));
+        assert_eq!(given, Some("{parent}-{index}".parse().unwrap()));
+    }
+
+    #[test]
+    fn we_can_print_errors_in_the_output_format_of_the_subcommand() {
+        let output = |args: &[&str]| output_format(&XrpexArgs::try_parse_from(args).unwrap());
+
+        assert_eq!(
+            output(&["xrpex", "--output", "json", "DP-1=+:"]),
+            Some(OutputFormat::Json)
+        );
+        assert_eq!(
+            output(&["xrpex", "profile", "apply", "desk", "--output", "json"]),
+            Some(OutputFormat::Json)
+        );
+        assert_eq!(output(&["xrpex", "profile", "apply", "desk"]), None);
+    }
+
+    #[test]
+    fn we_cannot_change_monitors_through_read_only_backends() {
+        let mut registry = BackendRegistry::default();
+
+        registry.register(
+            "unreachable",
+            Box::new(|| Err(BackendError::new("unable to connect"))),
+        );
+        registry.mark_read_only("unreachable");
+
+        let dispatch = |args: &[&str]| {
+            dispatch(
+                XrpexArgs::try_parse_from(args).unwrap(),
+                &registry,
+                Some("unreachable".to_string()),
+            )
+        };
+
+        for args in [
+            &["xrpex", "DP-1=2+1:1"][..],
+            &["xrpex", "reset", "DP-1"],
+            &["xrpex", "profile", "apply", "desk"],
+        ] {
+            assert!(matches!(
+                dispatch(args),
+                Err(XrpexError::ReadOnlyBackend(name)) if name == "unreachable"
+            ));
+        }
+
+        // Reading monitors still opens the backend.
+        assert!(matches!(
+            dispatch(&["xrpex", "status", "DP-1"]),
+            Err(XrpexError::Backend(_))
+        ));
+    }
+
+    #[test]
+    fn we_can_restore_split_virtual_monitors() {
+        let monitor = |name: &str, position, lengths, physical_size| RpexMonitor {
+            name: name.to_string(),
+            position,
+            resolution: HyperRectangle { lengths },
+            physical_size,
+        };
+        let monitors = [
+            monitor("DP-1", [1920, 0], [1920, 1080], [600, 340]),
+            monitor(
+                "DP-1-XRPEX-0-0-XRPEX-0-0",
+                [1920, 0],
+                [960, 540],
+                [300, 170],
+            ),
+            monitor(
+                "DP-1-XRPEX-0-0-XRPEX-0-540",
+                [1920, 540],
+                [960, 540],
+                [300, 170],
+            ),
+            monitor("DP-1-XRPEX-960-0", [2880, 0], [960, 1080], [300, 340]),
+        ];
+        let template = NameTemplate::default();
+
+        assert_eq!(
+            restore_virtual_monitor("DP-1-XRPEX-0-0", &monitors, &template),
+            Some(VirtualMonitor {
+                name: "DP-1-XRPEX-0-0".to_string(),
+                parent: "DP-1".to_string(),
+                position: [1920, 0],
+                resolution: HyperRectangle {
+                    lengths: [960, 1080]
+                },
+                physical_size: [300, 340],
+            })
+        );
+        assert_eq!(
+            restore_virtual_monitor("DP-1-XRPEX-960-0", &monitors, &template),
+            None
+        );
+    }
+
+    fn mock_state_file(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("xrpex-{name}-{}.json", std::process::id()));
+        let state = mock::MockState {
+            monitors: vec![mock::MockMonitor {
+                name: "DP-1".to_string(),
+                x: 1920,
+                y: 0,
+                width: 1920,
+                height: 1080,
+                width_mm: 600,
+                height_mm: 340,
+            }],
+            operations: vec![],
+        };
+        std::fs::write(&path, serde_json::to_string(&state).unwrap()).unwrap();
+
+        path
+    }
+
+    #[test]
+    fn we_can_apply_expressions_to_mock_monitors() {
+        let path = mock_state_file("apply");
+
+        let options = ApplyOptions::default;
+        let open = || MockMonitorManager::load(path.clone());
+
+        apply(
+            open,
+            vec![("DP-1".to_string(), "+:".parse().unwrap())],
+            options(),
+        )
+        .unwrap();
+        apply(
+            open,
+            vec![("DP-1-XRPEX-0-0".to_string(), ":+".parse().unwrap())],
+            options(),
+        )
+        .unwrap();
+
+        let manager = open().unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(
+            manager.state.operations,
+            vec![
+                "set DP-1-XRPEX-0-0 960x1080+1920+0 on DP-1",
+                "set DP-1-XRPEX-960-0 960x1080+2880+0 on DP-1",
+                "set DP-1-XRPEX-0-0-XRPEX-0-0 960x540+1920+0 on DP-1-XRPEX-0-0",
+                "set DP-1-XRPEX-0-0-XRPEX-0-540 960x540+1920+540 on DP-1-XRPEX-0-0",
+                "delete DP-1-XRPEX-0-0",
+            ]
+        );
+    }
+
+    #[test]
+    fn we_can_undo_to_labelled_layouts() {
+        let path = mock_state_file("undo");
+
+        let options = ApplyOptions::default;
+        let open = || MockMonitorManager::load(path.clone());
+        let mut history = XrpexHistory::default();
+
+        for rpex in ["8@left+8@right:9", "+:"] {
+            let targets = vec![("DP-1".to_string(), Rpex::from_str(rpex).unwrap())];
+
+            record_layouts(
+                &mut history,
+                &open().unwrap().get_monitors().unwrap(),
+                &targets,
+                &options(),
+            );
+            apply(open, targets, options()).unwrap();
+        }
+
+        undo_in(open, "DP-1".to_string(), options(), &mut history).unwrap();
+
+        let manager = open().unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(manager
+            .state
+            .monitors
+            .iter()
+            .any(|monitor| monitor.name == "DP-1-XRPEX-left"));
+        assert_eq!(history.applied["DP-1"].rpex, "8@left+8@right:9");
+        assert_eq!(history.monitors["DP-1"], vec![String::new()]);
+    }
+
+    #[test]
+    fn we_cannot_reset_monitors_for_expressions_that_fail() {
+        let path = mock_state_file("all-or-nothing");
+        let open = || MockMonitorManager::load(path.clone());
+        let options = ApplyOptions::default;
+
+        apply(
+            open,
+            vec![("DP-1".to_string(), "+:".parse().unwrap())],
+            options(),
+        )
+        .unwrap();
+
+        let result = apply(
+            open,
+            vec![("DP-1".to_string(), "2+1:1".parse().unwrap())],
+            options(),
+        );
+
+        let manager = open().unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let error = result.unwrap_err();
+        assert_eq!((error.code(), error.exit_code()), ("evaluation", 2));
+        assert_eq!(
+            manager.state.operations,
+            vec![
+                "set DP-1-XRPEX-0-0 960x1080+1920+0 on DP-1",
+                "set DP-1-XRPEX-960-0 960x1080+2880+0 on DP-1",
+            ]
+        );
+    }
+
+    #[test]
+    fn we_can_tell_why_each_monitor_failed() {
+        let monitor = |name: &str, lengths| RpexMonitor {
+            name: name.to_string(),
+            position: [0, 0],
+            resolution: HyperRectangle { lengths },
+            physical_size: [0, 0],
+        };
+        let monitors = [monitor("DP-1", [1920, 1080]), monitor("HDMI-1", [64, 64])];
+        let targets = [
+            ("DP-1".to_string(), Rpex::from_str("2+1:1").unwrap()),
+            ("HDMI-1".to_string(), Rpex::from_str("+:").unwrap()),
+        ];
+        let options = ApplyOptions {
+            gap: [100, 0],
+            ..Default::default()
+        };
+
+        let error = plan_targets(&monitors, &targets, &options).unwrap_err();
+
+        assert_eq!((error.code(), error.exit_code()), ("failed", 2));
+        assert_eq!(
+            error_json(&error)["monitors"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|failure| (failure["monitor"].as_str(), failure["code"].as_str()))
+                .collect::<Vec<_>>(),
+            vec![
+                (Some("DP-1"), Some("evaluation")),
+                (Some("HDMI-1"), Some("gap-too-large"))
+            ]
+        );
+    }
+
+    #[test]
+    fn we_cannot_tell_the_status_of_missing_monitors() {
+        let path = mock_state_file("status");
+        let mut manager = MockMonitorManager::load(path.clone()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let error = status(&mut manager, "NOPE", &NameTemplate::default()).unwrap_err();
+
+        assert_eq!((error.code(), error.exit_code()), ("no-monitor", 3));
+    }
+
+    #[test]
+    fn we_cannot_apply_expressions_to_missing_monitors() {
+        let path = mock_state_file("missing");
+        let open = || MockMonitorManager::load(path.clone());
+
+        let result = apply(
+            open,
+            vec![
+                ("DP-1".to_string(), "+:".parse().unwrap()),
+                ("HDMI-1".to_string(), "+:".parse().unwrap()),
+            ],
+            ApplyOptions::default(),
+        );
+
+        let manager = open().unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let error = result.unwrap_err();
+        assert!(matches!(&error, XrpexError::NoMonitor(name) if name == "HDMI-1"));
+        assert_eq!((error.code(), error.exit_code()), ("no-monitor", 3));
+        assert!(manager.state.operations.is_empty());
+    }
+
+    #[test]
+    fn we_cannot_split_monitors_overlapped_by_foreign_monitors() {
+        let path = mock_state_file("foreign");
+        let open = || MockMonitorManager::load(path.clone());
+        let options = |force| ApplyOptions {
+            force,
+            ..Default::default()
+        };
+
+        open()
+            .unwrap()
+            .set_virtual_monitors(&[VirtualMonitor {
+                name: "left".to_string(),
+                parent: "DP-1".to_string(),
+                position: [1920, 0],
+                resolution: HyperRectangle {
+                    lengths: [960, 1080],
+                },
+                physical_size: [300, 340],
+            }])
+            .unwrap();
+
+        let refused = apply(
+            open,
+            vec![("DP-1".to_string(), "+:".parse().unwrap())],
+            options(false),
+        );
+        let forced = apply(
+            open,
+            vec![("DP-1".to_string(), "+:".parse().unwrap())],
+            options(true),
+        );
+
+        let manager = open().unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(matches!(
+            refused,
+            Err(XrpexError::ForeignMonitor(parent, foreign)) if parent == "DP-1" && foreign == "left"
+        ));
+        assert!(forced.is_ok());
+        assert_eq!(manager.state.operations.len(), 3);
+    }
+
+    #[test]
+    fn we_can_keep_existing_virtual_monitors() {
+        let path = mock_state_file("keep-existing");
+        let options = |skip, keep_existing| ApplyOptions {
+            skip,
+            keep_existing,
+            ..Default::default()
+        };
+        let open = || MockMonitorManager::load(path.clone());
+        let target = |rpex: &str| vec![("DP-1".to_string(), rpex.parse().unwrap())];
+
+        apply(open, target("+:"), options(vec![1], false)).unwrap();
+        apply(open, target("+:"), options(vec![0], true)).unwrap();
+
+        let overlapping = apply(open, target("1+2:"), options(vec![0], true));
+        let duplicate = apply(open, target("+:"), options(vec![], true));
+
+        let manager = open().unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(matches!(
+            overlapping,
+            Err(XrpexError::OverlappingMonitor(name, existing))
+                if name == "DP-1-XRPEX-640-0" && existing == "DP-1-XRPEX-0-0"
+        ));
+        assert!(matches!(
+            duplicate,
+            Err(XrpexError::DuplicateName(name)) if name == "DP-1-XRPEX-0-0"
+        ));
+        assert_eq!(
+            manager
+                .state
+                .monitors
+                .iter()
+                .map(|monitor| monitor.name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["DP-1", "DP-1-XRPEX-0-0", "DP-1-XRPEX-960-0"]
+        );
+    }
+
+    #[test]
+    fn we_can_select_virtual_monitors_to_reset() {
+        let names = [
+            "DP-1",
+            "DP-1-XRPEX-top",
+            "DP-1-XRPEX-top-XRPEX-0-0",
+            "DP-1-XRPEX-bottom",
+            "HDMI-1",
+            "HDMI-1-XRPEX-top",
+        ]
+        .map(String::from)
+        .to_vec();
+        let template = NameTemplate::default();
+        let pattern = Pattern::new("DP-1-XRPEX-top*").unwrap();
+        let history = XrpexHistory::default();
+
+        assert_eq!(
+            names_to_reset(names.clone(), Some("DP-1"), None, &template, &history),
+            vec![
+                "DP-1-XRPEX-top",
+                "DP-1-XRPEX-top-XRPEX-0-0",
+                "DP-1-XRPEX-bottom"
+            ]
+        );
+        assert_eq!(
+            names_to_reset(names.clone(), None, Some(&pattern), &template, &history),
+            vec!["DP-1-XRPEX-top", "DP-1-XRPEX-top-XRPEX-0-0"]
+        );
+        assert_eq!(
+            names_to_reset(
+                names,
+                Some("HDMI-1"),
+                Some(&Pattern::new("*-top").unwrap()),
+                &template,
+                &history
+            ),
+            vec!["HDMI-1-XRPEX-top"]
+        );
+    }
+
+    #[test]
+    fn we_cannot_reset_physical_monitors_that_fit_the_name_template() {
+        let template = NameTemplate::from_str("{parent}-{index}").unwrap();
+        let names = ["DP-1", "DP-1-0", "DP-1-1", "HDMI-1"]
+            .map(String::from)
+            .to_vec();
+        let mut history = XrpexHistory::default();
+
+        assert_eq!(
+            names_to_reset(names.clone(), None, None, &template, &history),
+            vec!["DP-1-0", "DP-1-1"]
+        );
+        assert!(names_to_reset(names, Some("DP"), None, &template, &history).is_empty());
+
+        // X hides the output once virtual monitors cover it.
+        let names = ["DP-1-0", "DP-1-1", "HDMI-1"].map(String::from).to_vec();
+
+        assert!(names_to_reset(names.clone(), None, None, &template, &history).is_empty());
+
+        history.record_applied("DP-1", &Rpex::from_str("+:").unwrap(), [0, 0], &[]);
+
+        assert_eq!(
+            names_to_reset(names, None, None, &template, &history),
+            vec!["DP-1-0", "DP-1-1"]
+        );
+    }
+
+    #[test]
+    fn we_can_export_config() {
+        let monitor = |name: &str, position, lengths| RpexMonitor {
+            name: name.to_string(),
+            position,
+            resolution: HyperRectangle { lengths },
+            physical_size: [0, 0],
+        };
+        let monitors = [
+            monitor("DP-1", [0, 0], [1920, 1080]),
+            monitor("DP-1-XRPEX-main", [0, 0], [1280, 1080]),
+            monitor("DP-1-XRPEX-1280-0", [1280, 0], [640, 1080]),
+            monitor("HDMI-1", [1920, 0], [1920, 1080]),
+        ];
+
+        let contents = export_config(&monitors, None, &XrpexHistory::default()).unwrap();
+
+        assert_eq!(
+            contents,
+            "# DP-1 1920x1080+0+0\n\
+             #   DP-1-XRPEX-main 1280x1080+0+0\n\
+             #   DP-1-XRPEX-1280-0 640x1080+1280+0\n\
+             # HDMI-1 1920x1080+1920+0\n\
+             \n\
+             [monitors]\n\
+             DP-1 = \"32+16:27\"\n"
+        );
+
+        let config: XrpexConfig = toml::from_str(&contents).unwrap();
+        assert_eq!(config.monitors["DP-1"], "32+16:27".parse().unwrap());
+    }
+
+    #[test]
+    fn we_cannot_export_physical_monitors_that_fit_the_name_template() {
+        let monitor = |name: &str, position, lengths| RpexMonitor {
+            name: name.to_string(),
+            position,
+            resolution: HyperRectangle { lengths },
+            physical_size: [0, 0],
+        };
+        let monitors = [
+            monitor("DP-1", [0, 0], [1920, 1080]),
+            monitor("DP-1-0", [0, 0], [960, 1080]),
+            monitor("DP-1-1", [960, 0], [960, 1080]),
+            monitor("HDMI-1", [1920, 0], [1920, 1080]),
+        ];
+        let template = NameTemplate::from_str("{parent}-{index}").unwrap();
+
+        let targets = infer_targets(&monitors, &template, &XrpexHistory::default()).unwrap();
+
+        assert_eq!(
+            targets.into_iter().collect::<Vec<_>>(),
+            vec![("DP-1".to_string(), Rpex::from_str("8+8:9").unwrap())]
+        );
+    }
+
+    #[test]
+    fn we_can_tell_the_applied_expression_across_gaps() {
+        let monitor = RpexMonitor {
+            name: "DP-1".to_string(),
+            position: [0, 0],
+            resolution: HyperRectangle {
+                lengths: [1920, 1080],
+            },
+            physical_size: [0, 0],
+        };
+        let rpex = Rpex::from_str("8@left+8@right:9").unwrap();
+        let monitors = std::iter::once(monitor.clone())
+            .chain(
+                monitor
+                    .plan_virtual_monitors(
+                        rpex.clone(),
+                        &ApplyOptions {
+                            gap: [10, 0],
+                            ..Default::default()
+                        },
+                    )
+                    .unwrap()
+                    .iter()
+                    .map(RpexMonitor::from),
+            )
+            .collect::<Vec<_>>();
+        let name_template = NameTemplate::default();
+
+        let mut history = XrpexHistory::default();
+        assert!(current_rpex("DP-1", &monitors, &name_template, &history).is_err());
+
+        history.record_applied("DP-1", &rpex, [10, 0], &[]);
+        assert_eq!(
+            current_rpex("DP-1", &monitors, &name_template, &history).unwrap(),
+            rpex
+        );
+
+        // A layout changed since it was applied is inferred again.
+        history.record_applied("DP-1", &rpex, [20, 0], &[]);
+        assert!(current_rpex("DP-1", &monitors, &name_template, &history).is_err());
+    }
+}
@@ -0,0 +1,153 @@
+use std::env;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use rpex::HyperRectangle;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::monitor::{RpexMonitor, RpexMonitorManager, VirtualMonitor};
+
+#[derive(Error, Debug)]
+pub enum MockManagerError {
+    #[error("unable to access mock state {0}: {1}")]
+    Io(PathBuf, io::Error),
+    #[error("unable to parse mock state: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("unable to find parent monitor {0}")]
+    NoParent(String),
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct MockMonitor {
+    pub name: String,
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    #[serde(default)]
+    pub width_mm: u32,
+    #[serde(default)]
+    pub height_mm: u32,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct MockState {
+    pub monitors: Vec<MockMonitor>,
+    #[serde(default)]
+    pub operations: Vec<String>,
+}
+
+// With a path, the state is read from and written back to that file, so that scripts can run
+// xrpex several times against the same monitors and inspect the operations afterwards.
+pub struct MockMonitorManager {
+    path: Option<PathBuf>,
+    pub state: MockState,
+}
+
+impl MockMonitorManager {
+    pub fn new(monitors: Vec<MockMonitor>) -> MockMonitorManager {
+        MockMonitorManager {
+            path: None,
+            state: MockState {
+                monitors,
+                operations: vec![],
+            },
+        }
+    }
+
+    pub fn open() -> Result<MockMonitorManager, MockManagerError> {
+        match env::var_os("XRPEX_MOCK_STATE") {
+            Some(path) => MockMonitorManager::load(PathBuf::from(path)),
+            None => Ok(MockMonitorManager::new(vec![])),
+        }
+    }
+
+    pub fn load(path: PathBuf) -> Result<MockMonitorManager, MockManagerError> {
+        let contents =
+            fs::read_to_string(&path).map_err(|e| MockManagerError::Io(path.clone(), e))?;
+
+        Ok(MockMonitorManager {
+            state: serde_json::from_str(&contents)?,
+            path: Some(path),
+        })
+    }
+
+    fn save(&self) -> Result<(), MockManagerError> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+
+        let contents = serde_json::to_string_pretty(&self.state)?;
+
+        fs::write(path, contents).map_err(|e| MockManagerError::Io(path.clone(), e))
+    }
+}
+
+impl RpexMonitorManager for MockMonitorManager {
+    type ManagerError = MockManagerError;
+
+    fn get_monitors(&mut self) -> Result<Vec<RpexMonitor>, Self::ManagerError> {
+        Ok(self
+            .state
+            .monitors
+            .iter()
+            .map(|monitor| RpexMonitor {
+                name: monitor.name.clone(),
+                position: [monitor.x, monitor.y],
+                resolution: HyperRectangle {
+                    lengths: [monitor.width, monitor.height],
+                },
+                physical_size: [monitor.width_mm, monitor.height_mm],
+            })
+            .collect())
+    }
+
+    fn delete_monitors(&mut self, names: &[String]) -> Result<(), Self::ManagerError> {
+        for name in names {
+            if let Some(index) = self.state.monitors.iter().position(|m| &m.name == name) {
+                self.state.monitors.remove(index);
+                self.state.operations.push(format!("delete {name}"));
+            }
+        }
+
+        self.save()
+    }
+
+    fn set_virtual_monitors(
+        &mut self,
+        virtual_monitors: &[VirtualMonitor],
+    ) -> Result<(), Self::ManagerError> {
+        for VirtualMonitor {
+            name,
+            parent,
+            position: [x, y],
+            resolution,
+            physical_size: [width_mm, height_mm],
+        } in virtual_monitors
+        {
+            if !self.state.monitors.iter().any(|m| &m.name == parent) {
+                return Err(MockManagerError::NoParent(parent.clone()));
+            }
+
+            let [width, height] = resolution.lengths;
+
+            self.state.monitors.retain(|m| &m.name != name);
+            self.state.monitors.push(MockMonitor {
+                name: name.clone(),
+                x: *x,
+                y: *y,
+                width,
+                height,
+                width_mm: *width_mm,
+                height_mm: *height_mm,
+            });
+            self.state
+                .operations
+                .push(format!("set {name} {width}x{height}+{x}+{y} on {parent}"));
+        }
+
+        self.save()
+    }
+}
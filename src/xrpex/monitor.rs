@@ -0,0 +1,564 @@
+use itertools::Itertools;
+use rpex::Cell;
+use rpex::GeometryStyle;
+use rpex::HyperRectangle;
+use rpex::Layout;
+use rpex::Rpex;
+use serde_json::json;
+
+use crate::name_template::{NameTemplate, NameValues};
+use crate::persist::shell_quote;
+use crate::{ApplyOptions, XrpexError};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RpexMonitor {
+    pub name: String,
+    pub position: [i32; 2],
+    pub resolution: HyperRectangle<2>,
+    pub physical_size: [u32; 2],
+}
+
+impl RpexMonitor {
+    // Positions can be negative and ends can be past i32::MAX, so both are compared as i64.
+    pub fn overlaps(&self, position: [i32; 2], lengths: [u32; 2]) -> bool {
+        (0..2).all(|i| {
+            let [start, other_start] = [self.position[i], position[i]].map(i64::from);
+
+            start < other_start + i64::from(lengths[i])
+                && other_start < start + i64::from(self.resolution.lengths[i])
+        })
+    }
+
+    pub fn physical_size_of(&self, lengths: [u32; 2]) -> [u32; 2] {
+        [0, 1].map(|dim| {
+            let pixels = self.resolution.lengths[dim] as u64;
+            if pixels == 0 {
+                return 0;
+            }
+
+            let millimeters = self.physical_size[dim] as u64 * lengths[dim] as u64;
+            ((millimeters + pixels / 2) / pixels) as u32
+        })
+    }
+
+    // There are 25.4 millimetres to an inch.
+    pub fn physical_size_at_dpi(lengths: [u32; 2], dpi: u32) -> [u32; 2] {
+        lengths.map(|pixels| {
+            let dpi = dpi as u64;
+            ((pixels as u64 * 254 + dpi * 5) / (dpi * 10)) as u32
+        })
+    }
+
+    pub fn plan_virtual_monitors(
+        &self,
+        rpex: Rpex<2>,
+        options: &ApplyOptions,
+    ) -> Result<Vec<VirtualMonitor>, XrpexError> {
+        let layout = Layout::from(rpex.evaluate(self.resolution)?).with_gutter(options.gap);
+
+        let cells = layout
+            .iter_cells()
+            .zip(layout.iter_labels())
+            .map(
+                |(
+                    Cell {
+                        position,
+                        rectangle,
+                    },
+                    label,
+                )| (position, rectangle.lengths, label),
+            )
+            .collect::<Vec<_>>();
+
+        // The gap is taken out of the cells, so one as wide as a cell leaves nothing of it.
+        if cells.iter().any(|(_, lengths, _)| lengths.contains(&0)) {
+            return Err(XrpexError::GapTooLarge(options.gap, self.name.clone()));
+        }
+
+        if let Some(&index) = options.skip.iter().find(|&&index| index >= cells.len()) {
+            return Err(XrpexError::NoPartitionToSkip(
+                index,
+                self.name.clone(),
+                cells.len(),
+            ));
+        }
+
+        if (0..cells.len()).all(|index| options.skip.contains(&index)) {
+            return Err(XrpexError::SkippedAll(self.name.clone()));
+        }
+
+        let [columns, rows] = [0, 1].map(|dim| {
+            cells
+                .iter()
+                .map(|(position, _, _)| position[dim])
+                .sorted()
+                .dedup()
+                .collect::<Vec<_>>()
+        });
+
+        let parent_name = &self.name;
+
+        Ok(cells
+            .into_iter()
+            .enumerate()
+            .filter(|(index, _)| !options.skip.contains(index))
+            .map(|(index, (position, lengths, label))| {
+                let [x, y] = position;
+
+                let values = NameValues {
+                    index,
+                    position,
+                    label: label.as_deref(),
+                    col: columns.binary_search(&x).expect("x is one of the columns"),
+                    row: rows.binary_search(&y).expect("y is one of the rows"),
+                };
+
+                VirtualMonitor {
+                    name: options.name_template.render(parent_name, values),
+                    parent: parent_name.clone(),
+                    position: [
+                        self.position[0].saturating_add_unsigned(x),
+                        self.position[1].saturating_add_unsigned(y),
+                    ],
+                    resolution: HyperRectangle { lengths },
+                    physical_size: match options.dpi {
+                        Some(dpi) => RpexMonitor::physical_size_at_dpi(lengths, dpi),
+                        None => self.physical_size_of(lengths),
+                    },
+                }
+            })
+            .collect())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VirtualMonitor {
+    pub name: String,
+    pub parent: String,
+    pub position: [i32; 2],
+    pub resolution: HyperRectangle<2>,
+    pub physical_size: [u32; 2],
+}
+
+impl From<&VirtualMonitor> for RpexMonitor {
+    fn from(virtual_monitor: &VirtualMonitor) -> Self {
+        RpexMonitor {
+            name: virtual_monitor.name.clone(),
+            position: virtual_monitor.position,
+            resolution: virtual_monitor.resolution,
+            physical_size: virtual_monitor.physical_size,
+        }
+    }
+}
+
+pub fn virtual_monitors_json(virtual_monitors: &[VirtualMonitor]) -> String {
+    let virtual_monitors = virtual_monitors
+        .iter()
+        .map(
+            |VirtualMonitor {
+                 name,
+                 parent,
+                 position: [x, y],
+                 resolution,
+                 physical_size: [width_mm, height_mm],
+             }| {
+                let [width, height] = resolution.lengths;
+
+                json!({
+                    "name": name,
+                    "parent": parent,
+                    "x": x,
+                    "y": y,
+                    "width": width,
+                    "height": height,
+                    "width_mm": width_mm,
+                    "height_mm": height_mm,
+                })
+            },
+        )
+        .collect::<Vec<_>>();
+
+    serde_json::to_string(&virtual_monitors).expect("virtual monitors are plain data")
+}
+
+impl VirtualMonitor {
+    /// Like [`Cell::format_geometry`], but with a position that can be negative, which xrandr
+    /// takes as e.g. `+-1920+0`.
+    pub fn format_geometry(&self, style: GeometryStyle) -> String {
+        let [x, y] = self.position;
+        let [width, height] = self.resolution.lengths;
+
+        match style {
+            GeometryStyle::Xrandr => format!("{width}x{height}+{x}+{y}"),
+            GeometryStyle::XrandrPhysical([width_mm, height_mm]) => {
+                format!("{width}/{width_mm}x{height}/{height_mm}+{x}+{y}")
+            }
+        }
+    }
+}
+
+fn geometry(virtual_monitor: &VirtualMonitor) -> String {
+    virtual_monitor.format_geometry(GeometryStyle::Xrandr)
+}
+
+// Names are quoted, so the assignments are safe to `eval` whatever the monitors are called.
+pub fn virtual_monitors_shell(virtual_monitors: &[VirtualMonitor]) -> String {
+    virtual_monitors
+        .iter()
+        .enumerate()
+        .map(|(index, virtual_monitor)| {
+            format!(
+                "RPEX_{index}_NAME={}\nRPEX_{index}_PARENT={}\nRPEX_{index}_GEOM={}\n",
+                shell_quote(&virtual_monitor.name),
+                shell_quote(&virtual_monitor.parent),
+                geometry(virtual_monitor)
+            )
+        })
+        .chain(std::iter::once(format!(
+            "RPEX_COUNT={}\n",
+            virtual_monitors.len()
+        )))
+        .collect()
+}
+
+pub fn virtual_monitors_null(virtual_monitors: &[VirtualMonitor]) -> String {
+    virtual_monitors
+        .iter()
+        .map(|virtual_monitor| {
+            format!(
+                "{}\t{}\t{}\0",
+                virtual_monitor.name,
+                virtual_monitor.parent,
+                geometry(virtual_monitor)
+            )
+        })
+        .collect()
+}
+
+// A MetaMode may list each display only once, so it can't split one into several monitors. Each
+// monitor instead shows only the area its virtual monitors cover, through ViewPortOut, while
+// ViewPortIn places that area on the desktop at the same size. Monitors without virtual monitors
+// are listed as they are, since the driver turns off those that are left out.
+pub fn nvidia_meta_mode(monitors: &[RpexMonitor], virtual_monitors: &[VirtualMonitor]) -> String {
+    monitors
+        .iter()
+        .map(|monitor| {
+            let areas = virtual_monitors
+                .iter()
+                .filter(|VirtualMonitor { parent, .. }| parent == &monitor.name)
+                .map(|child| (child.position, child.resolution.lengths))
+                .collect::<Vec<_>>();
+            let areas = if areas.is_empty() {
+                vec![(monitor.position, monitor.resolution.lengths)]
+            } else {
+                areas
+            };
+
+            let start = [0, 1].map(|dim| {
+                areas
+                    .iter()
+                    .map(|(position, _)| position[dim])
+                    .min()
+                    .unwrap_or_default()
+            });
+            let end = [0, 1].map(|dim| {
+                areas
+                    .iter()
+                    .map(|(position, lengths)| position[dim].saturating_add_unsigned(lengths[dim]))
+                    .max()
+                    .unwrap_or_default()
+            });
+
+            let [x, y] = start;
+            let [width, height] = [0, 1].map(|dim| end[dim].abs_diff(start[dim]));
+            let name = &monitor.name;
+            let [mode_width, mode_height] = monitor.resolution.lengths;
+            let [offset_x, offset_y] = [x - monitor.position[0], y - monitor.position[1]];
+
+            format!(
+                "{name}: {mode_width}x{mode_height} +{x}+{y} \
+                 {{ViewPortIn={width}x{height}, \
+                 ViewPortOut={width}x{height}+{offset_x}+{offset_y}}}"
+            )
+        })
+        .join(", ")
+}
+
+pub trait RpexMonitorManager {
+    type ManagerError: std::error::Error;
+
+    fn get_monitors(&mut self) -> Result<Vec<RpexMonitor>, Self::ManagerError>;
+
+    fn reset_rpex_monitors(
+        &mut self,
+        parent_name: &str,
+        name_template: &NameTemplate,
+    ) -> Result<Vec<RpexMonitor>, Self::ManagerError> {
+        let descendants = self
+            .get_monitors()?
+            .into_iter()
+            .filter(|RpexMonitor { name, .. }| name_template.is_descendant(name, parent_name))
+            .collect::<Vec<_>>();
+
+        self.delete_monitors(
+            &descendants
+                .iter()
+                .map(|RpexMonitor { name, .. }| name.clone())
+                .collect::<Vec<_>>(),
+        )?;
+
+        Ok(descendants)
+    }
+
+    fn delete_monitors(&mut self, names: &[String]) -> Result<(), Self::ManagerError>;
+
+    fn set_virtual_monitors(
+        &mut self,
+        virtual_monitors: &[VirtualMonitor],
+    ) -> Result<(), Self::ManagerError>;
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    #[test]
+    fn we_can_split_physical_size_proportionally() {
+        let monitor = RpexMonitor {
+            name: "DP-1".to_string(),
+            position: [0, 0],
+            resolution: HyperRectangle {
+                lengths: [3840, 1280],
+            },
+            physical_size: [1200, 400],
+        };
+        let options = ApplyOptions::default();
+
+        let virtual_monitors = monitor
+            .plan_virtual_monitors(Rpex::from_str("1+2:1").unwrap(), &options)
+            .unwrap();
+
+        assert_eq!(
+            virtual_monitors
+                .iter()
+                .map(|VirtualMonitor { physical_size, .. }| *physical_size)
+                .collect::<Vec<_>>(),
+            vec![[400, 400], [800, 400]]
+        );
+    }
+
+    #[test]
+    fn we_can_split_monitors_left_of_the_origin() {
+        let monitor = RpexMonitor {
+            name: "HDMI-1".to_string(),
+            position: [-1920, 0],
+            resolution: HyperRectangle {
+                lengths: [1920, 1080],
+            },
+            physical_size: [0, 0],
+        };
+        let options = ApplyOptions::default();
+
+        let virtual_monitors = monitor
+            .plan_virtual_monitors(Rpex::from_str("+:").unwrap(), &options)
+            .unwrap();
+
+        assert_eq!(
+            virtual_monitors
+                .iter()
+                .map(|virtual_monitor| virtual_monitor.format_geometry(GeometryStyle::Xrandr))
+                .collect::<Vec<_>>(),
+            vec!["960x1080+-1920+0", "960x1080+-960+0"]
+        );
+        assert!(monitor.overlaps([-100, 0], [200, 200]));
+        assert!(!monitor.overlaps([0, 0], [200, 200]));
+    }
+
+    #[test]
+    fn we_can_skip_partitions() {
+        let monitor = RpexMonitor {
+            name: "DP-1".to_string(),
+            position: [0, 0],
+            resolution: HyperRectangle {
+                lengths: [1920, 1080],
+            },
+            physical_size: [600, 340],
+        };
+        let options = ApplyOptions {
+            name_template: NameTemplate::from_str("{parent}-{index}").unwrap(),
+            skip: vec![3],
+            ..Default::default()
+        };
+
+        let virtual_monitors = monitor
+            .plan_virtual_monitors(Rpex::from_str("16+16:9+9").unwrap(), &options)
+            .unwrap();
+
+        assert_eq!(
+            virtual_monitors
+                .iter()
+                .map(|VirtualMonitor { name, .. }| name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["DP-1-0", "DP-1-1", "DP-1-2"]
+        );
+
+        for (skip, code) in [(vec![4], "no-partition"), (vec![0, 1, 2, 3], "skipped-all")] {
+            let error = monitor
+                .plan_virtual_monitors(
+                    Rpex::from_str("16+16:9+9").unwrap(),
+                    &ApplyOptions {
+                        skip,
+                        ..Default::default()
+                    },
+                )
+                .unwrap_err();
+
+            assert_eq!((error.code(), error.exit_code()), (code, 2));
+        }
+    }
+
+    #[test]
+    fn we_can_name_virtual_monitors_by_label() {
+        let monitor = RpexMonitor {
+            name: "DP-1".to_string(),
+            position: [0, 0],
+            resolution: HyperRectangle {
+                lengths: [3000, 1000],
+            },
+            physical_size: [900, 300],
+        };
+        let options = ApplyOptions::default();
+
+        let names = monitor
+            .plan_virtual_monitors(Rpex::from_str("2@main+1@side:1").unwrap(), &options)
+            .unwrap()
+            .into_iter()
+            .map(|VirtualMonitor { name, .. }| name)
+            .collect::<Vec<_>>();
+
+        assert_eq!(names, vec!["DP-1-XRPEX-main", "DP-1-XRPEX-side"]);
+
+        let template = NameTemplate::default();
+        assert!(template.matches("DP-1-XRPEX-main", "DP-1"));
+        assert!(template.matches("DP-1-XRPEX-0-0", "DP-1"));
+        assert!(!template.matches("DP-1-XRPEX-main-side", "DP-1"));
+        assert_eq!(
+            template.parent_of("DP-1-XRPEX-main-XRPEX-0-0"),
+            Some("DP-1-XRPEX-main")
+        );
+    }
+
+    #[test]
+    fn we_can_build_nvidia_meta_mode() {
+        let monitor = |name: &str, position, lengths| RpexMonitor {
+            name: name.to_string(),
+            position,
+            resolution: HyperRectangle { lengths },
+            physical_size: [0, 0],
+        };
+        let monitors = [
+            monitor("HDMI-1", [0, 0], [1920, 1080]),
+            monitor("DP-1", [1920, 0], [3840, 1080]),
+        ];
+        let options = ApplyOptions {
+            skip: vec![0, 2],
+            ..Default::default()
+        };
+
+        let virtual_monitors = monitors[1]
+            .plan_virtual_monitors(Rpex::from_str("8+16+8:9").unwrap(), &options)
+            .unwrap();
+
+        // As documented for MetaModes in the NVIDIA driver README, every display appears once.
+        assert_eq!(
+            nvidia_meta_mode(&monitors, &virtual_monitors),
+            "HDMI-1: 1920x1080 +0+0 {ViewPortIn=1920x1080, ViewPortOut=1920x1080+0+0}, \
+             DP-1: 3840x1080 +2880+0 {ViewPortIn=1920x1080, ViewPortOut=1920x1080+960+0}"
+        );
+    }
+
+    #[test]
+    fn we_can_print_virtual_monitors_for_shells() {
+        let virtual_monitor = |name: &str, position| VirtualMonitor {
+            name: name.to_string(),
+            parent: "DP-1".to_string(),
+            position,
+            resolution: HyperRectangle {
+                lengths: [960, 1080],
+            },
+            physical_size: [300, 340],
+        };
+        let virtual_monitors = [
+            virtual_monitor("DP-1-XRPEX-main", [0, 0]),
+            virtual_monitor("DP-1 side's", [960, 0]),
+        ];
+
+        assert_eq!(
+            virtual_monitors_shell(&virtual_monitors),
+            "RPEX_0_NAME=DP-1-XRPEX-main\nRPEX_0_PARENT=DP-1\nRPEX_0_GEOM=960x1080+0+0\n\
+             RPEX_1_NAME='DP-1 side'\\''s'\nRPEX_1_PARENT=DP-1\nRPEX_1_GEOM=960x1080+960+0\n\
+             RPEX_COUNT=2\n"
+        );
+        assert_eq!(
+            virtual_monitors_null(&virtual_monitors),
+            "DP-1-XRPEX-main\tDP-1\t960x1080+0+0\0DP-1 side's\tDP-1\t960x1080+960+0\0"
+        );
+    }
+
+    #[test]
+    fn we_cannot_leave_virtual_monitors_without_pixels() {
+        let monitor = RpexMonitor {
+            name: "DP-1".to_string(),
+            position: [0, 0],
+            resolution: HyperRectangle {
+                lengths: [1920, 1080],
+            },
+            physical_size: [0, 0],
+        };
+        let plan = |gap| {
+            monitor.plan_virtual_monitors(
+                Rpex::from_str("+:").unwrap(),
+                &ApplyOptions {
+                    gap,
+                    ..Default::default()
+                },
+            )
+        };
+
+        assert!(plan([1918, 0]).is_ok());
+        assert!(matches!(
+            plan([2000, 0]),
+            Err(XrpexError::GapTooLarge([2000, 0], name)) if name == "DP-1"
+        ));
+    }
+
+    #[test]
+    fn we_can_size_virtual_monitors_by_dpi() {
+        let monitor = RpexMonitor {
+            name: "DP-1".to_string(),
+            position: [0, 0],
+            resolution: HyperRectangle {
+                lengths: [3840, 1280],
+            },
+            physical_size: [0, 0],
+        };
+        let options = ApplyOptions {
+            dpi: Some(96),
+            ..Default::default()
+        };
+
+        let virtual_monitors = monitor
+            .plan_virtual_monitors(Rpex::from_str("1+2:1").unwrap(), &options)
+            .unwrap();
+
+        assert_eq!(
+            virtual_monitors
+                .iter()
+                .map(|VirtualMonitor { physical_size, .. }| *physical_size)
+                .collect::<Vec<_>>(),
+            vec![[339, 339], [677, 339]]
+        );
+    }
+}
@@ -0,0 +1,241 @@
+use std::collections::HashMap;
+
+use rpex::HyperRectangle;
+use serde::Deserialize;
+use thiserror::Error;
+use tracing::debug;
+use zbus::blocking::Connection;
+use zbus::zvariant::{OwnedValue, Type};
+
+use crate::monitor::{RpexMonitor, RpexMonitorManager, VirtualMonitor};
+
+const DISPLAY_CONFIG: &str = "org.gnome.Mutter.DisplayConfig";
+const DISPLAY_CONFIG_PATH: &str = "/org/gnome/Mutter/DisplayConfig";
+
+// Logical monitors are laid out in logical pixels, which are physical pixels divided by scale.
+const LAYOUT_MODE_LOGICAL: u32 = 1;
+
+#[derive(Error, Debug)]
+pub enum MutterManagerError {
+    #[error("unable to talk to mutter over d-bus: {0}")]
+    DBus(#[from] zbus::Error),
+    #[error("mutter cannot split monitor {0} into virtual monitors")]
+    Unsupported(String),
+}
+
+#[derive(Clone, Debug, Deserialize, Type)]
+struct MonitorSpec {
+    connector: String,
+    _vendor: String,
+    _product: String,
+    _serial: String,
+}
+
+#[derive(Clone, Debug, Deserialize, Type)]
+struct MonitorMode {
+    _id: String,
+    width: i32,
+    height: i32,
+    _refresh_rate: f64,
+    _preferred_scale: f64,
+    _supported_scales: Vec<f64>,
+    properties: HashMap<String, OwnedValue>,
+}
+
+#[derive(Clone, Debug, Deserialize, Type)]
+struct Monitor {
+    spec: MonitorSpec,
+    modes: Vec<MonitorMode>,
+    properties: HashMap<String, OwnedValue>,
+}
+
+#[derive(Clone, Debug, Deserialize, Type)]
+struct LogicalMonitor {
+    x: i32,
+    y: i32,
+    scale: f64,
+    transform: u32,
+    _primary: bool,
+    monitors: Vec<MonitorSpec>,
+    _properties: HashMap<String, OwnedValue>,
+}
+
+type CurrentState = (
+    u32,
+    Vec<Monitor>,
+    Vec<LogicalMonitor>,
+    HashMap<String, OwnedValue>,
+);
+
+pub struct MutterManager {
+    connection: Connection,
+}
+
+impl MutterManager {
+    pub fn open() -> Result<MutterManager, MutterManagerError> {
+        Ok(MutterManager {
+            connection: Connection::session()?,
+        })
+    }
+
+    fn get_current_state(&self) -> Result<CurrentState, MutterManagerError> {
+        debug!("calling {DISPLAY_CONFIG}.GetCurrentState");
+
+        let reply = self.connection.call_method(
+            Some(DISPLAY_CONFIG),
+            DISPLAY_CONFIG_PATH,
+            Some(DISPLAY_CONFIG),
+            "GetCurrentState",
+            &(),
+        )?;
+
+        Ok(reply.body().deserialize()?)
+    }
+}
+
+fn property<T: TryFrom<OwnedValue>>(
+    properties: &HashMap<String, OwnedValue>,
+    name: &str,
+) -> Option<T> {
+    properties
+        .get(name)
+        .and_then(|value| value.try_clone().ok())
+        .and_then(|value| T::try_from(value).ok())
+}
+
+// Each logical monitor is named after the connector of its first monitor, since mirrored monitors
+// share a logical monitor.
+fn rpex_monitors_from_state(
+    (_, monitors, logical_monitors, properties): CurrentState,
+) -> Vec<RpexMonitor> {
+    let logical_layout = property::<u32>(&properties, "layout-mode") == Some(LAYOUT_MODE_LOGICAL);
+
+    logical_monitors
+        .into_iter()
+        .filter_map(|logical_monitor| {
+            let spec = logical_monitor.monitors.first()?;
+            let monitor = monitors
+                .iter()
+                .find(|monitor| monitor.spec.connector == spec.connector)?;
+            let mode = monitor
+                .modes
+                .iter()
+                .find(|mode| property::<bool>(&mode.properties, "is-current") == Some(true))?;
+
+            let scale = if logical_layout {
+                logical_monitor.scale
+            } else {
+                1.0
+            };
+            let mut lengths =
+                [mode.width, mode.height].map(|length| (length as f64 / scale).round() as u32);
+            let mut physical_size = ["width-mm", "height-mm"].map(|name| {
+                property::<i32>(&monitor.properties, name)
+                    .unwrap_or(0)
+                    .max(0) as u32
+            });
+
+            // Odd transforms rotate the monitor by 90 or 270 degrees.
+            if logical_monitor.transform % 2 == 1 {
+                lengths.reverse();
+                physical_size.reverse();
+            }
+
+            Some(RpexMonitor {
+                name: spec.connector.clone(),
+                position: [logical_monitor.x, logical_monitor.y],
+                resolution: HyperRectangle { lengths },
+                physical_size,
+            })
+        })
+        .collect()
+}
+
+impl RpexMonitorManager for MutterManager {
+    type ManagerError = MutterManagerError;
+
+    fn get_monitors(&mut self) -> Result<Vec<RpexMonitor>, Self::ManagerError> {
+        Ok(rpex_monitors_from_state(self.get_current_state()?))
+    }
+
+    // Logical monitors always cover whole monitors, and ApplyMonitorsConfig rejects two of them on
+    // the same monitor, which is why the registry marks mutter read-only.
+    fn delete_monitors(&mut self, names: &[String]) -> Result<(), Self::ManagerError> {
+        match names.first() {
+            Some(name) => Err(MutterManagerError::Unsupported(name.clone())),
+            None => Ok(()),
+        }
+    }
+
+    fn set_virtual_monitors(
+        &mut self,
+        virtual_monitors: &[VirtualMonitor],
+    ) -> Result<(), Self::ManagerError> {
+        match virtual_monitors.first() {
+            Some(VirtualMonitor { parent, .. }) => {
+                Err(MutterManagerError::Unsupported(parent.clone()))
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spec(connector: &str) -> MonitorSpec {
+        MonitorSpec {
+            connector: connector.to_string(),
+            _vendor: "GSM".to_string(),
+            _product: "LG HDR 4K".to_string(),
+            _serial: "0x0000".to_string(),
+        }
+    }
+
+    #[test]
+    fn we_can_read_mutter_logical_monitors() {
+        let mode = |width, height, current: bool| MonitorMode {
+            _id: format!("{width}x{height}@60"),
+            width,
+            height,
+            _refresh_rate: 60.0,
+            _preferred_scale: 1.0,
+            _supported_scales: vec![1.0, 2.0],
+            properties: HashMap::from([("is-current".to_string(), OwnedValue::from(current))]),
+        };
+        let monitors = vec![Monitor {
+            spec: spec("DP-1"),
+            modes: vec![mode(1920, 1080, false), mode(3840, 2160, true)],
+            properties: HashMap::from([
+                ("width-mm".to_string(), OwnedValue::from(600i32)),
+                ("height-mm".to_string(), OwnedValue::from(340i32)),
+            ]),
+        }];
+        let logical_monitors = vec![LogicalMonitor {
+            x: 0,
+            y: 0,
+            scale: 2.0,
+            transform: 1,
+            _primary: true,
+            monitors: vec![spec("DP-1")],
+            _properties: HashMap::new(),
+        }];
+        let properties = HashMap::from([(
+            "layout-mode".to_string(),
+            OwnedValue::from(LAYOUT_MODE_LOGICAL),
+        )]);
+
+        assert_eq!(
+            rpex_monitors_from_state((0, monitors, logical_monitors, properties)),
+            vec![RpexMonitor {
+                name: "DP-1".to_string(),
+                position: [0, 0],
+                resolution: HyperRectangle {
+                    lengths: [1080, 1920]
+                },
+                physical_size: [340, 600],
+            }]
+        );
+    }
+}
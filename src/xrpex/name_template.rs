@@ -0,0 +1,285 @@
+use std::fmt::Display;
+use std::str::FromStr;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use thiserror::Error;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum NameSegment {
+    Literal(String),
+    Parent,
+    Index,
+    X,
+    Y,
+    Row,
+    Col,
+    Label,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NameTemplate {
+    segments: Vec<NameSegment>,
+}
+
+#[derive(Error, Debug)]
+pub enum NameTemplateError {
+    #[error("unknown placeholder {{{0}}}")]
+    UnknownPlaceholder(String),
+    #[error("unclosed placeholder")]
+    Unclosed,
+    #[error("template must contain {{parent}}")]
+    NoParent,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct NameValues<'a> {
+    pub index: usize,
+    pub position: [u32; 2],
+    pub row: usize,
+    pub col: usize,
+    pub label: Option<&'a str>,
+}
+
+impl Default for NameTemplate {
+    fn default() -> Self {
+        NameTemplate::from_str("{parent}-XRPEX-{label}").expect("default template is valid")
+    }
+}
+
+impl FromStr for NameTemplate {
+    type Err = NameTemplateError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut segments = vec![];
+        let mut rest = s;
+
+        while let Some(start) = rest.find('{') {
+            let (literal, placeholder) = rest.split_at(start);
+            let end = placeholder.find('}').ok_or(NameTemplateError::Unclosed)?;
+
+            if !literal.is_empty() {
+                segments.push(NameSegment::Literal(literal.to_string()));
+            }
+
+            segments.push(match &placeholder[1..end] {
+                "parent" => NameSegment::Parent,
+                "index" => NameSegment::Index,
+                "x" => NameSegment::X,
+                "y" => NameSegment::Y,
+                "row" => NameSegment::Row,
+                "col" => NameSegment::Col,
+                "label" => NameSegment::Label,
+                unknown => return Err(NameTemplateError::UnknownPlaceholder(unknown.to_string())),
+            });
+
+            rest = &placeholder[end + 1..];
+        }
+
+        if !rest.is_empty() {
+            segments.push(NameSegment::Literal(rest.to_string()));
+        }
+
+        if !segments.contains(&NameSegment::Parent) {
+            return Err(NameTemplateError::NoParent);
+        }
+
+        Ok(NameTemplate { segments })
+    }
+}
+
+impl Display for NameTemplate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for segment in &self.segments {
+            match segment {
+                NameSegment::Literal(literal) => f.write_str(literal)?,
+                NameSegment::Parent => f.write_str("{parent}")?,
+                NameSegment::Index => f.write_str("{index}")?,
+                NameSegment::X => f.write_str("{x}")?,
+                NameSegment::Y => f.write_str("{y}")?,
+                NameSegment::Row => f.write_str("{row}")?,
+                NameSegment::Col => f.write_str("{col}")?,
+                NameSegment::Label => f.write_str("{label}")?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl<'de> Deserialize<'de> for NameTemplate {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+impl Serialize for NameTemplate {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(feature = "schemars")]
+impl schemars::JsonSchema for NameTemplate {
+    fn schema_name() -> String {
+        "NameTemplate".to_string()
+    }
+
+    fn json_schema(_: &mut schemars::gen::SchemaGenerator) -> schemars::schema::Schema {
+        schemars::schema::SchemaObject {
+            instance_type: Some(schemars::schema::InstanceType::String.into()),
+            metadata: Some(Box::new(schemars::schema::Metadata {
+                description: Some(
+                    "Name of virtual monitors, with {parent}, {index}, {x}, {y}, {row}, {col} \
+                     and {label} placeholders"
+                        .to_string(),
+                ),
+                ..Default::default()
+            })),
+            ..Default::default()
+        }
+        .into()
+    }
+}
+
+impl NameTemplate {
+    pub fn render(&self, parent_name: &str, values: NameValues) -> String {
+        self.segments
+            .iter()
+            .map(|segment| match segment {
+                NameSegment::Literal(literal) => literal.clone(),
+                NameSegment::Parent => parent_name.to_string(),
+                NameSegment::Index => values.index.to_string(),
+                NameSegment::X => values.position[0].to_string(),
+                NameSegment::Y => values.position[1].to_string(),
+                NameSegment::Row => values.row.to_string(),
+                NameSegment::Col => values.col.to_string(),
+                NameSegment::Label => match values.label {
+                    Some(label) => label.to_string(),
+                    None => format!("{}-{}", values.position[0], values.position[1]),
+                },
+            })
+            .collect()
+    }
+
+    pub fn matches(&self, name: &str, parent_name: &str) -> bool {
+        match_name_segments(&self.segments, name, Some(parent_name)).is_some()
+    }
+
+    pub fn parent_of<'a>(&self, name: &'a str) -> Option<&'a str> {
+        match_name_segments(&self.segments, name, None).flatten()
+    }
+
+    pub fn ancestors_of<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        std::iter::successors(self.parent_of(name), |name| self.parent_of(name))
+    }
+
+    pub fn is_descendant(&self, name: &str, ancestor_name: &str) -> bool {
+        self.ancestors_of(name).any(|name| name == ancestor_name)
+    }
+}
+
+// Numeric placeholders match one or more digits, labels match a label or two numbers joined by
+// `-`, and an unknown parent matches any non-empty text. Returns the parent matched by the name,
+// if the name matches the segments at all.
+fn match_name_segments<'a>(
+    segments: &[NameSegment],
+    name: &'a str,
+    parent_name: Option<&'a str>,
+) -> Option<Option<&'a str>> {
+    let Some((segment, rest)) = segments.split_first() else {
+        return name.is_empty().then_some(parent_name);
+    };
+
+    match (segment, parent_name) {
+        (NameSegment::Literal(literal), _) => name
+            .strip_prefix(literal.as_str())
+            .and_then(|name| match_name_segments(rest, name, parent_name)),
+        (NameSegment::Parent, Some(parent)) => name
+            .strip_prefix(parent)
+            .and_then(|name| match_name_segments(rest, name, parent_name)),
+        (NameSegment::Parent, None) => (1..=name.len())
+            .filter(|&end| name.is_char_boundary(end))
+            .find_map(|end| match_name_segments(rest, &name[end..], Some(&name[..end]))),
+        (NameSegment::Label, _) => (1..=name.len())
+            .filter(|&end| name.is_char_boundary(end) && is_label_or_position(&name[..end]))
+            .find_map(|end| match_name_segments(rest, &name[end..], parent_name)),
+        _ => {
+            let digits = name.len() - name.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+
+            (1..=digits).find_map(|end| match_name_segments(rest, &name[end..], parent_name))
+        }
+    }
+}
+
+fn is_label_or_position(text: &str) -> bool {
+    let is_number = |text: &str| !text.is_empty() && text.chars().all(|c| c.is_ascii_digit());
+
+    text.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        || text
+            .split_once('-')
+            .is_some_and(|(x, y)| is_number(x) && is_number(y))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn values(index: usize, position: [u32; 2], row: usize, col: usize) -> NameValues<'static> {
+        NameValues {
+            index,
+            position,
+            row,
+            col,
+            label: None,
+        }
+    }
+
+    #[test]
+    fn we_can_render_name_template() {
+        assert_eq!(
+            NameTemplate::default().render("DP-1", values(1, [960, 0], 0, 1)),
+            "DP-1-XRPEX-960-0"
+        );
+        assert_eq!(
+            NameTemplate::from_str("{parent}/{row}.{col}#{index}")
+                .unwrap()
+                .render("HDMI-1", values(3, [960, 540], 1, 1)),
+            "HDMI-1/1.1#3"
+        );
+    }
+
+    #[test]
+    fn we_cannot_parse_bad_name_template() {
+        assert!(NameTemplate::from_str("{index}").is_err());
+        assert!(NameTemplate::from_str("{parent}-{label").is_err());
+        assert!(NameTemplate::from_str("{parent}-{size}").is_err());
+    }
+
+    #[test]
+    fn we_can_match_names_against_template() {
+        let template = NameTemplate::from_str("{parent}-{index}").unwrap();
+
+        assert!(template.matches("DP-1-0", "DP-1"));
+        assert!(template.matches("DP-1-12", "DP-1"));
+        assert!(!template.matches("DP-1-side", "DP-1"));
+        assert!(!template.matches("DP-10", "DP-1"));
+        assert!(!template.matches("DP-1-0", "DP-2"));
+
+        assert_eq!(template.parent_of("DP-1-0"), Some("DP-1"));
+        assert_eq!(template.parent_of("DP-1"), Some("DP"));
+        assert_eq!(template.parent_of("DP"), None);
+    }
+
+    #[test]
+    fn we_can_find_nested_virtual_monitors() {
+        let template = NameTemplate::default();
+
+        assert!(template.is_descendant("DP-1-XRPEX-0-0", "DP-1"));
+        assert!(template.is_descendant("DP-1-XRPEX-0-0-XRPEX-960-0", "DP-1"));
+        assert!(template.is_descendant("DP-1-XRPEX-0-0-XRPEX-960-0", "DP-1-XRPEX-0-0"));
+        assert!(!template.is_descendant("DP-1-XRPEX-0-0", "DP-1-XRPEX-0-0"));
+        assert!(!template.is_descendant("DP-1-XRPEX-0-0", "DP-2"));
+    }
+}
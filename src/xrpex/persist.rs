@@ -0,0 +1,328 @@
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use clap::ValueEnum;
+use rpex::GeometryStyle;
+use rpex::Rpex;
+use thiserror::Error;
+
+use crate::monitor::VirtualMonitor;
+use crate::name_template::NameTemplate;
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum PersistFormat {
+    /// Write an XDG autostart entry that runs xrpex at login
+    #[default]
+    Desktop,
+    /// Print a shell script to run from a session startup file such as ~/.xprofile
+    Shell,
+}
+
+#[derive(Error, Debug)]
+pub enum PersistError {
+    #[error("unable to locate config directory")]
+    NoConfigDir,
+    #[error("unable to write {0}: {1}")]
+    Io(PathBuf, io::Error),
+    #[error("unable to determine the user name, is USER set?")]
+    NoUser,
+}
+
+pub fn xrpex_command(
+    program: &str,
+    targets: &BTreeMap<String, Rpex<2>>,
+    name_template: Option<&NameTemplate>,
+) -> Vec<String> {
+    let mut command = vec![program.to_string()];
+
+    if let Some(name_template) = name_template {
+        command.extend(["--name-template".to_string(), name_template.to_string()]);
+    }
+
+    command.extend(
+        targets
+            .iter()
+            .map(|(parent_name, rpex)| format!("{parent_name}={rpex}")),
+    );
+
+    command
+}
+
+pub fn shell_quote(arg: &str) -> String {
+    let is_plain = !arg.is_empty()
+        && arg
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "_@%+=:,./-".contains(c));
+
+    if is_plain {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', r"'\''"))
+    }
+}
+
+// Exec keys have their own quoting rules, and `%` introduces field codes.
+fn desktop_quote(arg: &str) -> String {
+    let arg = arg.replace('%', "%%");
+
+    if arg.chars().any(|c| " \t\n\"'\\><~|&;$*?#()`".contains(c)) {
+        let escaped = arg
+            .chars()
+            .flat_map(|c| match c {
+                '"' | '`' | '$' | '\\' => vec!['\\', c],
+                c => vec![c],
+            })
+            .collect::<String>();
+
+        format!("\"{escaped}\"")
+    } else {
+        arg
+    }
+}
+
+pub fn shell_script(command: &[String]) -> String {
+    let command = command
+        .iter()
+        .map(|arg| shell_quote(arg))
+        .collect::<Vec<_>>();
+
+    format!("#!/bin/sh\nexec {}\n", command.join(" "))
+}
+
+pub fn desktop_entry(command: &[String]) -> String {
+    let command = command
+        .iter()
+        .map(|arg| desktop_quote(arg))
+        .collect::<Vec<_>>();
+
+    format!(
+        "[Desktop Entry]\n\
+         Type=Application\n\
+         Name=xrpex\n\
+         Comment=Restore virtual monitors\n\
+         Exec={}\n\
+         NoDisplay=true\n",
+        command.join(" ")
+    )
+}
+
+// Virtual monitors are set on the output at the root of their ancestors, since a split virtual
+// monitor has no output of its own. RandR replaces monitors of the same name, so the script can be
+// run again.
+pub fn xrandr_script(
+    virtual_monitors: &[VirtualMonitor],
+    split: &[String],
+    name_template: &NameTemplate,
+) -> String {
+    let mut script = "#!/bin/sh\n\
+                      # Virtual monitors generated by xrpex, this script can be run repeatedly\n\
+                      set -e\n"
+        .to_string();
+
+    if !split.is_empty() {
+        script.push_str("\n# Split into the virtual monitors below\n");
+
+        for name in split {
+            script.push_str(&format!(
+                "xrandr --delmonitor {} 2>/dev/null || true\n",
+                shell_quote(name)
+            ));
+        }
+    }
+
+    let mut current_output = None;
+
+    for virtual_monitor in virtual_monitors {
+        let VirtualMonitor {
+            name,
+            parent,
+            physical_size,
+            ..
+        } = virtual_monitor;
+        let output = name_template.ancestors_of(parent).last().unwrap_or(parent);
+        let geometry =
+            virtual_monitor.format_geometry(GeometryStyle::XrandrPhysical(*physical_size));
+
+        if current_output != Some(output) {
+            script.push_str(&format!("\n# {output}\n"));
+            current_output = Some(output);
+        }
+
+        script.push_str(&format!(
+            "xrandr --setmonitor {} {geometry} {}\n",
+            shell_quote(name),
+            shell_quote(output)
+        ));
+    }
+
+    script
+}
+
+// Unit files expand `%` specifiers and `$` variables in ExecStart, and split it on whitespace
+// outside of quotes.
+fn systemd_quote(arg: &str) -> String {
+    let arg = arg.replace('%', "%%").replace('$', "$$");
+
+    if arg.is_empty()
+        || arg
+            .chars()
+            .any(|c| c.is_whitespace() || "\"'\\;".contains(c))
+    {
+        format!("\"{}\"", arg.replace('\\', r"\\").replace('"', "\\\""))
+    } else {
+        arg
+    }
+}
+
+// The unit is started with the graphical session, which is also when desktop environments import
+// DISPLAY or WAYLAND_DISPLAY into the user manager.
+pub fn systemd_unit(command: &[String]) -> String {
+    let command = command
+        .iter()
+        .map(|arg| systemd_quote(arg))
+        .collect::<Vec<_>>();
+
+    format!(
+        "[Unit]\n\
+         Description=Restore virtual monitors\n\
+         PartOf=graphical-session.target\n\
+         After=graphical-session.target\n\
+         \n\
+         [Service]\n\
+         Type=oneshot\n\
+         ExecStart={}\n\
+         \n\
+         [Install]\n\
+         WantedBy=graphical-session.target\n",
+        command.join(" ")
+    )
+}
+
+// udev runs as root, so it asks the user's manager to apply again whenever a monitor is plugged
+// in or unplugged. --no-block keeps udev from waiting on xrpex.
+pub fn udev_rule(user: &str) -> String {
+    format!(
+        "# Save as /etc/udev/rules.d/90-xrpex.rules and run `udevadm control --reload`\n\
+         ACTION==\"change\", SUBSYSTEM==\"drm\", \
+         RUN+=\"/usr/bin/systemctl --no-block --user --machine={user}@ restart xrpex.service\"\n"
+    )
+}
+
+pub fn current_user() -> Result<String, PersistError> {
+    env::var("USER")
+        .ok()
+        .filter(|user| !user.is_empty())
+        .ok_or(PersistError::NoUser)
+}
+
+pub fn write_systemd_unit(contents: &str) -> Result<PathBuf, PersistError> {
+    let dir = dirs::config_dir()
+        .ok_or(PersistError::NoConfigDir)?
+        .join("systemd")
+        .join("user");
+    let path = dir.join("xrpex.service");
+
+    fs::create_dir_all(&dir).map_err(|e| PersistError::Io(dir, e))?;
+    fs::write(&path, contents).map_err(|e| PersistError::Io(path.clone(), e))?;
+
+    Ok(path)
+}
+
+pub fn write_autostart_entry(contents: &str) -> Result<PathBuf, PersistError> {
+    let dir = dirs::config_dir()
+        .ok_or(PersistError::NoConfigDir)?
+        .join("autostart");
+    let path = dir.join("xrpex.desktop");
+
+    fs::create_dir_all(&dir).map_err(|e| PersistError::Io(dir, e))?;
+    fs::write(&path, contents).map_err(|e| PersistError::Io(path.clone(), e))?;
+
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::str::FromStr;
+
+    #[test]
+    fn we_can_build_autostart_commands() {
+        let targets = BTreeMap::from([
+            (
+                "DP-1".to_string(),
+                Rpex::from_str("2@main+1@side:1").unwrap(),
+            ),
+            ("HDMI 1".to_string(), Rpex::from_str("+:").unwrap()),
+        ]);
+        let template = NameTemplate::from_str("{parent}/{index}").unwrap();
+
+        let command = xrpex_command("/usr/bin/xrpex", &targets, Some(&template));
+
+        assert_eq!(
+            shell_script(&command),
+            "#!/bin/sh\nexec /usr/bin/xrpex --name-template '{parent}/{index}' \
+             DP-1=2@main+1@side:1 'HDMI 1=+:'\n"
+        );
+        assert!(desktop_entry(&command).contains(
+            "\nExec=/usr/bin/xrpex --name-template {parent}/{index} \
+             DP-1=2@main+1@side:1 \"HDMI 1=+:\"\n"
+        ));
+    }
+
+    #[test]
+    fn we_can_build_systemd_units() {
+        let command = [
+            "/home/me/bin/xrpex",
+            "--config",
+            "/home/me/my monitors/50%.toml",
+        ]
+        .map(str::to_string);
+
+        assert!(systemd_unit(&command).contains(
+            "\nExecStart=/home/me/bin/xrpex --config \"/home/me/my monitors/50%%.toml\"\n"
+        ));
+        assert!(udev_rule("me").contains("--machine=me@ restart xrpex.service"));
+    }
+
+    #[test]
+    fn we_can_build_xrandr_scripts() {
+        let virtual_monitor = |name: &str, parent: &str, position, lengths| VirtualMonitor {
+            name: name.to_string(),
+            parent: parent.to_string(),
+            position,
+            resolution: rpex::HyperRectangle { lengths },
+            physical_size: [300, 340],
+        };
+        let virtual_monitors = [
+            virtual_monitor("DP-1-XRPEX-0-0", "DP-1", [0, 0], [960, 1080]),
+            virtual_monitor(
+                "DP-1-XRPEX-960-0-XRPEX-0-0",
+                "DP-1-XRPEX-960-0",
+                [960, 0],
+                [960, 540],
+            ),
+        ];
+
+        assert_eq!(
+            xrandr_script(
+                &virtual_monitors,
+                &["DP-1-XRPEX-960-0".to_string()],
+                &NameTemplate::default()
+            ),
+            "#!/bin/sh\n\
+             # Virtual monitors generated by xrpex, this script can be run repeatedly\n\
+             set -e\n\
+             \n\
+             # Split into the virtual monitors below\n\
+             xrandr --delmonitor DP-1-XRPEX-960-0 2>/dev/null || true\n\
+             \n\
+             # DP-1\n\
+             xrandr --setmonitor DP-1-XRPEX-0-0 960/300x1080/340+0+0 DP-1\n\
+             xrandr --setmonitor DP-1-XRPEX-960-0-XRPEX-0-0 960/300x540/340+960+0 DP-1\n"
+        );
+    }
+}
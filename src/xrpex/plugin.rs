@@ -0,0 +1,323 @@
+use std::ffi::{c_char, c_void, CStr, CString};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use libloading::Library;
+use rpex::HyperRectangle;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::monitor::{RpexMonitor, RpexMonitorManager, VirtualMonitor};
+use crate::registry::{into_dyn, BackendError, BackendRegistry};
+
+/// The symbol a backend library exports its [`BackendV1`] as.
+pub const SYMBOL: &[u8] = b"XRPEX_BACKEND_V1\0";
+
+/// The table of functions a backend library exports, so that backends can be written in any
+/// language that can export C functions.
+///
+/// Every call is given a JSON request like `{"method": "get_monitors"}`,
+/// `{"method": "delete_monitors", "names": [...]}` or
+/// `{"method": "set_virtual_monitors", "virtual_monitors": [...]}`, and answers with
+/// `{"ok": ...}` or `{"error": "..."}`. Strings are NUL-terminated UTF-8, and the ones the backend
+/// returns are given back to `free_string`.
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct BackendV1 {
+    /// Name to register the backend under, which must stay valid while the library is loaded
+    pub name: *const c_char,
+    /// Connects to the backend, returning null and setting the error on failure
+    pub open: unsafe extern "C" fn(error: *mut *mut c_char) -> *mut c_void,
+    pub call: unsafe extern "C" fn(backend: *mut c_void, request: *const c_char) -> *mut c_char,
+    pub free_string: unsafe extern "C" fn(string: *mut c_char),
+    pub close: unsafe extern "C" fn(backend: *mut c_void),
+}
+
+#[derive(Error, Debug)]
+pub enum PluginError {
+    #[error("unable to load backend library {0}: {1}")]
+    Load(PathBuf, libloading::Error),
+    #[error("backend library {0} has an invalid name")]
+    InvalidName(PathBuf),
+    #[error("unable to open backend: {0}")]
+    Open(String),
+    #[error("backend returned null")]
+    NullResponse,
+    #[error("invalid response from backend: {0}")]
+    InvalidResponse(#[from] serde_json::Error),
+    #[error("{0}")]
+    Backend(String),
+}
+
+#[derive(Serialize, Deserialize)]
+struct PluginMonitor {
+    name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    parent: Option<String>,
+    x: i32,
+    y: i32,
+    width: u32,
+    height: u32,
+    #[serde(default)]
+    width_mm: u32,
+    #[serde(default)]
+    height_mm: u32,
+}
+
+#[derive(Serialize)]
+#[serde(tag = "method")]
+enum Request<'a> {
+    #[serde(rename = "get_monitors")]
+    Get,
+    #[serde(rename = "delete_monitors")]
+    Delete { names: &'a [String] },
+    #[serde(rename = "set_virtual_monitors")]
+    Set {
+        virtual_monitors: Vec<PluginMonitor>,
+    },
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Response<T> {
+    Ok(T),
+    Error(String),
+}
+
+// Takes ownership of a string returned by the backend.
+unsafe fn take_string(table: &BackendV1, string: *mut c_char) -> Option<String> {
+    if string.is_null() {
+        return None;
+    }
+
+    let owned = CStr::from_ptr(string).to_string_lossy().into_owned();
+    (table.free_string)(string);
+
+    Some(owned)
+}
+
+/// A backend opened from a library, which is kept loaded for as long as the backend is.
+pub struct PluginManager {
+    table: BackendV1,
+    backend: *mut c_void,
+    _library: Option<Arc<Library>>,
+}
+
+impl PluginManager {
+    /// Opens the backend in `table`.
+    ///
+    /// # Safety
+    ///
+    /// The functions in `table` must follow the contract of [`BackendV1`], and stay valid for as
+    /// long as `library` is loaded.
+    pub unsafe fn open(
+        table: BackendV1,
+        library: Option<Arc<Library>>,
+    ) -> Result<PluginManager, PluginError> {
+        let mut error = std::ptr::null_mut();
+        let backend = (table.open)(&mut error);
+
+        if backend.is_null() {
+            let message = take_string(&table, error).unwrap_or_else(|| "unknown error".to_string());
+
+            return Err(PluginError::Open(message));
+        }
+
+        Ok(PluginManager {
+            table,
+            backend,
+            _library: library,
+        })
+    }
+
+    fn call<T: DeserializeOwned>(&mut self, request: Request) -> Result<T, PluginError> {
+        let request = CString::new(serde_json::to_string(&request)?)
+            .expect("JSON strings escape NUL characters");
+
+        // SAFETY: the backend is open, and `PluginManager::open` requires the table to be valid.
+        let response = unsafe {
+            let response = (self.table.call)(self.backend, request.as_ptr());
+            take_string(&self.table, response)
+        }
+        .ok_or(PluginError::NullResponse)?;
+
+        match serde_json::from_str(&response)? {
+            Response::Ok(value) => Ok(value),
+            Response::Error(message) => Err(PluginError::Backend(message)),
+        }
+    }
+}
+
+impl Drop for PluginManager {
+    fn drop(&mut self) {
+        // SAFETY: the backend is open, and is never used again.
+        unsafe { (self.table.close)(self.backend) }
+    }
+}
+
+impl RpexMonitorManager for PluginManager {
+    type ManagerError = PluginError;
+
+    fn get_monitors(&mut self) -> Result<Vec<RpexMonitor>, Self::ManagerError> {
+        Ok(self
+            .call::<Vec<PluginMonitor>>(Request::Get)?
+            .into_iter()
+            .map(|monitor| RpexMonitor {
+                name: monitor.name,
+                position: [monitor.x, monitor.y],
+                resolution: HyperRectangle {
+                    lengths: [monitor.width, monitor.height],
+                },
+                physical_size: [monitor.width_mm, monitor.height_mm],
+            })
+            .collect())
+    }
+
+    fn delete_monitors(&mut self, names: &[String]) -> Result<(), Self::ManagerError> {
+        self.call(Request::Delete { names })
+    }
+
+    fn set_virtual_monitors(
+        &mut self,
+        virtual_monitors: &[VirtualMonitor],
+    ) -> Result<(), Self::ManagerError> {
+        let virtual_monitors = virtual_monitors
+            .iter()
+            .map(|virtual_monitor| PluginMonitor {
+                name: virtual_monitor.name.clone(),
+                parent: Some(virtual_monitor.parent.clone()),
+                x: virtual_monitor.position[0],
+                y: virtual_monitor.position[1],
+                width: virtual_monitor.resolution.lengths[0],
+                height: virtual_monitor.resolution.lengths[1],
+                width_mm: virtual_monitor.physical_size[0],
+                height_mm: virtual_monitor.physical_size[1],
+            })
+            .collect();
+
+        self.call(Request::Set { virtual_monitors })
+    }
+}
+
+// The table only holds pointers into the library, which is loaded for as long as the opener that
+// holds it exists.
+struct SharedTable(BackendV1);
+
+unsafe impl Send for SharedTable {}
+unsafe impl Sync for SharedTable {}
+
+impl SharedTable {
+    fn get(&self) -> BackendV1 {
+        self.0
+    }
+}
+
+/// Loads the backend library at `path` into `registry`, returning the name it is registered
+/// under.
+///
+/// # Safety
+///
+/// Loading a library runs its initialisation code, and the library must export a [`BackendV1`]
+/// as [`SYMBOL`] that follows its contract.
+pub unsafe fn load(registry: &mut BackendRegistry, path: &Path) -> Result<String, PluginError> {
+    let library = Library::new(path).map_err(|e| PluginError::Load(path.to_path_buf(), e))?;
+    let table = **library
+        .get::<*const BackendV1>(SYMBOL)
+        .map_err(|e| PluginError::Load(path.to_path_buf(), e))?;
+
+    let name = CStr::from_ptr(table.name)
+        .to_str()
+        .map_err(|_| PluginError::InvalidName(path.to_path_buf()))?
+        .to_string();
+
+    let library = Arc::new(library);
+    let table = SharedTable(table);
+
+    registry.register(
+        name.clone(),
+        Box::new(move || {
+            PluginManager::open(table.get(), Some(library.clone()))
+                .map(into_dyn)
+                .map_err(BackendError::new)
+        }),
+    );
+
+    Ok(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+
+    static CALLS: Mutex<Vec<String>> = Mutex::new(vec![]);
+
+    unsafe extern "C" fn open(_: *mut *mut c_char) -> *mut c_void {
+        Box::into_raw(Box::new(0u8)).cast()
+    }
+
+    unsafe extern "C" fn call(_: *mut c_void, request: *const c_char) -> *mut c_char {
+        let request = CStr::from_ptr(request).to_str().unwrap().to_string();
+
+        let response = if request.contains("get_monitors") {
+            r#"{"ok": [{"name": "DP-1", "x": 0, "y": 0, "width": 1920, "height": 1080}]}"#
+        } else if request.contains("delete_monitors") {
+            r#"{"error": "unable to delete"}"#
+        } else {
+            r#"{"ok": null}"#
+        };
+
+        CALLS.lock().unwrap().push(request);
+
+        CString::new(response).unwrap().into_raw()
+    }
+
+    unsafe extern "C" fn free_string(string: *mut c_char) {
+        drop(CString::from_raw(string));
+    }
+
+    unsafe extern "C" fn close(backend: *mut c_void) {
+        drop(Box::from_raw(backend.cast::<u8>()));
+    }
+
+    const TABLE: BackendV1 = BackendV1 {
+        name: c"test".as_ptr(),
+        open,
+        call,
+        free_string,
+        close,
+    };
+
+    #[test]
+    fn we_can_call_backend_through_table() {
+        let mut manager = unsafe { PluginManager::open(TABLE, None) }.unwrap();
+
+        let monitors = manager.get_monitors().unwrap();
+        assert_eq!(monitors[0].name, "DP-1");
+        assert_eq!(monitors[0].resolution.lengths, [1920, 1080]);
+
+        assert!(matches!(
+            manager.delete_monitors(&["DP-1-XRPEX-0-0".to_string()]),
+            Err(PluginError::Backend(message)) if message == "unable to delete"
+        ));
+
+        manager
+            .set_virtual_monitors(&[VirtualMonitor {
+                name: "DP-1-XRPEX-0-0".to_string(),
+                parent: "DP-1".to_string(),
+                position: [0, 0],
+                resolution: HyperRectangle {
+                    lengths: [960, 1080],
+                },
+                physical_size: [0, 0],
+            }])
+            .unwrap();
+
+        assert_eq!(
+            CALLS.lock().unwrap().last().unwrap(),
+            r#"{"method":"set_virtual_monitors","virtual_monitors":[{"name":"DP-1-XRPEX-0-0","parent":"DP-1","x":0,"y":0,"width":960,"height":1080,"width_mm":0,"height_mm":0}]}"#
+        );
+    }
+}
@@ -0,0 +1,135 @@
+use crate::monitor::{RpexMonitor, VirtualMonitor};
+
+// Terminal cells are roughly twice as tall as they are wide.
+const CELL_ASPECT: u32 = 2;
+
+fn scale(value: u32, length: usize, total: u32) -> usize {
+    ((value as u64 * length as u64 + total as u64 / 2) / total.max(1) as u64) as usize
+}
+
+fn draw(grid: &mut [Vec<char>], x: usize, y: usize, c: char) {
+    let current = grid[y][x];
+
+    grid[y][x] = match (current, c) {
+        (' ', c) => c,
+        ('-', '|') | ('|', '-') | ('+', _) | (_, '+') => '+',
+        (current, _) => current,
+    };
+}
+
+// Draws the outline of each virtual monitor on its parent, numbered in partition order, followed
+// by a legend with their names and geometries.
+pub fn render_preview(
+    monitor: &RpexMonitor,
+    virtual_monitors: &[&VirtualMonitor],
+    width: usize,
+) -> String {
+    let [monitor_width, monitor_height] = monitor.resolution.lengths;
+    let height = scale(monitor_height, width, monitor_width * CELL_ASPECT).max(2);
+
+    let mut grid = vec![vec![' '; width + 1]; height + 1];
+
+    for (
+        index,
+        VirtualMonitor {
+            position,
+            resolution,
+            ..
+        },
+    ) in virtual_monitors.iter().enumerate()
+    {
+        // Virtual monitors are never left of or above their parent.
+        let [x0, y0] = [0, 1].map(|dim| position[dim].abs_diff(monitor.position[dim]));
+        let [x1, y1] = [x0 + resolution.lengths[0], y0 + resolution.lengths[1]];
+        let [x0, x1] = [x0, x1].map(|x| scale(x, width, monitor_width));
+        let [y0, y1] = [y0, y1].map(|y| scale(y, height, monitor_height));
+
+        for x in x0..=x1 {
+            draw(&mut grid, x, y0, '-');
+            draw(&mut grid, x, y1, '-');
+        }
+        for y in y0..=y1 {
+            draw(&mut grid, x0, y, '|');
+            draw(&mut grid, x1, y, '|');
+        }
+        for (x, y) in [(x0, y0), (x1, y0), (x0, y1), (x1, y1)] {
+            draw(&mut grid, x, y, '+');
+        }
+
+        let label = index.to_string();
+        let label_x = ((x0 + x1 + 1).saturating_sub(label.len()) / 2).max(x0 + 1);
+        for (offset, c) in label.chars().enumerate() {
+            if label_x + offset < x1 && y1 > y0 + 1 {
+                grid[(y0 + y1) / 2][label_x + offset] = c;
+            }
+        }
+    }
+
+    let mut preview = format!("{}: {monitor_width}x{monitor_height}\n", monitor.name);
+
+    for row in grid {
+        preview.push_str(row.into_iter().collect::<String>().trim_end());
+        preview.push('\n');
+    }
+
+    for (
+        index,
+        VirtualMonitor {
+            name,
+            position: [x, y],
+            resolution,
+            ..
+        },
+    ) in virtual_monitors.iter().enumerate()
+    {
+        let [width, height] = resolution.lengths;
+
+        preview.push_str(&format!("{index}: {name} {width}x{height}+{x}+{y}\n"));
+    }
+
+    preview
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use rpex::{HyperRectangle, Rpex};
+
+    use super::*;
+    use crate::ApplyOptions;
+
+    #[test]
+    fn we_can_render_preview() {
+        let monitor = RpexMonitor {
+            name: "DP-1".to_string(),
+            position: [1920, 0],
+            resolution: HyperRectangle {
+                lengths: [1920, 1080],
+            },
+            physical_size: [600, 340],
+        };
+        let options = ApplyOptions::default();
+
+        let virtual_monitors = monitor
+            .plan_virtual_monitors(Rpex::from_str("+:+").unwrap(), &options)
+            .unwrap();
+
+        assert_eq!(
+            render_preview(&monitor, &virtual_monitors.iter().collect::<Vec<_>>(), 24),
+            "DP-1: 1920x1080\n\
+             +-----------+-----------+\n\
+             |           |           |\n\
+             |     0     |     2     |\n\
+             |           |           |\n\
+             +-----------+-----------+\n\
+             |     1     |     3     |\n\
+             |           |           |\n\
+             +-----------+-----------+\n\
+             0: DP-1-XRPEX-0-0 960x540+1920+0\n\
+             1: DP-1-XRPEX-0-540 960x540+1920+540\n\
+             2: DP-1-XRPEX-960-0 960x540+2880+0\n\
+             3: DP-1-XRPEX-960-540 960x540+2880+540\n"
+        );
+    }
+}
@@ -0,0 +1,244 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::error::Error;
+
+use thiserror::Error;
+
+#[cfg(any(test, feature = "test-support"))]
+use crate::mock::MockMonitorManager;
+use crate::monitor::{RpexMonitor, RpexMonitorManager, VirtualMonitor};
+use crate::mutter::MutterManager;
+use crate::name_template::NameTemplate;
+use crate::sway::SwayManager;
+use crate::wlr::WlrManager;
+use crate::xrandr::XrandrManager;
+
+/// An error of any backend, so that backends can be used without knowing their types.
+#[derive(Error, Debug)]
+#[error("{0}")]
+pub struct BackendError(Box<dyn Error + Send + Sync>);
+
+impl BackendError {
+    pub fn new(e: impl Into<Box<dyn Error + Send + Sync>>) -> BackendError {
+        BackendError(e.into())
+    }
+}
+
+pub type DynMonitorManager = Box<dyn RpexMonitorManager<ManagerError = BackendError>>;
+
+pub type Opener = Box<dyn Fn() -> Result<DynMonitorManager, BackendError> + Send + Sync>;
+
+// Both wrappers forward every method, rather than only the required ones, so that a backend's own
+// implementation of the provided methods is kept.
+struct Boxed<M>(M);
+
+impl<M: RpexMonitorManager> RpexMonitorManager for Boxed<M>
+where
+    M::ManagerError: Send + Sync + 'static,
+{
+    type ManagerError = BackendError;
+
+    fn get_monitors(&mut self) -> Result<Vec<RpexMonitor>, Self::ManagerError> {
+        self.0.get_monitors().map_err(BackendError::new)
+    }
+
+    fn reset_rpex_monitors(
+        &mut self,
+        parent_name: &str,
+        name_template: &NameTemplate,
+    ) -> Result<Vec<RpexMonitor>, Self::ManagerError> {
+        self.0
+            .reset_rpex_monitors(parent_name, name_template)
+            .map_err(BackendError::new)
+    }
+
+    fn delete_monitors(&mut self, names: &[String]) -> Result<(), Self::ManagerError> {
+        self.0.delete_monitors(names).map_err(BackendError::new)
+    }
+
+    fn set_virtual_monitors(
+        &mut self,
+        virtual_monitors: &[VirtualMonitor],
+    ) -> Result<(), Self::ManagerError> {
+        self.0
+            .set_virtual_monitors(virtual_monitors)
+            .map_err(BackendError::new)
+    }
+}
+
+impl RpexMonitorManager for DynMonitorManager {
+    type ManagerError = BackendError;
+
+    fn get_monitors(&mut self) -> Result<Vec<RpexMonitor>, Self::ManagerError> {
+        (**self).get_monitors()
+    }
+
+    fn reset_rpex_monitors(
+        &mut self,
+        parent_name: &str,
+        name_template: &NameTemplate,
+    ) -> Result<Vec<RpexMonitor>, Self::ManagerError> {
+        (**self).reset_rpex_monitors(parent_name, name_template)
+    }
+
+    fn delete_monitors(&mut self, names: &[String]) -> Result<(), Self::ManagerError> {
+        (**self).delete_monitors(names)
+    }
+
+    fn set_virtual_monitors(
+        &mut self,
+        virtual_monitors: &[VirtualMonitor],
+    ) -> Result<(), Self::ManagerError> {
+        (**self).set_virtual_monitors(virtual_monitors)
+    }
+}
+
+pub fn into_dyn<M>(manager: M) -> DynMonitorManager
+where
+    M: RpexMonitorManager + 'static,
+    M::ManagerError: Send + Sync + 'static,
+{
+    Box::new(Boxed(manager))
+}
+
+/// Backends by name, so that which one to use is decided at runtime and backends can be added
+/// without changing xrpex, e.g. from a library with `--backend-library`.
+#[derive(Default)]
+pub struct BackendRegistry {
+    openers: BTreeMap<String, Opener>,
+    read_only: BTreeSet<String>,
+}
+
+impl BackendRegistry {
+    /// A registry of the backends built into xrpex, named like `--backend` names them.
+    pub fn builtin() -> BackendRegistry {
+        let mut registry = BackendRegistry::default();
+
+        registry.register_manager("xrandr", XrandrManager::open);
+        registry.register_manager("sway", SwayManager::open);
+        registry.mark_read_only("sway");
+        registry.register_manager("mutter", MutterManager::open);
+        registry.mark_read_only("mutter");
+        registry.register_manager("wlr", WlrManager::open);
+        registry.mark_read_only("wlr");
+        #[cfg(any(test, feature = "test-support"))]
+        registry.register_manager("mock", MockMonitorManager::open);
+
+        registry
+    }
+
+    /// Registers a backend under `name`, replacing any backend already registered under it.
+    pub fn register(&mut self, name: impl Into<String>, open: Opener) {
+        let name = name.into();
+
+        self.read_only.remove(&name);
+        self.openers.insert(name, open);
+    }
+
+    pub fn register_manager<M>(
+        &mut self,
+        name: impl Into<String>,
+        open: fn() -> Result<M, M::ManagerError>,
+    ) where
+        M: RpexMonitorManager + 'static,
+        M::ManagerError: Send + Sync + 'static,
+    {
+        self.register(
+            name,
+            Box::new(move || open().map(into_dyn).map_err(BackendError::new)),
+        );
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Opener> {
+        self.openers.get(name)
+    }
+
+    /// Marks the backend under `name` as only able to read monitors, so that commands which split
+    /// or reset them are refused before anything is changed. Registering `name` again clears this.
+    pub fn mark_read_only(&mut self, name: impl Into<String>) {
+        self.read_only.insert(name.into());
+    }
+
+    pub fn is_read_only(&self, name: &str) -> bool {
+        self.read_only.contains(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rpex::HyperRectangle;
+
+    use super::*;
+    use crate::mock::MockMonitor;
+
+    #[test]
+    fn we_can_open_registered_backends_by_name() {
+        let mut registry = BackendRegistry::builtin();
+
+        assert!(registry.get("xrandr").is_some());
+        assert!(registry.get("kwin").is_none());
+
+        registry.register_manager("dual", || {
+            Ok(MockMonitorManager::new(vec![
+                MockMonitor {
+                    name: "DP-1".to_string(),
+                    width: 1920,
+                    height: 1080,
+                    ..Default::default()
+                },
+                MockMonitor {
+                    name: "HDMI-1".to_string(),
+                    x: 1920,
+                    width: 1920,
+                    height: 1080,
+                    ..Default::default()
+                },
+            ]))
+        });
+
+        let mut manager = registry.get("dual").unwrap()().unwrap();
+
+        manager
+            .set_virtual_monitors(&[VirtualMonitor {
+                name: "DP-1-XRPEX-0-0".to_string(),
+                parent: "DP-1".to_string(),
+                position: [0, 0],
+                resolution: HyperRectangle {
+                    lengths: [960, 1080],
+                },
+                physical_size: [0, 0],
+            }])
+            .unwrap();
+
+        assert_eq!(manager.get_monitors().unwrap().len(), 3);
+        assert_eq!(
+            manager
+                .reset_rpex_monitors("DP-1", &NameTemplate::default())
+                .unwrap()
+                .len(),
+            1
+        );
+        assert!(manager
+            .set_virtual_monitors(&[VirtualMonitor {
+                name: "DP-2-XRPEX-0-0".to_string(),
+                parent: "DP-2".to_string(),
+                position: [0, 0],
+                resolution: HyperRectangle { lengths: [1, 1] },
+                physical_size: [0, 0],
+            }])
+            .is_err_and(|e| e.to_string() == "unable to find parent monitor DP-2"));
+    }
+
+    #[test]
+    fn we_can_mark_backends_read_only() {
+        let mut registry = BackendRegistry::builtin();
+
+        assert!(registry.is_read_only("sway"));
+        assert!(registry.is_read_only("mutter"));
+        assert!(registry.is_read_only("wlr"));
+        assert!(!registry.is_read_only("xrandr"));
+
+        registry.register_manager("sway", MockMonitorManager::open);
+
+        assert!(!registry.is_read_only("sway"));
+    }
+}
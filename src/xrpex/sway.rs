@@ -0,0 +1,133 @@
+use std::env;
+
+use rpex::HyperRectangle;
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::i3ipc::{I3Ipc, I3IpcError, IPC_GET_OUTPUTS};
+use crate::monitor::{RpexMonitor, RpexMonitorManager, VirtualMonitor};
+
+#[derive(Error, Debug)]
+pub enum SwayManagerError {
+    #[error("SWAYSOCK is not set, is sway running?")]
+    NoSocket,
+    #[error(transparent)]
+    Ipc(#[from] I3IpcError),
+    #[error("sway sent an invalid reply: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("sway cannot split output {0} into virtual monitors")]
+    Unsupported(String),
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct SwayRect {
+    x: i32,
+    y: i32,
+    width: u32,
+    height: u32,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct SwayOutput {
+    name: String,
+    active: bool,
+    rect: SwayRect,
+}
+
+pub struct SwayManager {
+    ipc: I3Ipc,
+}
+
+impl SwayManager {
+    pub fn open() -> Result<SwayManager, SwayManagerError> {
+        let path = env::var_os("SWAYSOCK").ok_or(SwayManagerError::NoSocket)?;
+
+        Ok(SwayManager {
+            ipc: I3Ipc::connect(path)?,
+        })
+    }
+}
+
+fn rpex_monitors_from_outputs(outputs: Vec<SwayOutput>) -> Vec<RpexMonitor> {
+    outputs
+        .into_iter()
+        .filter(|SwayOutput { active, .. }| *active)
+        .map(|SwayOutput { name, rect, .. }| RpexMonitor {
+            name,
+            position: [rect.x, rect.y],
+            resolution: HyperRectangle {
+                lengths: [rect.width, rect.height],
+            },
+            // sway doesn't report the physical size of outputs over IPC.
+            physical_size: [0, 0],
+        })
+        .collect()
+}
+
+impl RpexMonitorManager for SwayManager {
+    type ManagerError = SwayManagerError;
+
+    fn get_monitors(&mut self) -> Result<Vec<RpexMonitor>, Self::ManagerError> {
+        let reply = self.ipc.request(IPC_GET_OUTPUTS, &[])?;
+
+        Ok(rpex_monitors_from_outputs(serde_json::from_slice(&reply)?))
+    }
+
+    // sway has no virtual monitors, so the only deletion that can succeed is an empty one. Headless
+    // outputs could stand in for them, but sway doesn't show them on any physical output.
+    fn delete_monitors(&mut self, names: &[String]) -> Result<(), Self::ManagerError> {
+        match names.first() {
+            Some(name) => Err(SwayManagerError::Unsupported(name.clone())),
+            None => Ok(()),
+        }
+    }
+
+    fn set_virtual_monitors(
+        &mut self,
+        virtual_monitors: &[VirtualMonitor],
+    ) -> Result<(), Self::ManagerError> {
+        match virtual_monitors.first() {
+            Some(VirtualMonitor { parent, .. }) => {
+                Err(SwayManagerError::Unsupported(parent.clone()))
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn we_can_read_sway_outputs() {
+        let outputs = serde_json::from_str(
+            r#"[
+                {
+                    "name": "DP-1",
+                    "active": true,
+                    "make": "Dell Inc.",
+                    "rect": { "x": 1920, "y": 0, "width": 2560, "height": 1440 }
+                },
+                {
+                    "name": "HDMI-A-1",
+                    "active": false,
+                    "rect": { "x": 0, "y": 0, "width": 0, "height": 0 }
+                }
+            ]"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            rpex_monitors_from_outputs(outputs),
+            vec![RpexMonitor {
+                name: "DP-1".to_string(),
+                position: [1920, 0],
+                resolution: HyperRectangle {
+                    lengths: [2560, 1440]
+                },
+                physical_size: [0, 0],
+            }]
+        );
+    }
+}
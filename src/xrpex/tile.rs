@@ -0,0 +1,153 @@
+use std::io;
+use std::process::Command;
+
+use thiserror::Error;
+use tracing::info;
+
+use crate::monitor::VirtualMonitor;
+
+#[derive(Error, Debug)]
+pub enum TileError {
+    #[error("unable to run wmctrl: {0}")]
+    Io(#[from] io::Error),
+    #[error("wmctrl {0} failed")]
+    Failed(String),
+    #[error("wmctrl didn't report a current desktop")]
+    NoDesktop,
+}
+
+fn wmctrl(args: &[String]) -> Result<String, TileError> {
+    info!(args = %args.join(" "), "running wmctrl");
+
+    let output = Command::new("wmctrl").args(args).output()?;
+
+    if !output.status.success() {
+        return Err(TileError::Failed(args.join(" ")));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+// `wmctrl -d` marks the current desktop with `*` in its second column, and `wmctrl -l` lists the
+// desktop of each window in its second column, with -1 for windows shown on every desktop.
+fn windows_on_current_desktop(desktops: &str, windows: &str) -> Option<Vec<String>> {
+    let current = desktops.lines().find_map(|line| {
+        let mut columns = line.split_whitespace();
+        let desktop = columns.next()?;
+
+        (columns.next()? == "*").then_some(desktop)
+    })?;
+
+    Some(
+        windows
+            .lines()
+            .filter_map(|line| {
+                let mut columns = line.split_whitespace();
+                let id = columns.next()?;
+
+                (columns.next()? == current).then(|| id.to_string())
+            })
+            .collect(),
+    )
+}
+
+pub fn list_windows() -> Result<Vec<String>, TileError> {
+    let desktops = wmctrl(&["-d".to_string()])?;
+    let windows = wmctrl(&["-l".to_string()])?;
+
+    windows_on_current_desktop(&desktops, &windows).ok_or(TileError::NoDesktop)
+}
+
+// Maximized windows ignore the requested geometry, so they are unmaximized first.
+pub fn tile_commands(windows: &[String], virtual_monitors: &[VirtualMonitor]) -> Vec<Vec<String>> {
+    windows
+        .iter()
+        .zip(virtual_monitors)
+        .flat_map(
+            |(
+                window,
+                VirtualMonitor {
+                    position: [x, y],
+                    resolution,
+                    ..
+                },
+            )| {
+                let [width, height] = resolution.lengths;
+
+                [
+                    vec![
+                        "-b".to_string(),
+                        "remove,maximized_vert,maximized_horz".to_string(),
+                    ],
+                    vec!["-e".to_string(), format!("0,{x},{y},{width},{height}")],
+                ]
+                .map(|action| {
+                    ["-i", "-r", window]
+                        .map(str::to_string)
+                        .into_iter()
+                        .chain(action)
+                        .collect()
+                })
+            },
+        )
+        .collect()
+}
+
+pub fn run_tile_commands(commands: &[Vec<String>]) -> Result<(), TileError> {
+    for args in commands {
+        wmctrl(args)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use rpex::HyperRectangle;
+
+    use super::*;
+
+    #[test]
+    fn we_can_list_windows_on_current_desktop() {
+        let desktops = "0  - DG: 3840x1080  VP: 0,0  WA: 0,0 3840x1080  one\n\
+                        1  * DG: 3840x1080  VP: 0,0  WA: 0,0 3840x1080  two\n";
+        let windows = "0x03a00003  0 host Terminal\n\
+                       0x04200007  1 host Firefox\n\
+                       0x01e00002 -1 host Panel\n\
+                       0x05000004  1 host Editor\n";
+
+        assert_eq!(
+            windows_on_current_desktop(desktops, windows),
+            Some(vec!["0x04200007".to_string(), "0x05000004".to_string()])
+        );
+        assert_eq!(windows_on_current_desktop("", windows), None);
+    }
+
+    #[test]
+    fn we_can_build_tile_commands() {
+        let virtual_monitor = |position, lengths| VirtualMonitor {
+            name: String::new(),
+            parent: "DP-1".to_string(),
+            position,
+            resolution: HyperRectangle { lengths },
+            physical_size: [0, 0],
+        };
+        let virtual_monitors = [
+            virtual_monitor([1920, 0], [960, 1080]),
+            virtual_monitor([2880, 0], [960, 1080]),
+        ];
+
+        let commands = tile_commands(&["0x04200007".to_string()], &virtual_monitors)
+            .into_iter()
+            .map(|args| args.join(" "))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            commands,
+            vec![
+                "-i -r 0x04200007 -b remove,maximized_vert,maximized_horz",
+                "-i -r 0x04200007 -e 0,1920,0,960,1080",
+            ]
+        );
+    }
+}
@@ -0,0 +1,315 @@
+use std::collections::HashMap;
+
+use rpex::HyperRectangle;
+use thiserror::Error;
+use tracing::debug;
+use wayland_client::backend::ObjectId;
+use wayland_client::protocol::wl_registry::{self, WlRegistry};
+use wayland_client::{
+    event_created_child, ConnectError, Connection, Dispatch, DispatchError, EventQueue, Proxy,
+    QueueHandle, WEnum,
+};
+use wayland_protocols_wlr::output_management::v1::client::zwlr_output_head_v1::{
+    self, ZwlrOutputHeadV1,
+};
+use wayland_protocols_wlr::output_management::v1::client::zwlr_output_manager_v1::{
+    self, ZwlrOutputManagerV1,
+};
+use wayland_protocols_wlr::output_management::v1::client::zwlr_output_mode_v1::{
+    self, ZwlrOutputModeV1,
+};
+
+use crate::monitor::{RpexMonitor, RpexMonitorManager, VirtualMonitor};
+
+#[derive(Error, Debug)]
+pub enum WlrManagerError {
+    #[error("unable to connect to wayland compositor: {0}")]
+    Connect(#[from] ConnectError),
+    #[error("lost connection to wayland compositor: {0}")]
+    Dispatch(#[from] DispatchError),
+    #[error("compositor doesn't support wlr-output-management")]
+    NoOutputManager,
+    #[error("wlr-output-management cannot split head {0} into virtual monitors")]
+    Unsupported(String),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct WlrHead {
+    name: String,
+    enabled: bool,
+    position: [i32; 2],
+    physical_size: [i32; 2],
+    transform: u32,
+    scale: f64,
+    current_size: Option<[i32; 2]>,
+}
+
+impl Default for WlrHead {
+    fn default() -> Self {
+        WlrHead {
+            name: String::new(),
+            enabled: false,
+            position: [0, 0],
+            physical_size: [0, 0],
+            transform: 0,
+            scale: 1.0,
+            current_size: None,
+        }
+    }
+}
+
+#[derive(Default)]
+struct WlrState {
+    manager: Option<ZwlrOutputManagerV1>,
+    heads: Vec<(ObjectId, WlrHead)>,
+    mode_sizes: HashMap<ObjectId, [i32; 2]>,
+    done: bool,
+}
+
+impl WlrState {
+    fn head(&mut self, id: ObjectId) -> Option<&mut WlrHead> {
+        self.heads
+            .iter_mut()
+            .find(|(head_id, _)| *head_id == id)
+            .map(|(_, head)| head)
+    }
+}
+
+impl Dispatch<WlRegistry, ()> for WlrState {
+    fn event(
+        state: &mut Self,
+        registry: &WlRegistry,
+        event: wl_registry::Event,
+        _: &(),
+        _: &Connection,
+        qh: &QueueHandle<Self>,
+    ) {
+        if let wl_registry::Event::Global {
+            name,
+            interface,
+            version,
+        } = event
+        {
+            if interface == ZwlrOutputManagerV1::interface().name {
+                state.manager = Some(registry.bind(name, version.min(2), qh, ()));
+            }
+        }
+    }
+}
+
+impl Dispatch<ZwlrOutputManagerV1, ()> for WlrState {
+    fn event(
+        state: &mut Self,
+        _: &ZwlrOutputManagerV1,
+        event: zwlr_output_manager_v1::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        match event {
+            zwlr_output_manager_v1::Event::Head { head } => {
+                state.heads.push((head.id(), WlrHead::default()));
+            }
+            zwlr_output_manager_v1::Event::Done { .. } => state.done = true,
+            _ => {}
+        }
+    }
+
+    event_created_child!(WlrState, ZwlrOutputManagerV1, [
+        zwlr_output_manager_v1::EVT_HEAD_OPCODE => (ZwlrOutputHeadV1, ()),
+    ]);
+}
+
+impl Dispatch<ZwlrOutputHeadV1, ()> for WlrState {
+    fn event(
+        state: &mut Self,
+        head: &ZwlrOutputHeadV1,
+        event: zwlr_output_head_v1::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        if let zwlr_output_head_v1::Event::Finished = event {
+            state.heads.retain(|(id, _)| *id != head.id());
+            return;
+        }
+
+        // Modes are announced before the head refers to one of them as its current mode.
+        let current_size = match &event {
+            zwlr_output_head_v1::Event::CurrentMode { mode } => {
+                state.mode_sizes.get(&mode.id()).copied()
+            }
+            _ => None,
+        };
+
+        let Some(wlr_head) = state.head(head.id()) else {
+            return;
+        };
+
+        match event {
+            zwlr_output_head_v1::Event::Name { name } => wlr_head.name = name,
+            zwlr_output_head_v1::Event::Enabled { enabled } => wlr_head.enabled = enabled != 0,
+            zwlr_output_head_v1::Event::Position { x, y } => wlr_head.position = [x, y],
+            zwlr_output_head_v1::Event::PhysicalSize { width, height } => {
+                wlr_head.physical_size = [width, height]
+            }
+            zwlr_output_head_v1::Event::Transform {
+                transform: WEnum::Value(transform),
+            } => wlr_head.transform = transform.into(),
+            zwlr_output_head_v1::Event::Scale { scale } => wlr_head.scale = scale,
+            zwlr_output_head_v1::Event::CurrentMode { .. } => wlr_head.current_size = current_size,
+            _ => {}
+        }
+    }
+
+    event_created_child!(WlrState, ZwlrOutputHeadV1, [
+        zwlr_output_head_v1::EVT_MODE_OPCODE => (ZwlrOutputModeV1, ()),
+    ]);
+}
+
+impl Dispatch<ZwlrOutputModeV1, ()> for WlrState {
+    fn event(
+        state: &mut Self,
+        mode: &ZwlrOutputModeV1,
+        event: zwlr_output_mode_v1::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        match event {
+            zwlr_output_mode_v1::Event::Size { width, height } => {
+                state.mode_sizes.insert(mode.id(), [width, height]);
+            }
+            zwlr_output_mode_v1::Event::Finished => {
+                state.mode_sizes.remove(&mode.id());
+            }
+            _ => {}
+        }
+    }
+}
+
+pub struct WlrManager {
+    event_queue: EventQueue<WlrState>,
+    state: WlrState,
+}
+
+impl WlrManager {
+    pub fn open() -> Result<WlrManager, WlrManagerError> {
+        let connection = Connection::connect_to_env()?;
+        let mut event_queue = connection.new_event_queue();
+
+        connection.display().get_registry(&event_queue.handle(), ());
+
+        let mut state = WlrState::default();
+        event_queue.roundtrip(&mut state)?;
+
+        if state.manager.is_none() {
+            return Err(WlrManagerError::NoOutputManager);
+        }
+
+        while !state.done {
+            event_queue.blocking_dispatch(&mut state)?;
+        }
+
+        debug!(heads = state.heads.len(), "received output heads");
+
+        Ok(WlrManager { event_queue, state })
+    }
+}
+
+// Positions are in the compositor's logical space, so sizes are scaled to match them.
+fn rpex_monitor_from_head(head: &WlrHead) -> Option<RpexMonitor> {
+    if !head.enabled {
+        return None;
+    }
+
+    let mut lengths = head
+        .current_size?
+        .map(|length| (length as f64 / head.scale).round() as u32);
+    let mut physical_size = head.physical_size.map(|length| length.max(0) as u32);
+
+    // Odd transforms rotate the head by 90 or 270 degrees.
+    if head.transform % 2 == 1 {
+        lengths.reverse();
+        physical_size.reverse();
+    }
+
+    Some(RpexMonitor {
+        name: head.name.clone(),
+        position: head.position,
+        resolution: HyperRectangle { lengths },
+        physical_size,
+    })
+}
+
+impl RpexMonitorManager for WlrManager {
+    type ManagerError = WlrManagerError;
+
+    fn get_monitors(&mut self) -> Result<Vec<RpexMonitor>, Self::ManagerError> {
+        self.event_queue.roundtrip(&mut self.state)?;
+
+        Ok(self
+            .state
+            .heads
+            .iter()
+            .filter_map(|(_, head)| rpex_monitor_from_head(head))
+            .collect())
+    }
+
+    // wlr-output-management only configures whole heads and can't add outputs to split them into.
+    fn delete_monitors(&mut self, names: &[String]) -> Result<(), Self::ManagerError> {
+        match names.first() {
+            Some(name) => Err(WlrManagerError::Unsupported(name.clone())),
+            None => Ok(()),
+        }
+    }
+
+    fn set_virtual_monitors(
+        &mut self,
+        virtual_monitors: &[VirtualMonitor],
+    ) -> Result<(), Self::ManagerError> {
+        match virtual_monitors.first() {
+            Some(VirtualMonitor { parent, .. }) => {
+                Err(WlrManagerError::Unsupported(parent.clone()))
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn we_can_read_wlr_heads() {
+        let head = WlrHead {
+            name: "eDP-1".to_string(),
+            enabled: true,
+            position: [0, 0],
+            physical_size: [310, 170],
+            transform: 0,
+            scale: 1.5,
+            current_size: Some([2880, 1620]),
+        };
+
+        assert_eq!(
+            rpex_monitor_from_head(&head),
+            Some(RpexMonitor {
+                name: "eDP-1".to_string(),
+                position: [0, 0],
+                resolution: HyperRectangle {
+                    lengths: [1920, 1080]
+                },
+                physical_size: [310, 170],
+            })
+        );
+        assert_eq!(
+            rpex_monitor_from_head(&WlrHead {
+                enabled: false,
+                ..head
+            }),
+            None
+        );
+    }
+}
@@ -0,0 +1,256 @@
+use std::collections::HashMap;
+
+use rpex::HyperRectangle;
+use thiserror::Error;
+use tracing::info;
+use x11rb::connection::Connection;
+use x11rb::errors::{ConnectError, ConnectionError, ReplyError};
+use x11rb::protocol::randr::{ConnectionExt as _, MonitorInfo};
+use x11rb::protocol::xproto::{ConnectionExt as _, Window};
+use x11rb::rust_connection::RustConnection;
+
+use crate::monitor::{RpexMonitor, RpexMonitorManager, VirtualMonitor};
+
+#[derive(Error, Debug)]
+pub enum XrandrManagerError {
+    #[error("unable to connect to x server: {0}")]
+    Connect(#[from] ConnectError),
+    #[error("lost connection to x server: {0}")]
+    Connection(#[from] ConnectionError),
+    #[error("x server returned an error: {0}")]
+    Reply(#[from] ReplyError),
+    #[error("unable to find parent monitor {0}")]
+    NoParent(String),
+    #[error("request to {0} failed: {1}")]
+    Request(String, ReplyError),
+    #[error("geometry {1} of monitor {0} doesn't fit in randr's 16-bit coordinates")]
+    OutOfRange(String, String),
+}
+
+pub struct XrandrManager {
+    connection: RustConnection,
+    root: Window,
+}
+
+impl XrandrManager {
+    pub fn open() -> Result<XrandrManager, XrandrManagerError> {
+        let (connection, screen_num) = x11rb::connect(None)?;
+        let root = connection.setup().roots[screen_num].root;
+
+        Ok(XrandrManager { connection, root })
+    }
+
+    pub fn randr_version(&self) -> Result<(u32, u32), XrandrManagerError> {
+        let reply = self.connection.randr_query_version(1, 6)?.reply()?;
+
+        Ok((reply.major_version, reply.minor_version))
+    }
+
+    fn get_monitor_infos(&self) -> Result<Vec<(String, MonitorInfo)>, XrandrManagerError> {
+        self.connection
+            .randr_get_monitors(self.root, true)?
+            .reply()?
+            .monitors
+            .into_iter()
+            .map(|monitor_info| {
+                let name = self
+                    .connection
+                    .get_atom_name(monitor_info.name)?
+                    .reply()?
+                    .name;
+
+                Ok((String::from_utf8_lossy(&name).into_owned(), monitor_info))
+            })
+            .collect()
+    }
+}
+
+fn rpex_monitor_from_info(name: String, monitor_info: &MonitorInfo) -> RpexMonitor {
+    RpexMonitor {
+        name,
+        position: [monitor_info.x, monitor_info.y].map(i32::from),
+        resolution: HyperRectangle {
+            lengths: [monitor_info.width, monitor_info.height].map(u32::from),
+        },
+        physical_size: [
+            monitor_info.width_in_millimeters,
+            monitor_info.height_in_millimeters,
+        ],
+    }
+}
+
+// Every geometry is checked before any monitor is set, so that none are set when one doesn't fit.
+fn randr_geometry(
+    VirtualMonitor {
+        name,
+        position: [x, y],
+        resolution,
+        ..
+    }: &VirtualMonitor,
+) -> Result<(i16, i16, u16, u16), XrandrManagerError> {
+    let [width, height] = resolution.lengths;
+
+    match (
+        i16::try_from(*x),
+        i16::try_from(*y),
+        u16::try_from(width),
+        u16::try_from(height),
+    ) {
+        (Ok(x), Ok(y), Ok(width), Ok(height)) => Ok((x, y, width, height)),
+        _ => Err(XrandrManagerError::OutOfRange(
+            name.clone(),
+            format!("{width}x{height}+{x}+{y}"),
+        )),
+    }
+}
+
+impl RpexMonitorManager for XrandrManager {
+    type ManagerError = XrandrManagerError;
+
+    fn get_monitors(&mut self) -> Result<Vec<RpexMonitor>, Self::ManagerError> {
+        Ok(self
+            .get_monitor_infos()?
+            .into_iter()
+            .map(|(name, monitor_info)| rpex_monitor_from_info(name, &monitor_info))
+            .collect())
+    }
+
+    fn delete_monitors(&mut self, names: &[String]) -> Result<(), Self::ManagerError> {
+        for (name, monitor_info) in self.get_monitor_infos()? {
+            if names.contains(&name) {
+                info!(name, "deleting randr monitor");
+
+                self.connection
+                    .randr_delete_monitor(self.root, monitor_info.name)?
+                    .check()
+                    .map_err(|e| {
+                        XrandrManagerError::Request(format!("delete monitor {name}"), e)
+                    })?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn set_virtual_monitors(
+        &mut self,
+        virtual_monitors: &[VirtualMonitor],
+    ) -> Result<(), Self::ManagerError> {
+        let parent_outputs = self
+            .get_monitor_infos()?
+            .into_iter()
+            .map(|(name, monitor_info)| (name, monitor_info.outputs))
+            .collect::<HashMap<_, _>>();
+        let geometries = virtual_monitors
+            .iter()
+            .map(randr_geometry)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        for (
+            VirtualMonitor {
+                name,
+                parent,
+                physical_size: [width_in_millimeters, height_in_millimeters],
+                ..
+            },
+            (x, y, width, height),
+        ) in virtual_monitors.iter().zip(geometries)
+        {
+            let outputs = parent_outputs
+                .get(parent)
+                .ok_or_else(|| XrandrManagerError::NoParent(parent.clone()))?;
+
+            let geometry = format!("{width}x{height}+{x}+{y}");
+
+            let atom = self
+                .connection
+                .intern_atom(false, name.as_bytes())?
+                .reply()?
+                .atom;
+
+            info!(name, parent, geometry, "setting randr monitor");
+
+            let monitor_info = MonitorInfo {
+                name: atom,
+                primary: false,
+                automatic: false,
+                x,
+                y,
+                width,
+                height,
+                width_in_millimeters: *width_in_millimeters,
+                height_in_millimeters: *height_in_millimeters,
+                outputs: outputs.clone(),
+            };
+
+            self.connection
+                .randr_set_monitor(self.root, monitor_info)?
+                .check()
+                .map_err(|e| {
+                    XrandrManagerError::Request(
+                        format!("set monitor {name} to {geometry} on {parent}"),
+                        e,
+                    )
+                })?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn we_can_read_monitors_left_of_the_origin() {
+        let monitor_info = MonitorInfo {
+            name: 0,
+            primary: false,
+            automatic: true,
+            x: -1920,
+            y: -120,
+            width: 1920,
+            height: 1080,
+            width_in_millimeters: 530,
+            height_in_millimeters: 300,
+            outputs: vec![],
+        };
+
+        assert_eq!(
+            rpex_monitor_from_info("HDMI-1".to_string(), &monitor_info),
+            RpexMonitor {
+                name: "HDMI-1".to_string(),
+                position: [-1920, -120],
+                resolution: HyperRectangle {
+                    lengths: [1920, 1080]
+                },
+                physical_size: [530, 300],
+            }
+        );
+    }
+
+    #[test]
+    fn we_cannot_set_monitors_beyond_randr_coordinates() {
+        let virtual_monitor = |position, lengths| VirtualMonitor {
+            name: "DP-1-XRPEX-0-0".to_string(),
+            parent: "DP-1".to_string(),
+            position,
+            resolution: HyperRectangle { lengths },
+            physical_size: [0, 0],
+        };
+
+        assert_eq!(
+            randr_geometry(&virtual_monitor([-1920, 0], [960, 1080])).unwrap(),
+            (-1920, 0, 960, 1080)
+        );
+        assert!(matches!(
+            randr_geometry(&virtual_monitor([40000, 0], [960, 1080])),
+            Err(XrandrManagerError::OutOfRange(_, geometry)) if geometry == "960x1080+40000+0"
+        ));
+        assert!(matches!(
+            randr_geometry(&virtual_monitor([0, 0], [70000, 1080])),
+            Err(XrandrManagerError::OutOfRange(_, _))
+        ));
+    }
+}